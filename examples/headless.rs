@@ -0,0 +1,40 @@
+use bevy::{
+    app::ScheduleRunnerSettings, asset::AssetPlugin, log::LogPlugin, prelude::*, utils::Duration,
+};
+use bevy_tiled_prototype::{Object, ObjectReadyEvent, TiledConfig};
+
+// this example demonstrates loading a map without rendering, e.g. on a dedicated server.
+// Object entities are spawned with their transforms, but no meshes or sprites are created.
+
+fn main() {
+    App::build()
+        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin)
+        .add_plugin(AssetPlugin)
+        .insert_resource(TiledConfig {
+            headless: true,
+            ..Default::default()
+        })
+        .add_plugin(bevy_tiled_prototype::TiledMapPlugin::default())
+        .add_startup_system(setup.system())
+        .add_system(print_objects.system())
+        .run();
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn_bundle(bevy_tiled_prototype::TiledMapBundle {
+        map_asset: asset_server.load("ortho-map.tmx"),
+        ..Default::default()
+    });
+}
+
+fn print_objects(mut events: EventReader<ObjectReadyEvent>, query: Query<(&Object, &Transform)>) {
+    for event in events.iter() {
+        if let Ok((object, transform)) = query.get(event.entity) {
+            println!("{:?} at {:?}", object.name, transform.translation);
+        }
+    }
+}
@@ -4,7 +4,7 @@ use bevy_tiled_prototype::TiledMapCenter;
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
-        .add_plugin(bevy_tiled_prototype::TiledMapPlugin)
+        .add_plugin(bevy_tiled_prototype::TiledMapPlugin::default())
         .add_startup_system(setup.system())
         .add_system(camera_movement.system())
         .run();
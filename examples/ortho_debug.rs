@@ -8,7 +8,7 @@ const SCALE: f32 = 2.0;
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
-        .add_plugin(bevy_tiled_prototype::TiledMapPlugin)
+        .add_plugin(bevy_tiled_prototype::TiledMapPlugin::default())
         .add_system(bevy::input::system::exit_on_esc_system.system())
         .add_system(toggle_debug.system())
         .add_startup_system(setup.system())
@@ -9,7 +9,7 @@ const SCALE: f32 = 0.25;
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
-        .add_plugin(bevy_tiled_prototype::TiledMapPlugin)
+        .add_plugin(bevy_tiled_prototype::TiledMapPlugin::default())
         .add_system(bevy::input::system::exit_on_esc_system.system())
         .add_system(move_parent_entity.system())
         .add_startup_system(setup.system())
@@ -0,0 +1,200 @@
+use crate::{map::tile_quad_uvs, TiledConfig};
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+
+/// Frames of an animated tile, as set in Tiled's tile animation editor. Inserted on tile
+/// objects whose tile is animated, see `animate_tile_objects`, and kept for the animated
+/// tiles of tile layers in `AnimatedTile`.
+#[derive(Debug, Clone)]
+pub struct TileAnimation {
+    /// Gid of the animated tile.
+    pub gid: u32,
+    /// Sprite indices of the frames with their durations in seconds.
+    pub frames: Vec<(u32, f32)>,
+    /// Index of the frame shown, in `frames`.
+    pub frame_index: usize,
+}
+
+impl TileAnimation {
+    /// The animation of a tile by its gid, if it is animated in its tileset.
+    pub fn from_tiled(map: &tiled::Map, gid: u32) -> Option<Self> {
+        let tileset = map.get_tileset_by_gid(gid)?;
+        let id = gid - tileset.first_gid;
+        let frames: Vec<(u32, f32)> = tileset
+            .tiles
+            .iter()
+            .find(|tile| tile.id == id)?
+            .animation
+            .as_ref()?
+            .iter()
+            .map(|frame| (frame.tile_id, frame.duration as f32 / 1000.0))
+            .collect();
+        if frames.is_empty() {
+            return None;
+        }
+        Some(Self {
+            gid,
+            frames,
+            frame_index: 0,
+        })
+    }
+
+    /// Index of the frame shown at a time of the `TileAnimationClock`, in `frames`.
+    pub fn frame_at(&self, seconds: f64) -> usize {
+        let total: f64 = self
+            .frames
+            .iter()
+            .map(|(_, duration)| duration.max(0.0) as f64)
+            .sum();
+        // frames without a duration would never advance past each other
+        if total <= 0.0 {
+            return 0;
+        }
+        let mut time = seconds.rem_euclid(total);
+        for (index, (_, duration)) in self.frames.iter().enumerate() {
+            let duration = duration.max(0.0) as f64;
+            if time < duration {
+                return index;
+            }
+            time -= duration;
+        }
+        self.frames.len() - 1
+    }
+}
+
+/// The time all tile animations are played at, so animations of the same tile stay in step
+/// wherever and whenever they were spawned. Sprites of your own can follow it with
+/// `TileAnimation::frame_at`.
+#[derive(Debug, Clone)]
+pub struct TileAnimationClock {
+    /// Seconds the clock has run.
+    pub elapsed: f64,
+    /// How fast the clock runs, e.g. 0.0 to pause all tile animations.
+    pub speed: f32,
+}
+
+impl Default for TileAnimationClock {
+    fn default() -> Self {
+        Self {
+            elapsed: 0.0,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Advances the `TileAnimationClock`, before any animation is updated in the frame.
+pub fn advance_tile_animation_clock(time: Res<Time>, mut clock: ResMut<TileAnimationClock>) {
+    clock.elapsed += (time.delta_seconds() * clock.speed) as f64;
+}
+
+/// Sent when an animated tile object shows another frame, if
+/// `TiledConfig::tile_animation_events` is set, e.g. to sync light flicker to a torch.
+#[derive(Debug, Clone)]
+pub struct TileAnimationFrameEvent {
+    pub entity: Entity,
+    /// Gid of the animated tile.
+    pub gid: u32,
+    /// Index of the new frame in `TileAnimation::frames`.
+    pub frame_index: usize,
+}
+
+/// Shows the frame of animated tile objects at the time of the `TileAnimationClock`.
+pub fn animate_tile_objects(
+    clock: Res<TileAnimationClock>,
+    config: Res<TiledConfig>,
+    mut animations: Query<(Entity, &mut TileAnimation, &mut TextureAtlasSprite)>,
+    mut frame_events: EventWriter<TileAnimationFrameEvent>,
+) {
+    for (entity, mut animation, mut sprite) in animations.iter_mut() {
+        let frame_index = animation.frame_at(clock.elapsed);
+        // new sprites show the animated tile itself until their first frame is set
+        if frame_index == animation.frame_index && sprite.index == animation.frames[frame_index].0 {
+            continue;
+        }
+        let changed = frame_index != animation.frame_index;
+        animation.frame_index = frame_index;
+        sprite.index = animation.frames[animation.frame_index].0;
+        if changed && config.tile_animation_events {
+            frame_events.send(TileAnimationFrameEvent {
+                entity,
+                gid: animation.gid,
+                frame_index: animation.frame_index,
+            });
+        }
+    }
+}
+
+/// A tile of a tile layer chunk that is animated in its tileset, see `Chunk::animated_tiles`.
+#[derive(Debug, Clone)]
+pub struct AnimatedTile {
+    /// Index of the tile in `Chunk::tiles`, which is also its quad in the chunk's mesh.
+    pub index: usize,
+    pub animation: TileAnimation,
+    /// UVs of each frame in `animation.frames`, like `Tile::uv`.
+    pub frame_uvs: Vec<Vec4>,
+    pub flip_d: bool,
+    pub flip_h: bool,
+    pub flip_v: bool,
+}
+
+impl AnimatedTile {
+    /// UVs of the frame shown.
+    pub fn frame_uv(&self) -> Option<Vec4> {
+        self.frame_uvs.get(self.animation.frame_index).copied()
+    }
+}
+
+/// The animated tiles of a tile layer chunk, inserted on chunks with animated tiles.
+/// `animate_tile_layers` writes the UVs of their frames into the chunk's mesh.
+#[derive(Debug, Clone, Default)]
+pub struct AnimatedChunk {
+    pub tiles: Vec<AnimatedTile>,
+    // mesh the frames were last written to, replaced meshes show the first frame again
+    mesh: Option<Handle<Mesh>>,
+}
+
+impl AnimatedChunk {
+    pub fn new(tiles: Vec<AnimatedTile>) -> Self {
+        Self { tiles, mesh: None }
+    }
+}
+
+/// Shows the frame of animated tiles in tile layers at the time of the `TileAnimationClock`,
+/// so the same tile stays in step across chunks, layers and maps. Chunks sharing a mesh are
+/// updated together.
+pub fn animate_tile_layers(
+    clock: Res<TileAnimationClock>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut chunks: Query<(&Handle<Mesh>, &mut AnimatedChunk)>,
+) {
+    for (mesh_handle, mut chunk) in chunks.iter_mut() {
+        let chunk = &mut *chunk;
+        let new_mesh = chunk.mesh.as_ref() != Some(mesh_handle);
+        let changed = chunk
+            .tiles
+            .iter()
+            .any(|tile| tile.animation.frame_at(clock.elapsed) != tile.animation.frame_index);
+        if !new_mesh && !changed {
+            continue;
+        }
+        // meshes unloaded by `max_loaded_chunks` are updated once they are rebuilt
+        let uvs = match meshes
+            .get_mut(mesh_handle)
+            .and_then(|mesh| mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0))
+        {
+            Some(VertexAttributeValues::Float2(uvs)) => uvs,
+            _ => continue,
+        };
+        for tile in chunk.tiles.iter_mut() {
+            let frame_index = tile.animation.frame_at(clock.elapsed);
+            if frame_index == tile.animation.frame_index && !new_mesh {
+                continue;
+            }
+            tile.animation.frame_index = frame_index;
+            let start = tile.index * 4;
+            if let (Some(quad), Some(uv)) = (uvs.get_mut(start..start + 4), tile.frame_uv()) {
+                quad.copy_from_slice(&tile_quad_uvs(uv, tile.flip_d, tile.flip_h, tile.flip_v));
+            }
+        }
+        chunk.mesh = Some(mesh_handle.clone_weak());
+    }
+}
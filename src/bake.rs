@@ -0,0 +1,220 @@
+use crate::{
+    streaming::map_chunk, Bounds, Chunk, ChunkLocation, Map, PropertiesExt, Tile, TiledConfig,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        pipeline::PrimitiveTopology,
+        texture::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+/// Marks chunk entities whose tiles were baked into a single texture, see `bake_static_layers`.
+/// Baked chunks are not unloaded by `TiledConfig::max_loaded_chunks`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BakedChunk;
+
+/// Tile layer property that bakes the layer's chunks into textures when set to `true`.
+pub const BAKE_PROPERTY: &str = "bake";
+
+/// Bakes the chunks of tile layers with a `bake` property set to `true` into one texture each,
+/// once their tileset image is loaded, and replaces their tile quads with a single quad.
+/// Baked layers are drawn the same, but can't be edited at runtime without respawning.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn bake_static_layers(
+    mut commands: Commands,
+    config: Res<TiledConfig>,
+    maps: Res<Assets<Map>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut textures: ResMut<Assets<Texture>>,
+    new_chunks: Query<(Entity, &ChunkLocation, &Handle<Map>), Added<ChunkLocation>>,
+    mut chunks: Query<(
+        &ChunkLocation,
+        &Handle<Map>,
+        &mut Handle<Mesh>,
+        &mut Handle<ColorMaterial>,
+    )>,
+    // chunks of baked layers waiting for their tileset image
+    mut pending: Local<Vec<Entity>>,
+) {
+    for (entity, location, map_handle) in new_chunks.iter() {
+        let bake = maps
+            .get(map_handle)
+            .and_then(|map| map.layers.get(location.layer_id))
+            .and_then(|layer| layer.properties.get_bool(BAKE_PROPERTY))
+            .unwrap_or(false);
+        if bake {
+            pending.push(entity);
+        }
+    }
+
+    pending.retain(|entity| {
+        let (location, map_handle, mut mesh, mut material) = match chunks.get_mut(*entity) {
+            Ok(chunk) => chunk,
+            // despawned before it could be baked
+            Err(_) => return false,
+        };
+        let chunk = match maps
+            .get(map_handle)
+            .and_then(|map| map_chunk(map, location))
+        {
+            Some(chunk) => chunk,
+            None => return false,
+        };
+        let tileset_image = match materials
+            .get(&*material)
+            .and_then(|material| material.texture.as_ref())
+            .and_then(|texture| textures.get(texture))
+        {
+            Some(texture) => texture,
+            None => return true,
+        };
+        let (baked, bounds) = match bake_chunk(chunk, tileset_image) {
+            Some(baked) => baked,
+            None => return false,
+        };
+
+        let mut baked_texture = baked;
+        baked_texture.sampler.mag_filter = config.texture_filter;
+        baked_texture.sampler.min_filter = config.texture_filter;
+        *material = materials.add(textures.add(baked_texture).into());
+        *mesh = meshes.add(quad_mesh(bounds));
+        commands.entity(*entity).insert(BakedChunk);
+        false
+    });
+}
+
+// draws a chunk's tiles in order into a texture covering their bounds, which are returned
+// as min and max corners relative to the map
+fn bake_chunk(chunk: &Chunk, tileset_image: &Texture) -> Option<(Texture, Vec4)> {
+    match tileset_image.format {
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => {}
+        _ => return None,
+    }
+    let bounds = chunk.bounds()?;
+    let width = bounds.size().x.ceil() as usize;
+    let height = bounds.size().y.ceil() as usize;
+    let mut data = vec![0u8; width * height * 4];
+    draw_tiles(
+        chunk.tiles.iter(),
+        tileset_image,
+        bounds.min,
+        bounds.max.y,
+        1.0,
+        (width, height),
+        &mut data,
+    );
+
+    let texture = Texture::new(
+        Extent3d::new(width as u32, height as u32, 1),
+        TextureDimension::D2,
+        data,
+        tileset_image.format,
+    );
+    let Bounds { min, max } = bounds;
+    Some((texture, Vec4::new(min.x, min.y, max.x, max.y)))
+}
+
+// blends tiles onto an RGBA8 image of `size` pixels whose left edge and top are at `min.x`
+// and `top` relative to the map, with `scale` image pixels per map pixel
+pub(crate) fn draw_tiles<'a>(
+    tiles: impl Iterator<Item = &'a Tile>,
+    tileset_image: &Texture,
+    min: Vec2,
+    top: f32,
+    scale: f32,
+    size: (usize, usize),
+    data: &mut [u8],
+) {
+    let (width, height) = size;
+    let image_width = tileset_image.size.width as usize;
+    let image_height = tileset_image.size.height as usize;
+
+    for tile in tiles {
+        // texture coordinates at the bottom left, top left, top right and bottom right corners,
+        // flips permute them the same way as in `Chunk::build_mesh`
+        let mut corners = [
+            Vec2::new(tile.uv.x, tile.uv.w),
+            Vec2::new(tile.uv.x, tile.uv.y),
+            Vec2::new(tile.uv.z, tile.uv.y),
+            Vec2::new(tile.uv.z, tile.uv.w),
+        ];
+        if tile.flip_d {
+            corners.swap(0, 2);
+        }
+        if tile.flip_h {
+            corners.reverse();
+        }
+        if tile.flip_v {
+            corners.reverse();
+            corners.swap(0, 2);
+            corners.swap(1, 3);
+        }
+
+        let tile_min = Vec2::new(tile.vertex.x, tile.vertex.y);
+        let tile_size = Vec2::new(tile.vertex.z, tile.vertex.w) - tile_min;
+        let start_x = ((tile.vertex.x - min.x) * scale).floor().max(0.0) as usize;
+        let end_x = (((tile.vertex.z - min.x) * scale).ceil() as usize).min(width);
+        // image rows go down from the top
+        let start_y = ((top - tile.vertex.w) * scale).floor().max(0.0) as usize;
+        let end_y = (((top - tile.vertex.y) * scale).ceil() as usize).min(height);
+
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let local = Vec2::new(
+                    min.x + (x as f32 + 0.5) / scale,
+                    top - (y as f32 + 0.5) / scale,
+                );
+                let s = (local.x - tile_min.x) / tile_size.x;
+                let t = (local.y - tile_min.y) / tile_size.y;
+                if !(0.0..1.0).contains(&s) || !(0.0..1.0).contains(&t) {
+                    continue;
+                }
+                let uv = corners[0] * (1.0 - s) * (1.0 - t)
+                    + corners[1] * (1.0 - s) * t
+                    + corners[2] * s * t
+                    + corners[3] * s * (1.0 - t);
+                let source_x = ((uv.x * image_width as f32) as usize).min(image_width - 1);
+                let source_y = ((uv.y * image_height as f32) as usize).min(image_height - 1);
+                let source = (source_y * image_width + source_x) * 4;
+                let target = (y * width + x) * 4;
+                blend_over(
+                    &mut data[target..target + 4],
+                    &tileset_image.data[source..source + 4],
+                );
+            }
+        }
+    }
+}
+
+fn blend_over(target: &mut [u8], source: &[u8]) {
+    let source_alpha = source[3] as f32 / 255.0;
+    if source_alpha <= 0.0 {
+        return;
+    }
+    let target_alpha = target[3] as f32 / 255.0;
+    let alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+    for channel in 0..3 {
+        let color = source[channel] as f32 * source_alpha
+            + target[channel] as f32 * target_alpha * (1.0 - source_alpha);
+        target[channel] = (color / alpha).round() as u8;
+    }
+    target[3] = (alpha * 255.0).round() as u8;
+}
+
+fn quad_mesh(bounds: Vec4) -> Mesh {
+    let positions = vec![
+        [bounds.x, bounds.y, 0.0],
+        [bounds.x, bounds.w, 0.0],
+        [bounds.z, bounds.w, 0.0],
+        [bounds.z, bounds.y, 0.0],
+    ];
+    let uvs = vec![[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(positions));
+    mesh.set_attribute("Vertex_Uv", VertexAttributeValues::Float2(uvs));
+    mesh.set_indices(Some(Indices::U32(vec![0, 2, 1, 0, 3, 2])));
+    mesh
+}
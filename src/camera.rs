@@ -0,0 +1,60 @@
+use crate::{Bounds, Map, TiledMapCenter};
+use bevy::{prelude::*, render::camera::OrthographicProjection};
+
+/// Keeps the view of a 2d camera inside the bounds of all spawned maps, grown by `margin`
+/// pixels. Along axes where the view is larger than the maps, the camera is centered on them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ClampToMap {
+    pub margin: f32,
+}
+
+/// Moves cameras with a `ClampToMap` back inside the map bounds, accounting for their zoom.
+#[allow(clippy::type_complexity)]
+pub fn clamp_cameras_to_map(
+    maps: Res<Assets<Map>>,
+    map_entities: Query<
+        (&Handle<Map>, &TiledMapCenter, &Transform, &Option<Entity>),
+        Without<ClampToMap>,
+    >,
+    parents: Query<&GlobalTransform>,
+    mut cameras: Query<(&ClampToMap, &OrthographicProjection, &mut Transform)>,
+) {
+    let bounds = map_entities
+        .iter()
+        .filter_map(|(map_handle, center, origin, optional_parent)| {
+            let map = maps.get(map_handle)?;
+            let mut tile_map_transform = map.tile_map_transform(origin, center);
+            // chunks are children of the parent entity, if there is one
+            if let Some(parent_transform) = optional_parent.and_then(|p| parents.get(p).ok()) {
+                tile_map_transform = parent_transform.mul_transform(tile_map_transform).into();
+            }
+            map.world_bounds(&tile_map_transform)
+        })
+        .reduce(Bounds::union);
+    let bounds = match bounds {
+        Some(bounds) => bounds,
+        None => return,
+    };
+
+    for (clamp, projection, mut transform) in cameras.iter_mut() {
+        let scale = transform.scale.truncate() * projection.scale;
+        let view_min = Vec2::new(projection.left, projection.bottom) * scale;
+        let view_max = Vec2::new(projection.right, projection.top) * scale;
+        // range of camera positions that keep the view inside the bounds
+        let low = bounds.min - Vec2::splat(clamp.margin) - view_min;
+        let high = bounds.max + Vec2::splat(clamp.margin) - view_max;
+        let clamp_axis = |position: f32, low: f32, high: f32| {
+            if low > high {
+                (low + high) / 2.0
+            } else {
+                position.max(low).min(high)
+            }
+        };
+        let x = clamp_axis(transform.translation.x, low.x, high.x);
+        let y = clamp_axis(transform.translation.y, low.y, high.y);
+        if x != transform.translation.x || y != transform.translation.y {
+            transform.translation.x = x;
+            transform.translation.y = y;
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{Bounds, Map, PropertiesExt, TiledConfig};
+use bevy::prelude::*;
+
+/// A rectangle of tiles, in tile coordinates, e.g. solid tiles merged into one collider by
+/// `Map::solid_rects`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRect {
+    pub min: UVec2,
+    pub size: UVec2,
+}
+
+impl Map {
+    /// Merges the tiles whose `TiledConfig::solid_property` is set into rectangles, within each
+    /// chunk of `TiledConfig::chunk_size` tiles, see `merged_rects`.
+    pub fn solid_rects(&self, config: &TiledConfig) -> Vec<TileRect> {
+        let chunk_size = if config.merge_layer_chunks {
+            UVec2::new(self.map.width, self.map.height)
+        } else {
+            config.chunk_size
+        };
+        self.merged_rects(chunk_size, |_, properties| {
+            properties.and_then(|p| p.get_bool(&config.solid_property)) == Some(true)
+        })
+    }
+
+    /// Merges the cells of the map that have a solid tile in any tile layer into as few
+    /// rectangles as possible, each of them as wide and then as tall as it can get. Rectangles
+    /// never cross chunks of `chunk_size` tiles, so a chunk's colliders can be rebuilt on their
+    /// own. `is_solid` receives each tile's gid and the custom properties set on it in its
+    /// tileset, like the cost function of `NavGrid::from_map`.
+    pub fn merged_rects(
+        &self,
+        chunk_size: UVec2,
+        is_solid: impl Fn(u32, Option<&tiled::Properties>) -> bool,
+    ) -> Vec<TileRect> {
+        let (width, height) = (self.map.width, self.map.height);
+        let solid: Vec<bool> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| UVec2::new(x, y)))
+            .map(|tile| {
+                (0..self.map.layers.len())
+                    .filter_map(|layer_index| self.gid_at(layer_index, tile))
+                    .any(|gid| is_solid(gid, self.tile_properties(gid)))
+            })
+            .collect();
+        let chunk_size = chunk_size.max(UVec2::ONE);
+        let mut merged = vec![false; solid.len()];
+        let mut rects = Vec::new();
+
+        for chunk_y in (0..height).step_by(chunk_size.y as usize) {
+            for chunk_x in (0..width).step_by(chunk_size.x as usize) {
+                let chunk_max = UVec2::new(
+                    (chunk_x + chunk_size.x).min(width),
+                    (chunk_y + chunk_size.y).min(height),
+                );
+                let free = |merged: &[bool], x: u32, y: u32| {
+                    let index = (y * width + x) as usize;
+                    solid[index] && !merged[index]
+                };
+                for y in chunk_y..chunk_max.y {
+                    for x in chunk_x..chunk_max.x {
+                        if !free(&merged, x, y) {
+                            continue;
+                        }
+                        let mut max_x = x + 1;
+                        while max_x < chunk_max.x && free(&merged, max_x, y) {
+                            max_x += 1;
+                        }
+                        let mut max_y = y + 1;
+                        while max_y < chunk_max.y
+                            && (x..max_x).all(|row_x| free(&merged, row_x, max_y))
+                        {
+                            max_y += 1;
+                        }
+                        for merged_y in y..max_y {
+                            for merged_x in x..max_x {
+                                merged[(merged_y * width + merged_x) as usize] = true;
+                            }
+                        }
+                        rects.push(TileRect {
+                            min: UVec2::new(x, y),
+                            size: UVec2::new(max_x - x, max_y - y),
+                        });
+                    }
+                }
+            }
+        }
+        rects
+    }
+
+    /// Bounds of a tile rectangle relative to the map transform, as for `Map::bounds`. `None`
+    /// for orientations other than orthogonal, where rectangles of tiles aren't axis aligned.
+    pub fn tile_rect_bounds(&self, rect: TileRect) -> Option<Bounds> {
+        if self.map.orientation != tiled::Orientation::Orthogonal {
+            return None;
+        }
+        let tile_width = self.map.tile_width as f32;
+        let tile_height = self.map.tile_height as f32;
+        let min = Vec2::new(rect.min.x as f32, rect.min.y as f32);
+        let max = min + Vec2::new(rect.size.x as f32, rect.size.y as f32);
+        // y points down in tile coordinates
+        let top_left = Map::project_ortho(min, tile_width, tile_height);
+        let bottom_right = Map::project_ortho(max, tile_width, tile_height);
+        Some(Bounds {
+            min: Vec2::new(top_left.x, bottom_right.y),
+            max: Vec2::new(bottom_right.x, top_left.y),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_maps::ascii_map;
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> TileRect {
+        TileRect {
+            min: UVec2::new(x, y),
+            size: UVec2::new(width, height),
+        }
+    }
+
+    #[test]
+    fn merges_l_shape_into_two_rects() {
+        let map = ascii_map(&["#...", "#...", "####"]);
+        let rects = map.solid_rects(&TiledConfig::default());
+        // grown from the top left, the stem of the L takes the corner tile
+        assert_eq!(rects, vec![rect(0, 0, 1, 3), rect(1, 2, 3, 1)]);
+    }
+
+    #[test]
+    fn rects_do_not_cross_chunks() {
+        let map = ascii_map(&["......", ".####.", ".####.", "......"]);
+        let rects = map.merged_rects(UVec2::new(3, 2), |gid, _| gid == 2);
+        assert_eq!(
+            rects,
+            vec![
+                rect(1, 1, 2, 1),
+                rect(3, 1, 2, 1),
+                rect(1, 2, 2, 1),
+                rect(3, 2, 2, 1),
+            ]
+        );
+
+        // the same region within one chunk is a single rectangle
+        let rects = map.merged_rects(UVec2::new(8, 8), |gid, _| gid == 2);
+        assert_eq!(rects, vec![rect(1, 1, 4, 2)]);
+    }
+}
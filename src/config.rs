@@ -0,0 +1,155 @@
+use bevy::{prelude::*, render::texture::FilterMode};
+use std::num::NonZeroU8;
+
+/// Plugin-wide settings for loading and spawning tiled maps.
+///
+/// Insert this resource to override the defaults. Settings used while loading, like
+/// `chunk_size`, `layer_z_spacing` or `mipmap_levels`, apply to maps loaded after the change;
+/// maps that are already loaded keep theirs until they are reloaded.
+#[derive(Debug, Clone)]
+pub struct TiledConfig {
+    /// Number of tiles per chunk mesh along x and y.
+    pub chunk_size: UVec2,
+    /// Distance in z between consecutive tile layers.
+    pub layer_z_spacing: f32,
+    /// How objects are placed in z.
+    pub object_z: ObjectZPolicy,
+    /// Filtering applied to tileset textures once they are loaded.
+    pub texture_filter: FilterMode,
+    /// Number of mip levels uploaded for tileset textures, 1 for none, to stop maps from
+    /// shimmering when zoomed far out. Tiles are extruded by 2^(levels - 1) pixels on each side
+    /// so that smaller levels don't blend neighbouring tiles in, which relays the tileset images
+    /// in place, so don't draw them as anything but tiles. Ignored with the `web` feature.
+    pub mipmap_levels: u32,
+    /// Anisotropic filtering of tileset textures, one of 1, 2, 4, 8 or 16. GPUs without
+    /// anisotropic filtering ignore it.
+    pub anisotropy: Option<NonZeroU8>,
+    /// Shows object debug shapes on every map, regardless of its `DebugConfig`.
+    pub debug_enabled: bool,
+    /// Color of the default debug material.
+    pub debug_color: Color,
+    /// Draws polygon and ellipse objects as filled meshes, e.g. for zones or blockout levels.
+    /// They use the object's `color` property, or else their object layer's color.
+    pub fill_shapes: bool,
+    /// Loads map data and spawns object entities without creating any meshes,
+    /// materials or texture atlases, for apps running without rendering.
+    pub headless: bool,
+    /// Maximum number of chunk meshes kept in `Assets<Mesh>`. When exceeded, the meshes of
+    /// the chunks farthest from the camera are dropped and rebuilt once they come back in range.
+    pub max_loaded_chunks: Option<usize>,
+    /// Maximum number of chunk and object entities spawned per frame, to spread the spawning
+    /// of large maps over several frames. Tile layers are spawned whole, so a frame may
+    /// exceed it by one layer. `MapReadyEvent` fires once everything is spawned.
+    pub max_spawns_per_frame: Option<usize>,
+    /// Boolean tile property that `MapQuery::is_solid` checks.
+    pub solid_property: String,
+    /// Drops the per-chunk tile data of maps once their meshes are built, which roughly halves
+    /// their memory. Queries and edits still work, but chunks are no longer unloaded by
+    /// `max_loaded_chunks`.
+    pub discard_chunk_data: bool,
+    /// Builds each tile layer as a single mesh per tileset instead of one per chunk, for fewer
+    /// draw calls on maps that are drawn whole. `chunk_size` is ignored, and `max_loaded_chunks`
+    /// can only unload whole layers.
+    pub merge_layer_chunks: bool,
+    /// Stacks the tileset images of each map into one array texture, so that all of its chunks
+    /// are drawn with the same pipeline and bindings, see `TilesetArray`. There is still one draw
+    /// per chunk and tileset. Tileset images must share their size and format, and maps with a
+    /// single tileset are left as they are. Ignored with the `web` feature.
+    pub tileset_array: bool,
+    /// Sends a `TileAnimationFrameEvent` whenever an animated tile object shows another frame.
+    pub tile_animation_events: bool,
+    /// Skips drawing the chunks and objects outside the view of the 2d camera, see
+    /// `CullingBounds`. Other cameras, e.g. of a minimap, won't see them either.
+    pub cull_outside_camera: bool,
+    /// Draws the chunks of tile layers whose tiles are fully opaque in the opaque pass, without
+    /// blending, see `OpaqueChunk`. Layers can also be marked with the `opaque` bool property.
+    pub opaque_layers: bool,
+    /// Leaves out the tiles that are fully covered by a tile of a visible layer above them with
+    /// the `opaque` property, to save fill rate. Hiding, tinting or scrolling such a layer at
+    /// runtime reveals the holes beneath it.
+    pub occlude_hidden_tiles: bool,
+}
+
+impl Default for TiledConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: UVec2::new(32, 32),
+            layer_z_spacing: 1.0,
+            object_z: ObjectZPolicy::default(),
+            texture_filter: FilterMode::Linear,
+            mipmap_levels: 1,
+            anisotropy: None,
+            debug_enabled: false,
+            debug_color: Color::rgba(0.4, 0.4, 0.9, 0.5),
+            fill_shapes: false,
+            headless: false,
+            max_loaded_chunks: None,
+            max_spawns_per_frame: None,
+            solid_property: "solid".to_string(),
+            discard_chunk_data: false,
+            merge_layer_chunks: false,
+            tileset_array: false,
+            tile_animation_events: false,
+            cull_outside_camera: false,
+            opaque_layers: false,
+            occlude_hidden_tiles: false,
+        }
+    }
+}
+
+impl TiledConfig {
+    /// Pixels that every tile is extruded by in its tileset image, for `mipmap_levels`.
+    pub fn tile_padding(&self) -> u32 {
+        if self.mipmap_levels <= 1 || cfg!(feature = "web") {
+            return 0;
+        }
+        1 << (self.mipmap_levels.min(8) - 1)
+    }
+}
+
+/// How spawned objects are placed in z, relative to the map transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectZPolicy {
+    /// All objects at the same z, regardless of their object layer.
+    Fixed(f32),
+    /// Objects at the z of their object layer, which is placed by its position among the
+    /// map's layers like tile layers are.
+    LayerOrder,
+    /// Like `LayerOrder`, with objects nudged forward by their y position divided by `range`,
+    /// so objects lower on the map are drawn in front. `range` should be larger than the
+    /// map height in pixels divided by `layer_z_spacing`.
+    YSorted { range: f32 },
+    /// Like `YSorted`, but by the y of the bottom of each object instead of its center, where
+    /// tile objects such as trees stand on the ground. Sprites sorted by their feet with the
+    /// same `object_z` are then drawn behind or in front of them as they walk past.
+    YSortedByBase { range: f32 },
+}
+
+impl Default for ObjectZPolicy {
+    fn default() -> Self {
+        // in front of the tile layers of most maps
+        ObjectZPolicy::Fixed(15.0)
+    }
+}
+
+impl ObjectZPolicy {
+    /// Z of an object layer entity, from its index among the map's layers.
+    pub fn layer_z(&self, z_index: u32, layer_z_spacing: f32) -> f32 {
+        match self {
+            ObjectZPolicy::Fixed(_) => 0.0,
+            ObjectZPolicy::LayerOrder
+            | ObjectZPolicy::YSorted { .. }
+            | ObjectZPolicy::YSortedByBase { .. } => z_index as f32 * layer_z_spacing,
+        }
+    }
+
+    /// Z of an object relative to its object layer, from its y position relative to the map.
+    pub fn object_z(&self, y: f32) -> f32 {
+        match self {
+            ObjectZPolicy::Fixed(z) => *z,
+            ObjectZPolicy::LayerOrder => 0.0,
+            // y points up, objects further down are in front
+            ObjectZPolicy::YSorted { range } | ObjectZPolicy::YSortedByBase { range } => -y / range,
+        }
+    }
+}
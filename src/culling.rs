@@ -0,0 +1,91 @@
+use crate::{Bounds, TiledConfig};
+use bevy::{
+    prelude::*,
+    render::{
+        camera::Camera, draw::OutsideFrustum, mesh::VertexAttributeValues,
+        render_graph::base::camera::CAMERA_2D,
+    },
+};
+
+/// Bounds of a spawned chunk or object in its own space, before its transform. Entities whose
+/// bounds are out of the 2d camera's view are marked `OutsideFrustum` and not drawn, if
+/// `TiledConfig::cull_outside_camera` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CullingBounds(pub Bounds);
+
+/// Marks chunks and objects with `CullingBounds` outside the view of the 2d camera as
+/// `OutsideFrustum`, and unmarks them once they are back in view.
+#[allow(clippy::type_complexity)]
+pub fn cull_outside_camera(
+    mut commands: Commands,
+    config: Res<TiledConfig>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    culled: Query<(
+        Entity,
+        &CullingBounds,
+        &GlobalTransform,
+        Option<&OutsideFrustum>,
+    )>,
+    mut was_enabled: Local<bool>,
+) {
+    if !config.cull_outside_camera {
+        // show everything again when culling is turned off
+        if *was_enabled {
+            for (entity, _, _, outside) in culled.iter() {
+                if outside.is_some() {
+                    commands.entity(entity).remove::<OutsideFrustum>();
+                }
+            }
+        }
+        *was_enabled = false;
+        return;
+    }
+    *was_enabled = true;
+
+    let (camera, camera_transform) = match cameras
+        .iter()
+        .find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))
+    {
+        Some(camera) => camera,
+        None => return,
+    };
+    // the corners of normalized device coordinates, through the camera into the world
+    let view = Bounds {
+        min: Vec2::splat(-1.0),
+        max: Vec2::splat(1.0),
+    }
+    .transformed(&Transform::from_matrix(
+        camera_transform.compute_matrix() * camera.projection_matrix.inverse(),
+    ));
+
+    for (entity, bounds, transform, outside) in culled.iter() {
+        let bounds = bounds.0.transformed(&(*transform).into());
+        let in_view = bounds.min.x <= view.max.x
+            && bounds.min.y <= view.max.y
+            && bounds.max.x >= view.min.x
+            && bounds.max.y >= view.min.y;
+        match (in_view, outside.is_some()) {
+            (true, true) => {
+                commands.entity(entity).remove::<OutsideFrustum>();
+            }
+            (false, false) => {
+                commands.entity(entity).insert(OutsideFrustum);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Bounds of a mesh's vertex positions in x and y.
+pub fn mesh_bounds(mesh: &Mesh) -> Option<Bounds> {
+    match mesh.attribute("Vertex_Position")? {
+        VertexAttributeValues::Float3(positions) => positions
+            .iter()
+            .map(|[x, y, _]| Bounds {
+                min: Vec2::new(*x, *y),
+                max: Vec2::new(*x, *y),
+            })
+            .reduce(Bounds::union),
+        _ => None,
+    }
+}
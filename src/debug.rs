@@ -0,0 +1,136 @@
+use crate::{ChunkLocation, DebugConfig, Map, Object, ObjectLayer, TiledConfig, TiledMapCenter};
+use bevy::prelude::*;
+
+/// Style of the debug labels spawned by `TiledDebugPlugin`. Bevy has no default font, so labels
+/// are only spawned once this resource is inserted.
+#[derive(Debug, Clone)]
+pub struct DebugLabels {
+    pub font: Handle<Font>,
+    pub font_size: f32,
+    pub color: Color,
+}
+
+impl DebugLabels {
+    pub fn new(font: Handle<Font>) -> Self {
+        Self {
+            font,
+            font_size: 12.0,
+            color: Color::WHITE,
+        }
+    }
+}
+
+/// Marks the text entities of debug labels, e.g. to hide them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DebugLabel;
+
+/// Labels objects with their name and type, and layers with their name at their origin, for
+/// maps with debug drawing enabled.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn spawn_debug_labels(
+    mut commands: Commands,
+    labels: Option<Res<DebugLabels>>,
+    config: Res<TiledConfig>,
+    maps: Res<Assets<Map>>,
+    map_entities: Query<(&Handle<Map>, &DebugConfig, &TiledMapCenter, &Transform)>,
+    objects: Query<(Entity, &Object, &Handle<Map>), Added<Object>>,
+    object_layers: Query<(Entity, &ObjectLayer, &Handle<Map>), Added<ObjectLayer>>,
+    chunks: Query<(Entity, &ChunkLocation, &Handle<Map>), Added<ChunkLocation>>,
+) {
+    let labels = match labels {
+        Some(labels) => labels,
+        None => return,
+    };
+    let debug_enabled = |map_handle: &Handle<Map>| {
+        config.debug_enabled
+            || map_entities
+                .iter()
+                .any(|(handle, debug_config, ..)| handle == map_handle && debug_config.enabled)
+    };
+    // labels are drawn just above what they label
+    let z = config.layer_z_spacing / 2.0;
+
+    for (entity, object, map_handle) in objects.iter() {
+        if !debug_enabled(map_handle) {
+            continue;
+        }
+        let text = match (object.name.is_empty(), object.obj_type.is_empty()) {
+            (true, true) => continue,
+            (false, true) => object.name.clone(),
+            (true, false) => format!("({})", object.obj_type),
+            (false, false) => format!("{} ({})", object.name, object.obj_type),
+        };
+        spawn_label(&mut commands, entity, text, Vec3::new(0.0, 0.0, z), &labels);
+    }
+
+    // object layer entities sit at the map's origin, their objects carry the map transform
+    for (entity, object_layer, map_handle) in object_layers.iter() {
+        if !debug_enabled(map_handle) {
+            continue;
+        }
+        let map_origin = match (
+            maps.get(map_handle),
+            map_entities
+                .iter()
+                .find(|(handle, ..)| *handle == map_handle),
+        ) {
+            (Some(map), Some((_, _, center, origin))) => {
+                map.tile_map_transform(origin, center).translation
+            }
+            _ => continue,
+        };
+        let translation = map_origin.truncate().extend(z);
+        let text = object_layer.name.clone();
+        spawn_label(&mut commands, entity, text, translation, &labels);
+    }
+
+    // tile layers are labeled on the first chunk of their first tileset, which has the
+    // transform of the map
+    for (entity, location, map_handle) in chunks.iter() {
+        if location.position != UVec2::ZERO || !debug_enabled(map_handle) {
+            continue;
+        }
+        let layer = match maps
+            .get(map_handle)
+            .and_then(|map| map.layers.get(location.layer_id))
+        {
+            Some(layer) => layer,
+            None => continue,
+        };
+        match layer.tileset_layers.first() {
+            Some(tileset_layer) if tileset_layer.tileset_guid == location.tileset_guid => {}
+            _ => continue,
+        }
+        let text = layer.name.clone();
+        spawn_label(&mut commands, entity, text, Vec3::new(0.0, 0.0, z), &labels);
+    }
+}
+
+fn spawn_label(
+    commands: &mut Commands,
+    parent: Entity,
+    text: String,
+    translation: Vec3,
+    labels: &DebugLabels,
+) {
+    let label = commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                text,
+                TextStyle {
+                    font: labels.font.clone(),
+                    font_size: labels.font_size,
+                    color: labels.color,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            transform: Transform::from_translation(translation),
+            ..Default::default()
+        })
+        .insert(DebugLabel)
+        .id();
+    commands.entity(parent).push_children(&[label]);
+}
@@ -0,0 +1,290 @@
+use crate::{Map, Object, TileChange, TileSnapshot};
+#[cfg(feature = "serialize")]
+use std::path::Path;
+
+/// Runtime edits of a map relative to the map as loaded, see `Map::diff_delta`.
+/// Objects that are only changed in place are not recorded.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapDelta {
+    pub tiles: Vec<TileChange>,
+    /// Ids of loaded objects that were removed.
+    pub removed_objects: Vec<u32>,
+    pub added_objects: Vec<AddedObject>,
+}
+
+/// An object added with `Map::add_object`, with the data needed to add it again.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddedObject {
+    pub group_index: usize,
+    pub id: u32,
+    pub gid: u32,
+    pub name: String,
+    pub obj_type: String,
+    pub x: f32,
+    pub y: f32,
+    /// Rotation in degrees clockwise.
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: AddedObjectShape,
+    /// Properties sorted by name.
+    pub properties: Vec<(String, AddedObjectProperty)>,
+}
+
+/// `tiled::ObjectShape`, which can't be serialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddedObjectShape {
+    Rect { width: f32, height: f32 },
+    Ellipse { width: f32, height: f32 },
+    Polyline { points: Vec<(f32, f32)> },
+    Polygon { points: Vec<(f32, f32)> },
+    Point(f32, f32),
+}
+
+/// `tiled::PropertyValue`, which can't be serialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddedObjectProperty {
+    Bool(bool),
+    Float(f32),
+    Int(i32),
+    Color(u32),
+    String(String),
+}
+
+impl AddedObject {
+    fn new(group_index: usize, object: &Object) -> Self {
+        let shape = match &object.shape {
+            tiled::ObjectShape::Rect { width, height } => AddedObjectShape::Rect {
+                width: *width,
+                height: *height,
+            },
+            tiled::ObjectShape::Ellipse { width, height } => AddedObjectShape::Ellipse {
+                width: *width,
+                height: *height,
+            },
+            tiled::ObjectShape::Polyline { points } => AddedObjectShape::Polyline {
+                points: points.clone(),
+            },
+            tiled::ObjectShape::Polygon { points } => AddedObjectShape::Polygon {
+                points: points.clone(),
+            },
+            tiled::ObjectShape::Point(x, y) => AddedObjectShape::Point(*x, *y),
+        };
+        let mut properties: Vec<_> = object
+            .props
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    tiled::PropertyValue::BoolValue(value) => AddedObjectProperty::Bool(*value),
+                    tiled::PropertyValue::FloatValue(value) => AddedObjectProperty::Float(*value),
+                    tiled::PropertyValue::IntValue(value) => AddedObjectProperty::Int(*value),
+                    tiled::PropertyValue::ColorValue(value) => AddedObjectProperty::Color(*value),
+                    tiled::PropertyValue::StringValue(value) => {
+                        AddedObjectProperty::String(value.clone())
+                    }
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        properties.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Self {
+            group_index,
+            id: object.id,
+            gid: object.gid(),
+            name: object.name.clone(),
+            obj_type: object.obj_type.clone(),
+            x: object.position.x,
+            y: object.position.y,
+            rotation: object.rotation,
+            visible: object.visible,
+            shape,
+            properties,
+        }
+    }
+
+    fn to_tiled(&self) -> tiled::Object {
+        let (width, height, shape) = match &self.shape {
+            AddedObjectShape::Rect { width, height } => (
+                *width,
+                *height,
+                tiled::ObjectShape::Rect {
+                    width: *width,
+                    height: *height,
+                },
+            ),
+            AddedObjectShape::Ellipse { width, height } => (
+                *width,
+                *height,
+                tiled::ObjectShape::Ellipse {
+                    width: *width,
+                    height: *height,
+                },
+            ),
+            AddedObjectShape::Polyline { points } => (
+                0.0,
+                0.0,
+                tiled::ObjectShape::Polyline {
+                    points: points.clone(),
+                },
+            ),
+            AddedObjectShape::Polygon { points } => (
+                0.0,
+                0.0,
+                tiled::ObjectShape::Polygon {
+                    points: points.clone(),
+                },
+            ),
+            AddedObjectShape::Point(x, y) => (0.0, 0.0, tiled::ObjectShape::Point(*x, *y)),
+        };
+        let properties = self
+            .properties
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    AddedObjectProperty::Bool(value) => tiled::PropertyValue::BoolValue(*value),
+                    AddedObjectProperty::Float(value) => tiled::PropertyValue::FloatValue(*value),
+                    AddedObjectProperty::Int(value) => tiled::PropertyValue::IntValue(*value),
+                    AddedObjectProperty::Color(value) => tiled::PropertyValue::ColorValue(*value),
+                    AddedObjectProperty::String(value) => {
+                        tiled::PropertyValue::StringValue(value.clone())
+                    }
+                };
+                (name.clone(), value)
+            })
+            .collect();
+
+        tiled::Object {
+            id: self.id,
+            gid: self.gid,
+            name: self.name.clone(),
+            obj_type: self.obj_type.clone(),
+            width,
+            height,
+            x: self.x,
+            y: self.y,
+            rotation: self.rotation,
+            visible: self.visible,
+            shape,
+            properties,
+        }
+    }
+}
+
+impl Map {
+    /// The tiles changed with `set_tile` and the objects added or removed since the map was
+    /// loaded, e.g. for small save files layered over the shipped map.
+    pub fn diff_delta(&self) -> MapDelta {
+        let loaded_ids: Vec<u32> = self
+            .map
+            .object_groups
+            .iter()
+            .flat_map(|group| group.objects.iter().map(|object| object.id))
+            .collect();
+        let current = || {
+            self.groups
+                .iter()
+                .enumerate()
+                .flat_map(|(group_index, group)| {
+                    group
+                        .objects
+                        .iter()
+                        .map(move |object| (group_index, object))
+                })
+        };
+
+        let mut removed_objects: Vec<u32> = loaded_ids
+            .iter()
+            .filter(|id| !current().any(|(_, object)| object.id == **id))
+            .copied()
+            .collect();
+        removed_objects.sort_unstable();
+        let added_objects = current()
+            .filter(|(_, object)| !loaded_ids.contains(&object.id))
+            .map(|(group_index, object)| AddedObject::new(group_index, object))
+            .collect();
+
+        MapDelta {
+            tiles: self.diff_snapshot().tiles,
+            removed_objects,
+            added_objects,
+        }
+    }
+
+    /// Applies a `diff_delta` after reverting all other tile and object edits.
+    /// Returns `false` if some of its tiles, objects or object groups don't exist in this map.
+    pub fn apply_delta(&mut self, delta: &MapDelta) -> bool {
+        let mut applied = self.apply_snapshot(&TileSnapshot {
+            tiles: delta.tiles.clone(),
+        });
+
+        let loaded_groups: Vec<Vec<Object>> = self
+            .map
+            .object_groups
+            .iter()
+            .map(|group| {
+                group
+                    .objects
+                    .iter()
+                    .map(|object| self.object_from_tiled(object))
+                    .collect()
+            })
+            .collect();
+        for (group, objects) in self.groups.iter_mut().zip(loaded_groups) {
+            group.objects = objects;
+        }
+
+        for id in delta.removed_objects.iter() {
+            applied &= self.remove_object(*id).is_some();
+        }
+        for object in delta.added_objects.iter() {
+            applied &= self
+                .add_object(object.group_index, object.to_tiled())
+                .is_some();
+        }
+        applied
+    }
+
+    /// Writes the `diff_delta` of the map to a RON file.
+    #[cfg(feature = "serialize")]
+    pub fn save_delta(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(&self.diff_delta(), Default::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Reads a RON file written by `save_delta` and applies it, see `apply_delta`.
+    #[cfg(feature = "serialize")]
+    pub fn load_delta(&mut self, path: impl AsRef<Path>) -> anyhow::Result<bool> {
+        let text = std::fs::read_to_string(path)?;
+        let delta: MapDelta = ron::de::from_str(&text)?;
+        Ok(self.apply_delta(&delta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::fixture_map;
+
+    #[test]
+    fn added_objects_keep_their_rotation() {
+        let mut map = fixture_map("content_hash.tmx");
+        let mut fence = map.map.object_groups[0].objects[3].clone();
+        fence.id = 0;
+        let id = map.add_object(0, fence).unwrap();
+        let delta = map.diff_delta();
+        assert_eq!(delta.added_objects[0].rotation, 90.0);
+
+        let mut loaded = fixture_map("content_hash.tmx");
+        assert!(loaded.apply_delta(&delta));
+        let object = loaded.groups[0]
+            .objects
+            .iter()
+            .find(|object| object.id == id)
+            .unwrap();
+        assert_eq!(object.rotation, 90.0);
+    }
+}
@@ -0,0 +1,633 @@
+use crate::{
+    loader::{FLIPPED_DIAGONALLY_FLAG, FLIPPED_HORIZONTALLY_FLAG, FLIPPED_VERTICALLY_FLAG},
+    map::{tmx_attribute, tmx_elements, tmx_start_tags},
+    BlendMode, Map, Object, UvScroll,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+// Runtime tile edits only change the tiled map data. Call `Map::rebuild` once done editing
+// to regenerate the chunk meshes; the changed layers are then respawned automatically.
+impl Map {
+    /// Sets the gid of a tile in a tiled layer, 0 clears it.
+    /// Returns `false` if the layer or position doesn't exist.
+    pub fn set_tile(&mut self, layer_index: usize, tile: UVec2, gid: u32) -> bool {
+        let layer = match self.map.layers.get_mut(layer_index) {
+            Some(layer) => layer,
+            None => return false,
+        };
+        match &mut layer.tiles {
+            tiled::LayerData::Finite(tiles) => {
+                match tiles
+                    .get_mut(tile.y as usize)
+                    .and_then(|row| row.get_mut(tile.x as usize))
+                {
+                    Some(layer_tile) => {
+                        self.original_tiles
+                            .entry((layer_index, tile.x, tile.y))
+                            .or_insert(*layer_tile);
+                        *layer_tile = tiled::LayerTile::new(gid);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            tiled::LayerData::Infinite(_) => false,
+        }
+    }
+
+    /// The tiles changed with `set_tile` since the map was loaded, e.g. to store destructible
+    /// terrain in a save game.
+    pub fn diff_snapshot(&self) -> TileSnapshot {
+        let mut tiles: Vec<TileChange> = self
+            .original_tiles
+            .iter()
+            .filter_map(|(&(layer_index, x, y), original)| {
+                let current = self.layer_tile(layer_index, x, y)?;
+                if current == *original {
+                    return None;
+                }
+                Some(TileChange {
+                    layer_index,
+                    x,
+                    y,
+                    gid: raw_gid(current),
+                })
+            })
+            .collect();
+        tiles.sort_by_key(|change| (change.layer_index, change.y, change.x));
+        TileSnapshot { tiles }
+    }
+
+    /// Restores the tiles of a `diff_snapshot`, after reverting all other tile edits.
+    /// Returns `false` if some of its tiles don't exist in this map.
+    pub fn apply_snapshot(&mut self, snapshot: &TileSnapshot) -> bool {
+        let originals: Vec<_> = self.original_tiles.drain().collect();
+        for ((layer_index, x, y), original) in originals.iter() {
+            self.set_tile(*layer_index, UVec2::new(*x, *y), raw_gid(*original));
+        }
+        // reverting is not an edit
+        self.original_tiles.clear();
+        self.original_tiles.extend(originals);
+
+        let mut applied = true;
+        for change in snapshot.tiles.iter() {
+            let tile = UVec2::new(change.x, change.y);
+            applied &= self.set_tile(change.layer_index, tile, change.gid);
+        }
+        applied
+    }
+
+    fn layer_tile(&self, layer_index: usize, x: u32, y: u32) -> Option<tiled::LayerTile> {
+        match &self.map.layers.get(layer_index)?.tiles {
+            tiled::LayerData::Finite(tiles) => tiles.get(y as usize)?.get(x as usize).copied(),
+            tiled::LayerData::Infinite(_) => None,
+        }
+    }
+
+    /// Sets the blend mode of a tile layer by writing its `blend_mode` property.
+    pub fn set_layer_blend_mode(&mut self, layer_index: usize, blend_mode: BlendMode) {
+        if let Some(layer) = self.map.layers.get_mut(layer_index) {
+            layer.properties.insert(
+                BlendMode::PROPERTY.to_string(),
+                tiled::PropertyValue::StringValue(blend_mode.property_value().to_string()),
+            );
+        }
+    }
+
+    /// Sets or clears the `UvScroll` of a tile layer by writing its `uv_scroll_x` and
+    /// `uv_scroll_y` properties. Takes effect right away, without a `rebuild`.
+    pub fn set_layer_uv_scroll(&mut self, layer_index: usize, uv_scroll: Option<UvScroll>) {
+        let properties = match self.map.layers.get_mut(layer_index) {
+            Some(layer) => &mut layer.properties,
+            None => return,
+        };
+        match uv_scroll {
+            Some(UvScroll { velocity }) => {
+                properties.insert(
+                    UvScroll::PROPERTY_X.to_string(),
+                    tiled::PropertyValue::FloatValue(velocity.x),
+                );
+                properties.insert(
+                    UvScroll::PROPERTY_Y.to_string(),
+                    tiled::PropertyValue::FloatValue(velocity.y),
+                );
+            }
+            None => {
+                properties.remove(UvScroll::PROPERTY_X);
+                properties.remove(UvScroll::PROPERTY_Y);
+            }
+        }
+        if let Some(layer) = self
+            .layers
+            .iter_mut()
+            .find(|layer| layer.layer_index == layer_index)
+        {
+            layer.uv_scroll = uv_scroll;
+            layer.properties = self.map.layers[layer_index].properties.clone();
+        }
+    }
+
+    /// Appends an empty tile layer, e.g. an overlay for an in-game editor, and returns its
+    /// index. `z_index` is its position among the map's layers, which determines its z.
+    pub fn add_layer(&mut self, name: impl Into<String>, z_index: u32) -> usize {
+        let empty_row = vec![tiled::LayerTile::new(0); self.map.width as usize];
+        self.map.layers.push(tiled::Layer {
+            name: name.into(),
+            opacity: 1.0,
+            visible: true,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            tiles: tiled::LayerData::Finite(vec![empty_row; self.map.height as usize]),
+            properties: Default::default(),
+            layer_index: z_index,
+        });
+        self.map.layers.len() - 1
+    }
+
+    /// Removes a tile layer; the layers after it move down one index.
+    /// Returns `false` if the layer doesn't exist.
+    pub fn remove_layer(&mut self, layer_index: usize) -> bool {
+        if layer_index >= self.map.layers.len() {
+            return false;
+        }
+        self.map.layers.remove(layer_index);
+        self.original_tiles = self
+            .original_tiles
+            .drain()
+            .filter(|((index, ..), _)| *index != layer_index)
+            .map(|((index, x, y), tile)| {
+                let index = if index > layer_index {
+                    index - 1
+                } else {
+                    index
+                };
+                ((index, x, y), tile)
+            })
+            .collect();
+        true
+    }
+
+    /// Adds an object to an object group and returns its id; objects with id 0 get the next
+    /// free id. Object edits show up the next time the map is spawned.
+    /// Returns `None` if the group doesn't exist.
+    pub fn add_object(&mut self, group_index: usize, mut object: tiled::Object) -> Option<u32> {
+        if group_index >= self.groups.len() {
+            return None;
+        }
+        if object.id == 0 {
+            object.id = self.next_object_id();
+        }
+        let object = self.object_from_tiled(&object);
+        let id = object.id;
+        self.groups[group_index].objects.push(object);
+        Some(id)
+    }
+
+    /// Removes an object by its id, see `add_object`.
+    pub fn remove_object(&mut self, id: u32) -> Option<Object> {
+        self.groups.iter_mut().find_map(|group| {
+            let index = group.objects.iter().position(|object| object.id == id)?;
+            Some(group.objects.remove(index))
+        })
+    }
+
+    // one past the highest id of the loaded and current objects, so removed ids aren't reused
+    fn next_object_id(&self) -> u32 {
+        let loaded = self
+            .map
+            .object_groups
+            .iter()
+            .flat_map(|group| group.objects.iter().map(|object| object.id));
+        let current = self
+            .groups
+            .iter()
+            .flat_map(|group| group.objects.iter().map(|object| object.id));
+        loaded.chain(current).max().unwrap_or(0) + 1
+    }
+
+    /// The probability set on a tile in its tileset, 1.0 if it has none.
+    pub fn tile_probability(&self, gid: u32) -> f32 {
+        self.tileset_tile(gid)
+            .map(|tile| tile.probability)
+            .unwrap_or(1.0)
+    }
+
+    /// Fills an area of a tiled layer with tiles picked from `candidates`, weighted by
+    /// each tile's probability. `random` must return values in `0.0..1.0`.
+    pub fn random_fill(
+        &mut self,
+        layer_index: usize,
+        min: UVec2,
+        size: UVec2,
+        candidates: &[u32],
+        mut random: impl FnMut() -> f32,
+    ) {
+        let weights: Vec<(u32, f32)> = candidates
+            .iter()
+            .map(|gid| (*gid, self.tile_probability(*gid)))
+            .collect();
+        for y in min.y..min.y + size.y {
+            for x in min.x..min.x + size.x {
+                if let Some(gid) = weighted_gid(&weights, &mut random) {
+                    self.set_tile(layer_index, UVec2::new(x, y), gid);
+                }
+            }
+        }
+    }
+
+    /// Paints a brush onto a tiled layer, centered on `center`. Rebuild once after painting,
+    /// however many tiles the brush covers. `random` must return values in `0.0..1.0` and is
+    /// only used by `Brush::Random`. Tiles outside of the map are skipped.
+    pub fn paint(
+        &mut self,
+        layer_index: usize,
+        center: UVec2,
+        brush: &Brush,
+        mut random: impl FnMut() -> f32,
+    ) {
+        let size = brush.size();
+        let min = center.as_i32() - (size / 2).as_i32();
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let gid = match brush {
+                    Brush::Tile(gid) => Some(*gid),
+                    Brush::Stamp(rows) => rows[y as usize]
+                        .get(x as usize)
+                        .copied()
+                        .filter(|gid| *gid != 0),
+                    Brush::Random { tiles, .. } => weighted_gid(tiles, &mut random),
+                };
+                let tile = min + UVec2::new(x, y).as_i32();
+                if let (Some(gid), true) = (gid, tile.cmpge(IVec2::ZERO).all()) {
+                    self.set_tile(layer_index, tile.as_u32(), gid);
+                }
+            }
+        }
+    }
+}
+
+/// What `Map::paint` puts down around the painted tile.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Brush {
+    /// A single tile.
+    Tile(u32),
+    /// Rows of gids indexed `[y][x]`, like a stamp in Tiled. 0 leaves a tile unchanged.
+    Stamp(Vec<Vec<u32>>),
+    /// A rectangle of `size` tiles, each picked from `tiles` by its weight.
+    Random { tiles: Vec<(u32, f32)>, size: UVec2 },
+}
+
+impl Brush {
+    /// The size of the brush in tiles.
+    pub fn size(&self) -> UVec2 {
+        match self {
+            Brush::Tile(_) => UVec2::ONE,
+            Brush::Stamp(rows) => UVec2::new(
+                rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32,
+                rows.len() as u32,
+            ),
+            Brush::Random { size, .. } => *size,
+        }
+    }
+}
+
+// picks one of the gids by weight, `None` if no weight is positive
+fn weighted_gid(weights: &[(u32, f32)], random: &mut impl FnMut() -> f32) -> Option<u32> {
+    let total: f32 = weights.iter().map(|(_, weight)| weight.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut roll = random() * total;
+    weights
+        .iter()
+        .find(|(_, weight)| {
+            roll -= weight.max(0.0);
+            roll < 0.0
+        })
+        .or_else(|| weights.last())
+        .map(|(gid, _)| *gid)
+}
+
+/// Corner-based autotiling rules, mapping the terrains at the corners of a tile
+/// (top-left, top-right, bottom-right, bottom-left) to the gid drawn for it.
+///
+/// Maps read the corner Wang sets of their tilesets into `Map::terrain_sets`, terrains are
+/// numbered by their Wang color starting at 1. Sets can also be filled in by hand with `insert`.
+#[derive(Debug, Default, Clone)]
+pub struct TerrainSet {
+    pub name: String,
+    /// First gid of the tileset the set was read from, 0 for sets filled in by hand.
+    pub first_gid: u32,
+    tiles: HashMap<[u32; 4], u32>,
+    corners: HashMap<u32, [u32; 4]>,
+}
+
+impl TerrainSet {
+    /// Adds a tile with the given corner terrains, replacing any tile with the same corners.
+    pub fn insert(&mut self, corners: [u32; 4], gid: u32) {
+        if let Some(old_gid) = self.tiles.insert(corners, gid) {
+            self.corners.remove(&old_gid);
+        }
+        self.corners.insert(gid, corners);
+    }
+
+    /// The gid of the tile with the given corner terrains.
+    pub fn gid(&self, corners: [u32; 4]) -> Option<u32> {
+        self.tiles.get(&corners).copied()
+    }
+
+    /// The corner terrains of a tile, if it's part of the set.
+    pub fn corners_of(&self, gid: u32) -> Option<[u32; 4]> {
+        self.corners.get(&gid).copied()
+    }
+
+    /// Reads the Wang sets with corner colors of the tilesets in tmx text, which tiled 0.9
+    /// doesn't parse. Edge-only sets are skipped.
+    pub fn from_tmx(text: &str) -> Vec<TerrainSet> {
+        let mut terrain_sets = Vec::new();
+        for tileset in tmx_elements(text, "tileset") {
+            let first_gid: u32 = match tmx_start_tags(tileset, "tileset")
+                .first()
+                .and_then(|tag| tmx_attribute(tag, "firstgid"))
+                .and_then(|first_gid| first_gid.parse().ok())
+            {
+                Some(first_gid) => first_gid,
+                None => continue,
+            };
+            for wang_set in tmx_elements(tileset, "wangset") {
+                let tag = tmx_start_tags(wang_set, "wangset")
+                    .first()
+                    .copied()
+                    .unwrap_or_default();
+                if tmx_attribute(tag, "type") == Some("edge") {
+                    continue;
+                }
+                let mut terrain_set = TerrainSet {
+                    name: tmx_attribute(tag, "name").unwrap_or_default().to_string(),
+                    first_gid,
+                    ..Default::default()
+                };
+                for tile in tmx_start_tags(wang_set, "wangtile") {
+                    let tile_id =
+                        tmx_attribute(tile, "tileid").and_then(|id| id.parse::<u32>().ok());
+                    let corners = tmx_attribute(tile, "wangid").and_then(wang_id_corners);
+                    if let (Some(tile_id), Some(corners)) = (tile_id, corners) {
+                        terrain_set.insert(corners, first_gid + tile_id);
+                    }
+                }
+                terrain_sets.push(terrain_set);
+            }
+        }
+        terrain_sets
+    }
+}
+
+// corner colors of a wang id, either 8 comma separated colors clockwise from the top edge
+// (Tiled 1.5+) or a hex number with a nibble per edge and corner from the top edge up
+fn wang_id_corners(wang_id: &str) -> Option<[u32; 4]> {
+    if wang_id.contains(',') {
+        let colors = wang_id
+            .split(',')
+            .map(|color| color.trim().parse().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        if colors.len() != 8 {
+            return None;
+        }
+        Some([colors[7], colors[1], colors[3], colors[5]])
+    } else {
+        let wang_id = u32::from_str_radix(wang_id.trim_start_matches("0x"), 16).ok()?;
+        let color = |index: u32| (wang_id >> (index * 8 + 4)) & 0xf;
+        Some([color(3), color(0), color(1), color(2)])
+    }
+}
+
+impl Map {
+    /// Paints `terrain` on the given tiles of a tiled layer and picks transition tiles
+    /// for their neighbours from `terrain_set`. Neighbouring tiles that aren't part of
+    /// the set, or whose new corners have no matching tile, are left unchanged.
+    pub fn paint_terrain(
+        &mut self,
+        layer_index: usize,
+        terrain_set: &TerrainSet,
+        tiles: &[UVec2],
+        terrain: u32,
+    ) {
+        let mut painted_corners = HashSet::default();
+        let mut affected = HashSet::default();
+        for tile in tiles {
+            for (x, y) in [(0, 0), (1, 0), (1, 1), (0, 1)].iter() {
+                painted_corners.insert(UVec2::new(tile.x + x, tile.y + y));
+            }
+            for y in tile.y.saturating_sub(1)..=tile.y + 1 {
+                for x in tile.x.saturating_sub(1)..=tile.x + 1 {
+                    affected.insert(UVec2::new(x, y));
+                }
+            }
+        }
+
+        for tile in affected {
+            let painted = tiles.contains(&tile);
+            let mut corners = match self.gid_at(layer_index, tile) {
+                Some(gid) => match terrain_set.corners_of(gid) {
+                    Some(corners) => corners,
+                    None if painted => [terrain; 4],
+                    None => continue,
+                },
+                None if painted => [terrain; 4],
+                None => continue,
+            };
+            let positions = [
+                tile,
+                UVec2::new(tile.x + 1, tile.y),
+                UVec2::new(tile.x + 1, tile.y + 1),
+                UVec2::new(tile.x, tile.y + 1),
+            ];
+            for (corner, position) in corners.iter_mut().zip(positions.iter()) {
+                if painted_corners.contains(position) {
+                    *corner = terrain;
+                }
+            }
+            if let Some(gid) = terrain_set.gid(corners) {
+                self.set_tile(layer_index, tile, gid);
+            }
+        }
+    }
+}
+
+/// Undo and redo of runtime tile edits, e.g. for in-game level editors. Each `set_tile` or
+/// `record` call is one step. Call `Map::rebuild` after undoing or redoing, as after any edit.
+#[derive(Debug, Default, Clone)]
+pub struct EditHistory {
+    undo: Vec<Vec<HistoryEdit>>,
+    redo: Vec<Vec<HistoryEdit>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HistoryEdit {
+    layer_index: usize,
+    tile: UVec2,
+    before: u32,
+    after: u32,
+}
+
+impl EditHistory {
+    /// Sets a tile like `Map::set_tile`, as one undo step.
+    pub fn set_tile(&mut self, map: &mut Map, layer_index: usize, tile: UVec2, gid: u32) -> bool {
+        self.record(map, |map| map.set_tile(layer_index, tile, gid))
+    }
+
+    /// Runs several tile edits, e.g. `random_fill` or `paint_terrain`, as one undo step.
+    /// Adding or removing layers inside it is not supported.
+    pub fn record<R>(&mut self, map: &mut Map, edit: impl FnOnce(&mut Map) -> R) -> R {
+        // every tile changed with `set_tile` has an entry in `original_tiles`
+        let before: HashMap<(usize, u32, u32), u32> = map
+            .original_tiles
+            .keys()
+            .filter_map(|&(layer_index, x, y)| {
+                let tile = map.layer_tile(layer_index, x, y)?;
+                Some(((layer_index, x, y), raw_gid(tile)))
+            })
+            .collect();
+        let result = edit(map);
+
+        let mut edits: Vec<HistoryEdit> = map
+            .original_tiles
+            .iter()
+            .filter_map(|(&(layer_index, x, y), original)| {
+                let after = raw_gid(map.layer_tile(layer_index, x, y)?);
+                let before = match before.get(&(layer_index, x, y)) {
+                    Some(before) => *before,
+                    None => raw_gid(*original),
+                };
+                if before == after {
+                    return None;
+                }
+                Some(HistoryEdit {
+                    layer_index,
+                    tile: UVec2::new(x, y),
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        if !edits.is_empty() {
+            edits.sort_by_key(|edit| (edit.layer_index, edit.tile.y, edit.tile.x));
+            self.undo.push(edits);
+            self.redo.clear();
+        }
+        result
+    }
+
+    /// Reverts the last step. Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self, map: &mut Map) -> bool {
+        let edits = match self.undo.pop() {
+            Some(edits) => edits,
+            None => return false,
+        };
+        for edit in edits.iter() {
+            map.set_tile(edit.layer_index, edit.tile, edit.before);
+        }
+        self.redo.push(edits);
+        true
+    }
+
+    /// Applies the last undone step again. Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self, map: &mut Map) -> bool {
+        let edits = match self.redo.pop() {
+            Some(edits) => edits,
+            None => return false,
+        };
+        for edit in edits.iter() {
+            map.set_tile(edit.layer_index, edit.tile, edit.after);
+        }
+        self.undo.push(edits);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all steps, e.g. after loading another map.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// Tile changes of a map relative to the tiles it was loaded with, see `Map::diff_snapshot`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileSnapshot {
+    pub tiles: Vec<TileChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileChange {
+    pub layer_index: usize,
+    pub x: u32,
+    pub y: u32,
+    /// The tile's gid, including its flip flags.
+    pub gid: u32,
+}
+
+// gid with the flip flags that `tiled::LayerTile::new` reads back
+fn raw_gid(tile: tiled::LayerTile) -> u32 {
+    let mut gid = tile.gid;
+    if tile.flip_h {
+        gid |= FLIPPED_HORIZONTALLY_FLAG;
+    }
+    if tile.flip_v {
+        gid |= FLIPPED_VERTICALLY_FLAG;
+    }
+    if tile.flip_d {
+        gid |= FLIPPED_DIAGONALLY_FLAG;
+    }
+    gid
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::fixture_map;
+    use bevy::prelude::*;
+
+    #[test]
+    fn wang_sets_are_read_from_the_tmx() {
+        let map = fixture_map("terrain.tmx");
+        let names: Vec<&str> = map
+            .terrain_sets
+            .iter()
+            .map(|terrain_set| terrain_set.name.as_str())
+            .collect();
+        assert_eq!(names, ["Ground", "Legacy"]);
+
+        let ground = &map.terrain_sets[0];
+        assert_eq!(ground.gid([2, 2, 2, 2]), Some(2));
+        assert_eq!(ground.corners_of(3), Some([1, 1, 2, 1]));
+        assert_eq!(map.terrain_sets[1].corners_of(1), Some([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn paint_terrain_picks_transition_tiles() {
+        let mut map = fixture_map("terrain.tmx");
+        let ground = map.terrain_sets[0].clone();
+        map.paint_terrain(0, &ground, &[UVec2::new(1, 1)], 2);
+
+        let gids: Vec<Vec<u32>> = (0..3)
+            .map(|y| {
+                (0..3)
+                    .map(|x| map.gid_at(0, UVec2::new(x, y)).unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(gids, [[3, 1, 1], [1, 2, 1], [1, 1, 4]]);
+    }
+}
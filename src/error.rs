@@ -0,0 +1,106 @@
+use std::{error::Error, fmt};
+
+/// Errors which can occur while loading a tiled map.
+#[derive(Debug)]
+pub enum TiledMapError {
+    /// The tmx file (or one of its external tilesets) could not be parsed.
+    ParseError(tiled::TiledError),
+    /// An external tileset file referenced by the map could not be found.
+    MissingTileset { source: String },
+    /// A tile layer uses a data encoding or compression that is not supported.
+    UnsupportedEncoding(String),
+    /// The map uses an orientation that can't be rendered.
+    UnsupportedOrientation(tiled::Orientation),
+    /// Infinite maps are not supported yet.
+    InfiniteMap,
+    /// A tileset has no `tilecount` attribute.
+    MissingTileCount { tileset: String },
+    /// A tileset has no image, or its image could not be loaded.
+    MissingImage { tileset: String },
+    /// The map file could not be read by the asset server.
+    LoadFailed(std::path::PathBuf),
+    /// The asset path has no parent folder to resolve tileset images from.
+    InvalidAssetPath(std::path::PathBuf),
+    /// A generated map has no tiles.
+    EmptyMap,
+    /// A layer or row of a generated map doesn't match the size of the first layer.
+    LayerSizeMismatch {
+        layer: usize,
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+}
+
+impl fmt::Display for TiledMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledMapError::ParseError(e) => write!(f, "failed to parse map: {}", e),
+            TiledMapError::MissingTileset { source } => {
+                write!(f, "tileset file {} not found", source)
+            }
+            TiledMapError::UnsupportedEncoding(message) => {
+                write!(f, "unsupported layer data: {}", message)
+            }
+            TiledMapError::UnsupportedOrientation(orientation) => {
+                write!(f, "unsupported orientation {:?}", orientation)
+            }
+            TiledMapError::InfiniteMap => write!(f, "infinite maps are not supported"),
+            TiledMapError::MissingTileCount { tileset } => {
+                write!(f, "tileset \"{}\" has no tile count", tileset)
+            }
+            TiledMapError::MissingImage { tileset } => {
+                write!(f, "tileset \"{}\" has no image", tileset)
+            }
+            TiledMapError::LoadFailed(path) => {
+                write!(f, "could not read map file {}", path.display())
+            }
+            TiledMapError::InvalidAssetPath(path) => {
+                write!(f, "invalid map asset path {}", path.display())
+            }
+            TiledMapError::EmptyMap => write!(f, "generated map has no tiles"),
+            TiledMapError::LayerSizeMismatch {
+                layer,
+                expected,
+                found,
+            } => write!(
+                f,
+                "layer {} is {}x{} tiles, expected {}x{}",
+                layer, found.0, found.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl Error for TiledMapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TiledMapError::ParseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// tiled only reports these failures as messages, so they are recognized by their text
+impl From<tiled::TiledError> for TiledMapError {
+    fn from(e: tiled::TiledError) -> Self {
+        match e {
+            tiled::TiledError::Other(message)
+                if message.starts_with("External tileset file not found") =>
+            {
+                let source = message
+                    .split_once(": ")
+                    .map(|(_, path)| path)
+                    .unwrap_or_default()
+                    .trim_matches('"')
+                    .to_string();
+                TiledMapError::MissingTileset { source }
+            }
+            tiled::TiledError::Other(message)
+                if message.contains("encoding") || message.starts_with("XML format") =>
+            {
+                TiledMapError::UnsupportedEncoding(message)
+            }
+            e => TiledMapError::ParseError(e),
+        }
+    }
+}
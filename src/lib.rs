@@ -1,26 +1,282 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, render::RenderStage};
 
+mod animation;
+pub use animation::*;
+mod bake;
+pub use bake::*;
+mod camera;
+pub use camera::*;
+mod colliders;
+pub use colliders::*;
+mod config;
+pub use config::*;
+mod culling;
+pub use culling::*;
+mod debug;
+pub use debug::*;
+mod delta;
+pub use delta::*;
+mod edit;
+pub use edit::*;
+mod error;
+pub use error::*;
 mod loader;
+pub use loader::ExternalTileset;
 mod map;
 pub use map::*;
+mod mipmaps;
+pub use mipmaps::*;
+mod nav;
+pub use nav::*;
+mod objects;
+pub use objects::*;
+mod picking;
+pub use picking::*;
 mod pipeline;
 pub use pipeline::*;
+mod progress;
+pub use progress::*;
+mod properties;
+pub use properties::*;
+mod query;
+pub use query::*;
+mod raycast;
+pub use raycast::*;
+mod spawners;
+pub use spawners::*;
+mod states;
+pub use states::*;
+mod stats;
+pub use stats::*;
+mod streaming;
+pub use streaming::*;
+mod thumbnail;
+mod tileset_array;
+pub use tileset_array::*;
+mod triggers;
+pub use triggers::*;
 mod tile_map;
 pub use tile_map::*;
+mod validate;
+pub use validate::*;
+#[cfg(test)]
+mod test_maps;
 
-/// Adds support for GLTF file loading to Apps
-#[derive(Default)]
-pub struct TiledMapPlugin;
+/// Labels of the systems added by `TiledObjectPlugin`, to order user systems around them.
+/// Entities are spawned through `Commands`, so they exist from the end of the stage on, while
+/// `ObjectReadyEvent`s and `MapReadyEvent`s can be read right after `SpawnObjects`.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TiledSystem {
+    /// Builds loaded maps and spawns their chunks.
+    ProcessMaps,
+    /// Spawns map objects and sends their `ObjectReadyEvent`s, currently the same system as
+    /// `ProcessMaps`.
+    SpawnObjects,
+    /// Updates `MapLoadProgress`.
+    TrackProgress,
+    /// Updates `TiledLoadState`, which systems that change states on it should run after.
+    UpdateLoadState,
+}
+
+#[derive(StageLabel, Debug, Clone, PartialEq, Eq, Hash)]
+enum TiledRenderStage {
+    MipmappedTextures,
+}
+
+/// Adds support for Tiled maps to Apps: `TiledObjectPlugin`, plus `TiledRenderPlugin` unless
+/// `TiledConfig::headless` is set.
+#[derive(Default, Clone)]
+pub struct TiledMapPlugin {
+    objects: TiledObjectPlugin,
+}
+
+impl TiledMapPlugin {
+    /// Processes maps in the given stage instead of `CoreStage::Update`, see
+    /// `TiledObjectPlugin::in_stage`.
+    pub fn in_stage(stage: impl StageLabel) -> Self {
+        Self {
+            objects: TiledObjectPlugin::in_stage(stage),
+        }
+    }
+}
 
 impl Plugin for TiledMapPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_asset::<map::Map>()
+        app.add_plugin(self.objects.clone());
+        let headless = app.world().get_resource::<TiledConfig>().unwrap().headless;
+        if !headless {
+            app.add_plugin(TiledRenderPlugin);
+        }
+    }
+}
+
+/// Map loading, object spawning, streaming and events, without any rendering. Use it alone
+/// with `TiledConfig::headless` set, e.g. on servers or with another tile renderer.
+#[derive(Clone)]
+pub struct TiledObjectPlugin {
+    stage: Box<dyn StageLabel>,
+}
+
+impl TiledObjectPlugin {
+    /// Adds the `TiledSystem` systems to the given stage instead of `CoreStage::Update`, e.g. a
+    /// fixed timestep stage. The stage must be a `SystemStage` added before this plugin.
+    pub fn in_stage(stage: impl StageLabel) -> Self {
+        Self {
+            stage: Box::new(stage),
+        }
+    }
+}
+
+impl Default for TiledObjectPlugin {
+    fn default() -> Self {
+        Self::in_stage(CoreStage::Update)
+    }
+}
+
+impl Plugin for TiledObjectPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TiledConfig>()
+            .init_resource::<MapLoadProgress>()
+            .init_resource::<TiledLoadState>()
+            .init_resource::<ObjectSpawners>()
+            .init_resource::<ClassSpawners>()
+            .init_resource::<loader::MapLoadErrors>()
+            .init_resource::<loader::LoaderConfig>()
+            .add_asset::<map::Map>()
             .init_asset_loader::<loader::TiledMapLoader>()
+            .add_asset::<ExternalTileset>()
+            .init_asset_loader::<loader::ExternalTilesetLoader>()
             .add_event::<ObjectReadyEvent>()
             .add_event::<MapReadyEvent>()
-            .add_system(process_loaded_tile_maps.system());
+            .add_event::<ChunkSpawnedEvent>()
+            .add_event::<MapLoadFailedEvent>()
+            .register_type::<Object>()
+            .register_type::<RectObject>()
+            .register_type::<EllipseObject>()
+            .register_type::<PolygonObject>()
+            .register_type::<PolylineObject>()
+            .register_type::<PointObject>()
+            .register_type::<ObjectPath>()
+            .register_type::<ObjectLayer>()
+            .register_type::<MapRoot>()
+            .register_type::<TiledMapCenter>()
+            // before maps are loaded in startup systems, and again whenever the config changes
+            .add_startup_system_to_stage(
+                StartupStage::PreStartup,
+                loader::sync_loader_config.system(),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, loader::sync_loader_config.system())
+            .add_system_to_stage(CoreStage::PostUpdate, register_pooled_objects.system());
+
+        let systems = SystemSet::new()
+            .with_system(
+                process_loaded_tile_maps
+                    .system()
+                    .label(TiledSystem::ProcessMaps)
+                    .label(TiledSystem::SpawnObjects),
+            )
+            .with_system(stream_world_maps.system())
+            .with_system(reload_external_tilesets.system())
+            .with_system(despawn_removed_maps.system())
+            .with_system(
+                despawn_replaced_chunks
+                    .system()
+                    .after(TiledSystem::ProcessMaps),
+            )
+            .with_system(
+                track_map_load_progress
+                    .system()
+                    .label(TiledSystem::TrackProgress)
+                    .after(TiledSystem::ProcessMaps),
+            )
+            .with_system(
+                update_tiled_load_state
+                    .system()
+                    .label(TiledSystem::UpdateLoadState)
+                    .after(TiledSystem::TrackProgress),
+            )
+            .with_system(progress::report_map_load_failures.system());
+        app.app
+            .schedule
+            .get_stage_mut::<SystemStage>(&*self.stage)
+            .unwrap_or_else(|| panic!("bevy_tiled: no system stage {:?}", self.stage))
+            .add_system_set(systems);
 
+        let headless = app.world().get_resource::<TiledConfig>().unwrap().headless;
+        if headless {
+            // without the render plugins these asset storages don't exist
+            add_asset_if_missing::<Mesh>(app);
+            add_asset_if_missing::<ColorMaterial>(app);
+            add_asset_if_missing::<TextureAtlas>(app);
+        }
+    }
+}
+
+/// Renders the chunks and objects spawned by `TiledObjectPlugin`.
+#[derive(Default)]
+pub struct TiledRenderPlugin;
+
+impl Plugin for TiledRenderPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.register_type::<TileMapChunk>()
+            .add_asset::<TilesetArray>()
+            .add_event::<TileAnimationFrameEvent>()
+            .init_resource::<TileAnimationClock>()
+            .add_system_to_stage(CoreStage::PreUpdate, advance_tile_animation_clock.system())
+            .init_resource::<MipmappedTextures>()
+            .add_system(apply_tileset_texture_filter.system())
+            .add_system(pad_tileset_textures.system())
+            .add_system(propagate_object_layer_visibility.system())
+            .add_system(apply_object_visibility.system())
+            .add_system(animate_tile_objects.system())
+            .add_system(animate_tile_layers.system())
+            .add_system(unload_distant_chunks.system())
+            .add_system(bake_static_layers.system())
+            .add_system(use_tileset_arrays.system())
+            .add_system(apply_layer_tints.system())
+            .add_system(scroll_layer_uvs.system())
+            .add_system(apply_layer_visibility.system())
+            .add_system(apply_map_pipelines.system())
+            .add_system(use_opaque_pipelines.system())
+            .add_system(clamp_cameras_to_map.system())
+            // after transforms are propagated, so sprites entering the view aren't a frame late
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                cull_outside_camera
+                    .system()
+                    .after(bevy::transform::TransformSystem::TransformPropagate),
+            );
+        if !cfg!(feature = "web") {
+            // between bevy creating render textures and the render graph systems binding them
+            app.add_stage_after(
+                RenderStage::RenderResource,
+                TiledRenderStage::MipmappedTextures,
+                SystemStage::single(mipmaps::create_mipmapped_textures.system()),
+            );
+        }
         let world = app.world_mut();
         add_tile_map_graph(world);
     }
 }
+
+/// Shows the debug shapes of all objects, as if `TiledConfig::debug_enabled` was set, and
+/// labels objects and layers once a `DebugLabels` resource is inserted.
+#[derive(Default)]
+pub struct TiledDebugPlugin;
+
+impl Plugin for TiledDebugPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TiledConfig>()
+            .add_system(spawn_debug_labels.system());
+        app.world_mut()
+            .get_resource_mut::<TiledConfig>()
+            .unwrap()
+            .debug_enabled = true;
+    }
+}
+
+fn add_asset_if_missing<T: bevy::asset::Asset>(app: &mut AppBuilder) {
+    if app.world().get_resource::<Assets<T>>().is_none() {
+        app.add_asset::<T>();
+    }
+}
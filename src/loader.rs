@@ -1,12 +1,62 @@
-use crate::map::Map;
+use crate::{map::Map, TiledConfig, TiledMapError};
 use anyhow::Result;
 use bevy::{
     asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
-    utils::BoxedFuture,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use std::{
+    io::BufReader,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
 };
 
-#[derive(Default)]
-pub struct TiledMapLoader;
+/// Errors of maps that failed to load, by asset path. The asset server only logs loader
+/// errors, so the loader keeps them here to be reported with `MapLoadFailedEvent`.
+#[derive(Default, Clone)]
+pub(crate) struct MapLoadErrors(pub(crate) Arc<Mutex<HashMap<PathBuf, TiledMapError>>>);
+
+/// The `TiledConfig` as seen by the loader, which can't access resources. Kept up to date by
+/// `sync_loader_config`, so maps are loaded with the config of the time they are loaded.
+#[derive(Clone)]
+pub(crate) struct LoaderConfig(pub(crate) Arc<RwLock<TiledConfig>>);
+
+impl FromWorld for LoaderConfig {
+    fn from_world(world: &mut World) -> Self {
+        let config = world
+            .get_resource::<TiledConfig>()
+            .cloned()
+            .unwrap_or_default();
+        Self(Arc::new(RwLock::new(config)))
+    }
+}
+
+/// Hands changes of the `TiledConfig` resource to the asset loader.
+pub(crate) fn sync_loader_config(config: Res<TiledConfig>, loader_config: Res<LoaderConfig>) {
+    if config.is_changed() {
+        *loader_config.0.write().unwrap() = config.clone();
+    }
+}
+
+pub struct TiledMapLoader {
+    config: LoaderConfig,
+    errors: MapLoadErrors,
+}
+
+impl FromWorld for TiledMapLoader {
+    fn from_world(world: &mut World) -> Self {
+        let config = world
+            .get_resource::<LoaderConfig>()
+            .cloned()
+            .unwrap_or_else(|| LoaderConfig::from_world(world));
+        let errors = world
+            .get_resource::<MapLoadErrors>()
+            .cloned()
+            .unwrap_or_default();
+        Self { config, errors }
+    }
+}
 
 impl TiledMapLoader {
     pub fn remove_tile_flags(tile: u32) -> u32 {
@@ -15,10 +65,10 @@ impl TiledMapLoader {
     }
 }
 
-const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
-const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
-const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
-const ALL_FLIP_FLAGS: u32 =
+pub(crate) const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
+pub(crate) const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
+pub(crate) const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
+pub(crate) const ALL_FLIP_FLAGS: u32 =
     FLIPPED_HORIZONTALLY_FLAG | FLIPPED_VERTICALLY_FLAG | FLIPPED_DIAGONALLY_FLAG;
 
 impl AssetLoader for TiledMapLoader {
@@ -28,14 +78,40 @@ impl AssetLoader for TiledMapLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
+            let config = self.config.0.read().unwrap().clone();
             let path = load_context.path();
-            let mut map = Map::try_from_bytes(path, bytes.into())?;
+            // external tilesets are read through the asset io as well, missing ones are
+            // reported when the map is parsed
+            let mut tilesets = HashMap::default();
+            for (tileset_path, _) in Map::external_tileset_paths(path, bytes) {
+                if let Ok(tileset) = load_context.read_asset_bytes(&tileset_path).await {
+                    tilesets.insert(tileset_path, tileset);
+                }
+            }
+            let map = Map::try_from_bytes_with_tilesets(path, bytes.into(), &tilesets, &config);
+            let mut map = match map {
+                Ok(map) => map,
+                Err(e) => {
+                    let message = e.to_string();
+                    self.errors.0.lock().unwrap().insert(path.to_path_buf(), e);
+                    return Err(anyhow::anyhow!(message));
+                }
+            };
+            // tileset images go through the asset server, so they work with any AssetIo
+            if !config.headless {
+                for tileset in map.map.tilesets.iter() {
+                    let image_path = map.tileset_image_path(tileset);
+                    let texture_handle = load_context.get_handle(AssetPath::new(image_path, None));
+                    map.tileset_textures
+                        .insert(tileset.first_gid, texture_handle);
+                }
+            }
             let dependencies = map
                 .asset_dependencies
-                .drain(..)
-                .map(|image_path| {
-                    // add tileset to dependencies
-                    AssetPath::new(image_path, None)
+                .iter()
+                .map(|dependency_path| {
+                    // add tileset images and external tilesets to dependencies
+                    AssetPath::new(dependency_path.clone(), None)
                 })
                 .collect();
             let loaded_asset = LoadedAsset::new(map);
@@ -49,3 +125,37 @@ impl AssetLoader for TiledMapLoader {
         EXTENSIONS
     }
 }
+
+/// An external TSX tileset. These are loaded as dependencies of the maps using them, so
+/// that changes to them are picked up by hot reload.
+#[derive(Debug, TypeUuid)]
+#[uuid = "2c1e8bd3-6a7b-4d1f-9a3e-7f3c0b5d2e61"]
+pub struct ExternalTileset {
+    /// The parsed tileset. Its `first_gid` depends on the map using it and is always 1 here.
+    pub tileset: tiled::Tileset,
+    /// The TSX text, for the tileset data tiled 0.9 doesn't parse.
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct ExternalTilesetLoader;
+
+impl AssetLoader for ExternalTilesetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tileset = tiled::parse_tileset(BufReader::new(bytes), 1)?;
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            load_context.set_default_asset(LoadedAsset::new(ExternalTileset { tileset, text }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        static EXTENSIONS: &[&str] = &["tsx"];
+        EXTENSIONS
+    }
+}
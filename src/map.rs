@@ -1,9 +1,16 @@
-use crate::{loader::TiledMapLoader, TileMapChunk, TILE_MAP_PIPELINE_HANDLE};
-use anyhow::Result;
+use crate::{
+    class_of,
+    loader::{ExternalTileset, TiledMapLoader},
+    mesh_bounds, AnimatedChunk, AnimatedTile, BlendMode, ChunkLocation, ClassSpawners,
+    CullingBounds, MapPipelines, ObjectSpawnContext, ObjectSpawners, ObjectZPolicy, PropertiesExt,
+    TerrainSet, TileAnimation, TileMapChunk, TiledConfig, TiledMapError, TriggerRegion, UvScroll,
+    BAKE_PROPERTY, OPAQUE_PROPERTY, TILE_MAP_PIPELINE_HANDLE,
+};
 use bevy::{
+    asset::LoadState,
     ecs::system::EntityCommands,
     prelude::*,
-    reflect::TypeUuid,
+    reflect::{Reflect, TypeUuid},
     render::mesh::Indices,
     render::{
         draw::Visible, mesh::VertexAttributeValues, pipeline::PrimitiveTopology,
@@ -12,16 +19,20 @@ use bevy::{
     utils::{HashMap, HashSet},
 };
 use std::{
+    collections::hash_map::{DefaultHasher, Entry},
+    fmt,
+    hash::{Hash, Hasher},
     io::BufReader,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
 };
 
 // objects include these by default for now
+pub use tiled;
+pub use tiled::LayerData;
 pub use tiled::ObjectShape;
 pub use tiled::Properties;
 pub use tiled::PropertyValue;
-pub use tiled::LayerData;
-pub use tiled;
 
 #[derive(Debug)]
 pub struct Tile {
@@ -29,15 +40,214 @@ pub struct Tile {
     pub pos: Vec2,
     pub vertex: Vec4,
     pub uv: Vec4,
+    /// Depth nudge relative to the layer, breaking ties between tiles in the map's render order.
+    /// Chunks store their tiles in that order, which decides how tiles of a chunk overlap.
+    pub z: f32,
+    pub flip_d: bool,
+    pub flip_h: bool,
+    pub flip_v: bool,
+}
+
+// f32 fields are hashed by their bit patterns, which is enough to detect changes on reload
+impl Hash for Tile {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tile_id.hash(state);
+        let pos: [f32; 2] = self.pos.into();
+        let vertex: [f32; 4] = self.vertex.into();
+        let uv: [f32; 4] = self.uv.into();
+        for value in pos
+            .iter()
+            .chain(vertex.iter())
+            .chain(uv.iter())
+            .chain(std::iter::once(&self.z))
+        {
+            value.to_bits().hash(state);
+        }
+        self.flip_d.hash(state);
+        self.flip_h.hash(state);
+        self.flip_v.hash(state);
+    }
+}
+
+/// A tile placed in a tile layer, with the flips it is drawn with, see `Map::placed_tile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedTile {
+    pub gid: u32,
+    /// The tile's id within its tileset.
+    pub local_id: u32,
+    /// First gid of the tileset, which identifies it like `ChunkLocation::tileset_guid`.
+    pub tileset_guid: u32,
     pub flip_d: bool,
     pub flip_h: bool,
     pub flip_v: bool,
 }
 
+/// UVs of the four corners of a tile quad in `Chunk::build_mesh`, in vertex order, with the
+/// tile's flips applied.
+pub(crate) fn tile_quad_uvs(uv: Vec4, flip_d: bool, flip_h: bool, flip_v: bool) -> [[f32; 2]; 4] {
+    let mut next_uvs = [
+        // X, Y
+        [uv.x, uv.w],
+        // X, Y + 1
+        [uv.x, uv.y],
+        // X + 1, Y + 1
+        [uv.z, uv.y],
+        // X + 1, Y
+        [uv.z, uv.w],
+    ];
+    // square tile flips only; hexagonal maps are rejected by the loader for now. When
+    // they are supported, flip_d/h/v encode 60° and 120° rotations there instead, and
+    // the fourth flag (0x10000000) needs to be stripped from the gid as well, which
+    // tiled 0.9 doesn't do.
+    if flip_d {
+        next_uvs.swap(0, 2);
+    }
+    if flip_h {
+        next_uvs.reverse();
+    }
+    if flip_v {
+        next_uvs.reverse();
+        next_uvs.swap(0, 2);
+        next_uvs.swap(1, 3);
+    }
+    next_uvs
+}
+
 #[derive(Debug)]
 pub struct Chunk {
     pub position: Vec2,
-    pub tiles: Vec<Vec<Tile>>,
+    /// Non-empty tiles in this chunk; `Tile::pos` is the position within the chunk.
+    pub tiles: Vec<Tile>,
+    /// The tiles were dropped after building the chunk's mesh, see
+    /// `TiledConfig::discard_chunk_data`.
+    pub discarded: bool,
+    /// Tiles of the chunk that are animated in their tileset, kept when the tiles are dropped.
+    pub animated_tiles: Vec<AnimatedTile>,
+}
+
+impl Hash for Chunk {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.position.x.to_bits().hash(state);
+        self.position.y.to_bits().hash(state);
+        self.tiles.hash(state);
+        // frames can change in the tileset without changing the tiles
+        for tile in self.animated_tiles.iter() {
+            tile.index.hash(state);
+            for (tile_id, duration) in tile.animation.frames.iter() {
+                tile_id.hash(state);
+                duration.to_bits().hash(state);
+            }
+        }
+    }
+}
+
+impl Chunk {
+    /// Builds the mesh for the tiles of this chunk, or `None` if it has no tiles.
+    pub fn build_mesh(&self) -> Option<Mesh> {
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+
+        let mut i = 0;
+        for tile in self.tiles.iter() {
+            // X, Y
+            positions.push([tile.vertex.x, tile.vertex.y, tile.z]);
+            // X, Y + 1
+            positions.push([tile.vertex.x, tile.vertex.w, tile.z]);
+            // X + 1, Y + 1
+            positions.push([tile.vertex.z, tile.vertex.w, tile.z]);
+            // X + 1, Y
+            positions.push([tile.vertex.z, tile.vertex.y, tile.z]);
+
+            uvs.extend_from_slice(&tile_quad_uvs(
+                tile.uv,
+                tile.flip_d,
+                tile.flip_h,
+                tile.flip_v,
+            ));
+
+            indices.extend_from_slice(&[i, i + 2, i + 1, i, i + 3, i + 2]);
+
+            i += 4;
+        }
+
+        if positions.is_empty() {
+            return None;
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(positions));
+        mesh.set_attribute("Vertex_Uv", VertexAttributeValues::Float2(uvs));
+        mesh.set_indices(Some(Indices::U32(indices)));
+        Some(mesh)
+    }
+
+    /// Center of the chunk's tiles, relative to the map.
+    pub fn center(&self) -> Option<Vec2> {
+        self.bounds().map(|bounds| bounds.center())
+    }
+
+    /// Bounds of the chunk's tiles relative to the map, including oversized tiles.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.tiles
+            .iter()
+            .map(|tile| Bounds {
+                min: Vec2::new(tile.vertex.x, tile.vertex.y),
+                max: Vec2::new(tile.vertex.z, tile.vertex.w),
+            })
+            .reduce(Bounds::union)
+    }
+}
+
+/// An axis aligned bounding box in pixels, with y pointing up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Bounds {
+    pub fn union(self, other: Bounds) -> Bounds {
+        Bounds {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.y >= self.min.y
+            && point.x <= self.max.x
+            && point.y <= self.max.y
+    }
+
+    /// Bounds of these bounds' corners moved by a transform.
+    pub fn transformed(&self, transform: &Transform) -> Bounds {
+        let matrix = transform.compute_matrix();
+        let corner = |x: f32, y: f32| matrix.transform_point3(Vec3::new(x, y, 0.0)).truncate();
+        let corners = [
+            corner(self.min.x, self.min.y),
+            corner(self.min.x, self.max.y),
+            corner(self.max.x, self.min.y),
+            corner(self.max.x, self.max.y),
+        ];
+        Bounds {
+            min: corners
+                .iter()
+                .fold(corners[0], |min, corner| min.min(*corner)),
+            max: corners
+                .iter()
+                .fold(corners[0], |max, corner| max.max(*corner)),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -45,24 +255,410 @@ pub struct TilesetLayer {
     pub tile_size: Vec2,
     pub chunks: Vec<Vec<Chunk>>,
     pub tileset_guid: u32,
+    /// Hash of the chunk data and the layer settings they are drawn with, used to skip
+    /// unchanged layers on hot reload.
+    pub content_hash: u64,
 }
 
 #[derive(Debug)]
 pub struct Layer {
+    pub name: String,
+    /// Index of the layer in `tiled::Map::layers`, which also includes invisible layers.
+    pub layer_index: usize,
+    /// Position of the layer among all layers in Tiled, including object and image layers,
+    /// which determines its z.
+    pub z_index: u32,
+    /// Custom properties set on the layer in Tiled.
+    pub properties: Properties,
+    /// The layer's class, from its `class` attribute or else its `class` property.
+    pub class: Option<String>,
+    pub blend_mode: BlendMode,
+    pub uv_scroll: Option<UvScroll>,
+    /// Whether the layer is shown in Tiled. Hidden layers are loaded too, e.g. for collision
+    /// data, and their chunks are spawned hidden.
+    pub visible: bool,
+    /// Bounds of the layer's tiles relative to the map, `None` if it has no tiles.
+    pub bounds: Option<Bounds>,
     pub tileset_layers: Vec<TilesetLayer>,
 }
 
+/// The order in which Tiled draws the tiles of a layer, from the map's `renderorder`.
+/// Later tiles are drawn on top where oversized tiles overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderOrder {
+    RightDown,
+    RightUp,
+    LeftDown,
+    LeftUp,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for RenderOrder {
+    fn default() -> Self {
+        RenderOrder::RightDown
+    }
+}
+
+impl RenderOrder {
+    /// Reads the `renderorder` attribute of the map element, which tiled 0.9 doesn't parse.
+    pub fn from_tmx(bytes: &[u8]) -> RenderOrder {
+        let text = String::from_utf8_lossy(bytes);
+        let value = tmx_start_tags(&text, "map")
+            .first()
+            .and_then(|tag| tmx_attribute(tag, "renderorder"));
+        match value {
+            Some("right-up") => RenderOrder::RightUp,
+            Some("left-down") => RenderOrder::LeftDown,
+            Some("left-up") => RenderOrder::LeftUp,
+            _ => RenderOrder::RightDown,
+        }
+    }
+
+    /// Position of a tile in the drawing order of a map of the given size.
+    pub fn index(&self, tile: UVec2, map_size: UVec2) -> u32 {
+        let x = match self {
+            RenderOrder::RightDown | RenderOrder::RightUp => tile.x,
+            RenderOrder::LeftDown | RenderOrder::LeftUp => map_size.x - 1 - tile.x,
+        };
+        let y = match self {
+            RenderOrder::RightDown | RenderOrder::LeftDown => tile.y,
+            RenderOrder::RightUp | RenderOrder::LeftUp => map_size.y - 1 - tile.y,
+        };
+        y * map_size.x + x
+    }
+}
+
+/// Which point of a tile object its position refers to, set per tileset in Tiled 1.4+.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectAlignment {
+    /// Bottom left on orthogonal maps and bottom on isometric maps.
+    Unspecified,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for ObjectAlignment {
+    fn default() -> Self {
+        ObjectAlignment::Unspecified
+    }
+}
+
+impl ObjectAlignment {
+    /// Reads the `objectalignment` attribute of every tileset in tmx text, by first gid.
+    /// tiled 0.9 doesn't parse it; external tilesets have to be inlined to be included.
+    pub fn from_tmx(text: &str) -> HashMap<u32, ObjectAlignment> {
+        tmx_start_tags(text, "tileset")
+            .into_iter()
+            .filter_map(|tag| {
+                let first_gid = tmx_attribute(tag, "firstgid")?.parse().ok()?;
+                let alignment = match tmx_attribute(tag, "objectalignment")? {
+                    "topleft" => ObjectAlignment::TopLeft,
+                    "top" => ObjectAlignment::Top,
+                    "topright" => ObjectAlignment::TopRight,
+                    "left" => ObjectAlignment::Left,
+                    "center" => ObjectAlignment::Center,
+                    "right" => ObjectAlignment::Right,
+                    "bottomleft" => ObjectAlignment::BottomLeft,
+                    "bottom" => ObjectAlignment::Bottom,
+                    "bottomright" => ObjectAlignment::BottomRight,
+                    _ => ObjectAlignment::Unspecified,
+                };
+                Some((first_gid, alignment))
+            })
+            .collect()
+    }
+
+    /// The aligned point as a fraction of the object's size, from its top left corner.
+    pub fn anchor(&self, orientation: tiled::Orientation) -> Vec2 {
+        match self {
+            ObjectAlignment::Unspecified => match orientation {
+                tiled::Orientation::Orthogonal => Vec2::new(0.0, 1.0),
+                _ => Vec2::new(0.5, 1.0),
+            },
+            ObjectAlignment::TopLeft => Vec2::new(0.0, 0.0),
+            ObjectAlignment::Top => Vec2::new(0.5, 0.0),
+            ObjectAlignment::TopRight => Vec2::new(1.0, 0.0),
+            ObjectAlignment::Left => Vec2::new(0.0, 0.5),
+            ObjectAlignment::Center => Vec2::new(0.5, 0.5),
+            ObjectAlignment::Right => Vec2::new(1.0, 0.5),
+            ObjectAlignment::BottomLeft => Vec2::new(0.0, 1.0),
+            ObjectAlignment::Bottom => Vec2::new(0.5, 1.0),
+            ObjectAlignment::BottomRight => Vec2::new(1.0, 1.0),
+        }
+    }
+}
+
+// 64 bit FNV-1a over little endian values, so hashes don't depend on the platform
+struct ContentHasher(u64);
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        ContentHasher(0xcbf29ce484222325)
+    }
+}
+
+impl ContentHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_u32(value.len() as u32);
+        self.write(value.as_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    // properties are sorted by name, their order in a HashMap isn't stable
+    fn write_properties(&mut self, properties: &Properties) {
+        let mut names: Vec<&String> = properties.keys().collect();
+        names.sort();
+        self.write_u32(names.len() as u32);
+        for name in names {
+            self.write_str(name);
+            match &properties[name] {
+                PropertyValue::BoolValue(value) => {
+                    self.write_u32(0);
+                    self.write_u32(*value as u32);
+                }
+                PropertyValue::FloatValue(value) => {
+                    self.write_u32(1);
+                    self.write_f32(*value);
+                }
+                PropertyValue::IntValue(value) => {
+                    self.write_u32(2);
+                    self.write_u32(*value as u32);
+                }
+                PropertyValue::ColorValue(value) => {
+                    self.write_u32(3);
+                    self.write_u32(*value);
+                }
+                PropertyValue::StringValue(value) => {
+                    self.write_u32(4);
+                    self.write_str(value);
+                }
+            }
+        }
+    }
+
+    fn write_shape(&mut self, shape: &ObjectShape) {
+        match shape {
+            ObjectShape::Rect { width, height } => {
+                self.write_u32(0);
+                self.write_f32(*width);
+                self.write_f32(*height);
+            }
+            ObjectShape::Ellipse { width, height } => {
+                self.write_u32(1);
+                self.write_f32(*width);
+                self.write_f32(*height);
+            }
+            ObjectShape::Polyline { points } => {
+                self.write_u32(2);
+                self.write_points(points);
+            }
+            ObjectShape::Polygon { points } => {
+                self.write_u32(3);
+                self.write_points(points);
+            }
+            ObjectShape::Point(x, y) => {
+                self.write_u32(4);
+                self.write_f32(*x);
+                self.write_f32(*y);
+            }
+        }
+    }
+
+    fn write_points(&mut self, points: &[(f32, f32)]) {
+        self.write_u32(points.len() as u32);
+        for (x, y) in points.iter() {
+            self.write_f32(*x);
+            self.write_f32(*y);
+        }
+    }
+}
+
+// byte offsets of the start tags with the given name in tmx text
+fn tmx_tag_offsets(text: &str, name: &str) -> Vec<usize> {
+    let open = format!("<{}", name);
+    text.match_indices(open.as_str())
+        .map(|(start, _)| start)
+        .filter(|start| {
+            text[start + open.len()..]
+                .starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        })
+        .collect()
+}
+
+// start tags with the given name in tmx text, up to their closing `>`
+pub(crate) fn tmx_start_tags<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+    tmx_tag_offsets(text, name)
+        .into_iter()
+        .filter_map(|start| {
+            let tag = &text[start..];
+            tag.find('>').map(|end| &tag[..end])
+        })
+        .collect()
+}
+
+// attributes may be preceded by any whitespace, including line breaks, and use either quote
+pub(crate) fn tmx_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.match_indices(name).find_map(|(start, _)| {
+        if !tag[..start].ends_with(char::is_whitespace) {
+            return None;
+        }
+        let value = tag[start + name.len()..]
+            .trim_start()
+            .strip_prefix('=')?
+            .trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        value[1..].split(quote).next()
+    })
+}
+
+// elements with the given name in tmx text, from their start tag up to their closing tag or
+// the next element with that name
+pub(crate) fn tmx_elements<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+    let close = format!("</{}>", name);
+    let starts = tmx_tag_offsets(text, name);
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let element = &text[*start..starts.get(index + 1).copied().unwrap_or(text.len())];
+            element
+                .find(close.as_str())
+                .map_or(element, |end| &element[..end])
+        })
+        .collect()
+}
+
+// tmx text after the map's tilesets, whose tiles can have object groups of their own
+fn tmx_map_content(text: &str) -> &str {
+    text.rfind("</tileset>").map_or(text, |end| &text[end..])
+}
+
+// a `class` attribute, falling back to the `class` property of maps made before Tiled 1.9
+fn class_from(attribute: Option<&str>, properties: &Properties) -> Option<String> {
+    attribute
+        .filter(|class| !class.is_empty())
+        .or_else(|| class_of(properties))
+        .map(String::from)
+}
+
+// Tiled 1.9 writes the type of objects as their `class`, which tiled 0.9 doesn't parse
+fn set_object_classes(map: &mut tiled::Map, text: &str) {
+    let classes: HashMap<u32, &str> = tmx_start_tags(tmx_map_content(text), "object")
+        .into_iter()
+        .filter_map(|tag| {
+            let id = tmx_attribute(tag, "id")?.parse().ok()?;
+            Some((id, tmx_attribute(tag, "class")?))
+        })
+        .collect();
+    for object in map
+        .object_groups
+        .iter_mut()
+        .flat_map(|group| group.objects.iter_mut())
+    {
+        if object.obj_type.is_empty() {
+            if let Some(class) = classes.get(&object.id) {
+                object.obj_type = class.to_string();
+            }
+        }
+    }
+}
+
+// the tileset of a TSX file as a tileset element of a map, starting at `first_gid`
+pub(crate) fn inline_tileset(tsx: &str, first_gid: u32) -> Option<String> {
+    let start = *tmx_tag_offsets(tsx, "tileset").first()?;
+    Some(format!(
+        "<tileset firstgid=\"{}\"{}",
+        first_gid,
+        &tsx[start + "<tileset".len()..]
+    ))
+}
+
+// `parallaxoriginx`/`parallaxoriginy` of the map element, added in Tiled 1.8
+fn parallax_origin_from_tmx(bytes: &[u8]) -> Vec2 {
+    let text = String::from_utf8_lossy(bytes);
+    let tag = tmx_start_tags(&text, "map")
+        .first()
+        .copied()
+        .unwrap_or_default();
+    let value = |name| {
+        tmx_attribute(tag, name)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0.0)
+    };
+    Vec2::new(value("parallaxoriginx"), value("parallaxoriginy"))
+}
+
+// resolves `.` and `..` components, so paths leaving the map folder are valid asset paths.
+// `..` at the start is kept, as there is nothing to resolve it against.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
 // An asset for maps
 #[derive(Debug, TypeUuid)]
 #[uuid = "5f6fbac8-3f52-424e-a928-561667fea074"]
 pub struct Map {
     pub map: tiled::Map,
-    pub meshes: Vec<(u32, u32, Mesh)>,
+    /// The map's class, from its `class` attribute or else its `class` property.
+    pub class: Option<String>,
+    pub render_order: RenderOrder,
+    /// The map's parallax origin in pixels, which tiled 0.9 doesn't parse. Layers don't scroll
+    /// with parallax yet, this is where parallax scrolling should be anchored.
+    pub parallax_origin: Vec2,
     pub layers: Vec<Layer>,
     pub groups: Vec<ObjectGroup>,
     pub tile_size: Vec2,
     pub image_folder: std::path::PathBuf,
     pub asset_dependencies: Vec<PathBuf>,
+    /// Tileset textures by the first gid of their tileset, when the map was loaded by the
+    /// asset loader. Other maps load tileset images from `image_folder` once spawned.
+    pub tileset_textures: HashMap<u32, Handle<Texture>>,
+    /// Asset paths of external TSX tilesets and the first gid they are used at,
+    /// so the map can be rebuilt when one of them changes.
+    pub external_tilesets: Vec<(PathBuf, u32)>,
+    /// Loaded tiles of the tiles edited with `set_tile`, by layer index and x/y position.
+    pub original_tiles: HashMap<(usize, u32, u32), tiled::LayerTile>,
+    /// Alignments of tile objects by the first gid of their tileset, read from the TMX.
+    pub object_alignments: HashMap<u32, ObjectAlignment>,
+    /// Corner Wang sets of the map's tilesets, read from the TMX, for `paint_terrain`.
+    pub terrain_sets: Vec<TerrainSet>,
+    // `class` attributes of the tile layers by their index in `map.layers`
+    layer_classes: Vec<Option<String>>,
 }
 
 impl Map {
@@ -88,322 +684,1133 @@ impl Map {
         let y = ((-(pos.y) / half_height) - (pos.x / half_width)) / 2.0;
         Vec2::new(x.round(), y.round())
     }
-    pub fn center(&self, origin: Transform) -> Transform {
-        let tile_size = Vec2::new(self.map.tile_width as f32, self.map.tile_height as f32);
-        let map_center = Vec2::new(self.map.width as f32 / 2.0, self.map.height as f32 / 2.0);
+    /// Converts a position relative to the map transform into the tile coordinates
+    /// it falls on, or `None` if it is outside of the map.
+    pub fn tile_at(&self, pos: Vec2) -> Option<UVec2> {
+        let tile = self.tile_coordinates(pos)?.floor();
+        if tile.x < 0.0
+            || tile.y < 0.0
+            || tile.x >= self.map.width as f32
+            || tile.y >= self.map.height as f32
+        {
+            return None;
+        }
+        Some(UVec2::new(tile.x as u32, tile.y as u32))
+    }
+
+    /// Converts a position relative to the map transform into fractional tile coordinates,
+    /// which may be outside of the map. `None` for unsupported orientations.
+    pub fn tile_coordinates(&self, pos: Vec2) -> Option<Vec2> {
+        let tile_width = self.map.tile_width as f32;
+        let tile_height = self.map.tile_height as f32;
         match self.map.orientation {
             tiled::Orientation::Orthogonal => {
-                let center = Map::project_ortho(map_center, tile_size.x, tile_size.y);
-                Transform::from_matrix(
-                    origin.compute_matrix() * Mat4::from_translation(-center.extend(0.0)),
-                )
+                Some(Map::unproject_ortho(pos, tile_width, tile_height))
             }
             tiled::Orientation::Isometric => {
-                let center = Map::project_iso(map_center, tile_size.x, tile_size.y);
-                Transform::from_matrix(
-                    origin.compute_matrix() * Mat4::from_translation(-center.extend(0.0)),
-                )
+                // same as unproject_iso, without rounding to the closest tile corner
+                let half_width = tile_width / 2.0;
+                let half_height = tile_height / 2.0;
+                let x = ((pos.x / half_width) + (-(pos.y) / half_height)) / 2.0;
+                let y = ((-(pos.y) / half_height) - (pos.x / half_width)) / 2.0;
+                Some(Vec2::new(x, y))
             }
-            _ => panic!("Unsupported orientation {:?}", self.map.orientation),
+            _ => None,
         }
     }
 
-    pub fn try_from_bytes(asset_path: &Path, bytes: Vec<u8>) -> Result<Map> {
-        let map = tiled::parse_with_path(BufReader::new(bytes.as_slice()), asset_path).unwrap();
+    /// Converts a world position into the tile it falls on, or `None` if it is outside of the map.
+    /// `map_transform` is the transform the map's chunks are placed with, see `tile_map_transform`.
+    pub fn world_to_tile(&self, world: Vec2, map_transform: &Transform) -> Option<UVec2> {
+        let local = map_transform
+            .compute_matrix()
+            .inverse()
+            .transform_point3(world.extend(0.0));
+        self.tile_at(local.truncate())
+    }
 
-        let mut layers = Vec::new();
-        let mut groups = Vec::new();
+    /// World position of the center of a tile, the inverse of `world_to_tile`.
+    /// `None` for unsupported orientations.
+    pub fn tile_to_world(&self, tile: UVec2, map_transform: &Transform) -> Option<Vec2> {
+        let local = self.tile_center(tile)?;
+        Some(
+            map_transform
+                .compute_matrix()
+                .transform_point3(local.extend(0.0))
+                .truncate(),
+        )
+    }
 
-        // this only works if gids are uniques across all maps used - todo move into ObjectGroup?
-        let mut tile_gids: HashMap<u32, u32> = Default::default();
+    /// The non-empty tiles of a tiled layer, with their tile position, the world position of
+    /// their center as in `tile_to_world`, their gid and their tile properties.
+    pub fn iter_layer_tiles<'a>(
+        &'a self,
+        layer_index: usize,
+        map_transform: &Transform,
+    ) -> impl Iterator<Item = (UVec2, Vec2, u32, Option<&'a Properties>)> + 'a {
+        let matrix = map_transform.compute_matrix();
+        let rows = match self.map.layers.get(layer_index).map(|layer| &layer.tiles) {
+            Some(tiled::LayerData::Finite(rows)) => rows.as_slice(),
+            _ => &[],
+        };
+        rows.iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(move |(x, layer_tile)| (UVec2::new(x as u32, y as u32), layer_tile.gid))
+            })
+            .filter(|(_, gid)| *gid != 0)
+            .filter_map(move |(tile, gid)| {
+                let world = matrix
+                    .transform_point3(self.tile_center(tile)?.extend(0.0))
+                    .truncate();
+                Some((tile, world, gid, self.tile_properties(gid)))
+            })
+    }
 
-        for tileset in &map.tilesets {
-            for i in tileset.first_gid..(tileset.first_gid + tileset.tilecount.unwrap_or(1)) {
-                tile_gids.insert(i, tileset.first_gid);
+    // center of a tile relative to the map, `None` for unsupported orientations
+    fn tile_center(&self, tile: UVec2) -> Option<Vec2> {
+        let tile_width = self.map.tile_width as f32;
+        let tile_height = self.map.tile_height as f32;
+        let center = Vec2::new(tile.x as f32, tile.y as f32) + Vec2::splat(0.5);
+        match self.map.orientation {
+            tiled::Orientation::Orthogonal => {
+                Some(Map::project_ortho(center, tile_width, tile_height))
+            }
+            tiled::Orientation::Isometric => {
+                Some(Map::project_iso(center, tile_width, tile_height))
             }
+            _ => None,
         }
+    }
 
-        let mut object_gids: HashSet<u32> = Default::default();
-        for object_group in map.object_groups.iter() {
-            // recursively creates objects in the groups:
-            let tiled_o_g = ObjectGroup::new_with_tile_ids(object_group, &tile_gids);
-            // keep track of which objects will need to have tiles loaded
-            tiled_o_g.objects.iter().for_each(|o| {
-                tile_gids.get(&o.gid).map(|first_gid| {
-                    object_gids.insert(*first_gid);
-                });
-            });
-            groups.push(tiled_o_g);
+    /// Bounds of the map's tile grid and all of its tiles relative to the map, `None` for
+    /// unsupported orientations.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let tile_width = self.map.tile_width as f32;
+        let tile_height = self.map.tile_height as f32;
+        let size = Vec2::new(self.map.width as f32, self.map.height as f32);
+        let project = |pos: Vec2| match self.map.orientation {
+            tiled::Orientation::Orthogonal => {
+                Some(Map::project_ortho(pos, tile_width, tile_height))
+            }
+            tiled::Orientation::Isometric => Some(Map::project_iso(pos, tile_width, tile_height)),
+            _ => None,
+        };
+        let corners = [
+            project(Vec2::ZERO)?,
+            project(Vec2::new(size.x, 0.0))?,
+            project(Vec2::new(0.0, size.y))?,
+            project(size)?,
+        ];
+        let grid = Bounds {
+            min: corners
+                .iter()
+                .fold(corners[0], |min, corner| min.min(*corner)),
+            max: corners
+                .iter()
+                .fold(corners[0], |max, corner| max.max(*corner)),
+        };
+        Some(
+            self.layers
+                .iter()
+                .filter_map(|layer| layer.bounds)
+                .fold(grid, Bounds::union),
+        )
+    }
+
+    /// Bounds of the map in world space, for maps placed with `map_transform`, see
+    /// `tile_map_transform`.
+    pub fn world_bounds(&self, map_transform: &Transform) -> Option<Bounds> {
+        self.bounds()
+            .map(|bounds| bounds.transformed(map_transform))
+    }
+
+    /// Transform the chunks of a map bundle are placed with, from the bundle's `origin` and
+    /// `center`. Maps spawned with a parent are additionally placed by the parent's transform.
+    pub fn tile_map_transform(&self, origin: &Transform, center: &TiledMapCenter) -> Transform {
+        if center.0 {
+            self.center(*origin).unwrap_or(*origin)
+        } else {
+            *origin
         }
+    }
 
-        let target_chunk_x = 32;
-        let target_chunk_y = 32;
+    /// Tiles next to a tile that are inside the map, the four orthogonal ones first and then,
+    /// if `diagonal` is set, the four diagonal ones. Isometric maps share the grid of orthogonal
+    /// ones. Hexagonal and staggered maps aren't loaded yet; their six neighbours will depend on
+    /// the stagger axis and index, which tiled 0.9 doesn't parse.
+    pub fn neighbors(&self, tile: UVec2, diagonal: bool) -> Vec<UVec2> {
+        let offsets = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        offsets
+            .iter()
+            .take(if diagonal { 8 } else { 4 })
+            .map(|(x, y)| IVec2::new(tile.x as i32 + x, tile.y as i32 + y))
+            .filter(|neighbor| {
+                neighbor.x >= 0
+                    && neighbor.y >= 0
+                    && neighbor.x < self.map.width as i32
+                    && neighbor.y < self.map.height as i32
+            })
+            .map(|neighbor| UVec2::new(neighbor.x as u32, neighbor.y as u32))
+            .collect()
+    }
 
-        let chunk_size_x = (map.width as f32 / target_chunk_x as f32).ceil().max(1.0) as usize;
-        let chunk_size_y = (map.height as f32 / target_chunk_y as f32).ceil().max(1.0) as usize;
-        let tile_size = Vec2::new(map.tile_width as f32, map.tile_height as f32);
-        let image_folder: PathBuf = asset_path.parent().unwrap().into();
-        let mut asset_dependencies = Vec::new();
+    /// Returns the gid at a tile position of a tiled layer, ignoring empty tiles.
+    pub fn gid_at(&self, layer_index: usize, tile: UVec2) -> Option<u32> {
+        match &self.map.layers.get(layer_index)?.tiles {
+            tiled::LayerData::Finite(tiles) => tiles
+                .get(tile.y as usize)
+                .and_then(|row| row.get(tile.x as usize))
+                .map(|layer_tile| layer_tile.gid)
+                .filter(|gid| *gid != 0),
+            tiled::LayerData::Infinite(_) => None,
+        }
+    }
 
-        for layer in map.layers.iter() {
-            if !layer.visible {
-                continue;
-            }
-            let mut tileset_layers = Vec::new();
+    /// The tile at a tile position of a tiled layer with its tileset and flips, ignoring empty
+    /// tiles, e.g. for conveyor tiles whose direction is set by flipping them.
+    pub fn placed_tile(&self, layer_index: usize, tile: UVec2) -> Option<PlacedTile> {
+        let layer_tile = match &self.map.layers.get(layer_index)?.tiles {
+            tiled::LayerData::Finite(tiles) => *tiles.get(tile.y as usize)?.get(tile.x as usize)?,
+            tiled::LayerData::Infinite(_) => return None,
+        };
+        let tileset = self.map.get_tileset_by_gid(layer_tile.gid)?;
+        Some(PlacedTile {
+            gid: layer_tile.gid,
+            local_id: layer_tile.gid - tileset.first_gid,
+            tileset_guid: tileset.first_gid,
+            flip_d: layer_tile.flip_d,
+            flip_h: layer_tile.flip_h,
+            flip_v: layer_tile.flip_v,
+        })
+    }
 
-            for tileset in map.tilesets.iter() {
-                let tile_width = tileset.tile_width as f32;
-                let tile_height = tileset.tile_height as f32;
-                let tile_space = tileset.spacing as f32;
-                let image = tileset.images.first().unwrap();
-                let texture_width = image.width as f32;
-                let texture_height = image.height as f32;
-                let columns = ((texture_width + tile_space) / (tile_width + tile_space)).floor(); // account for no end tile
+    /// The tile data of a gid in its tileset, if anything was set on that tile in Tiled.
+    pub fn tileset_tile(&self, gid: u32) -> Option<&tiled::Tile> {
+        let tileset = self.map.get_tileset_by_gid(gid)?;
+        let id = gid - tileset.first_gid;
+        tileset.tiles.iter().find(|tile| tile.id == id)
+    }
 
-                let tile_path = image_folder.join(tileset.images.first().unwrap().source.as_str());
-                asset_dependencies.push(tile_path);
+    /// Custom properties set on a tile in its tileset.
+    pub fn tile_properties(&self, gid: u32) -> Option<&Properties> {
+        self.tileset_tile(gid).map(|tile| &tile.properties)
+    }
 
-                let mut chunks = Vec::new();
-                // 32 x 32 tile chunk sizes
-                for chunk_x in 0..chunk_size_x {
-                    let mut chunks_y = Vec::new();
-                    for chunk_y in 0..chunk_size_y {
-                        let mut tiles = Vec::new();
+    /// Custom properties set on a tileset in Tiled, by its first gid, e.g. a footstep sound set.
+    pub fn tileset_properties(&self, first_gid: u32) -> Option<&Properties> {
+        self.tileset(first_gid).map(|tileset| &tileset.properties)
+    }
 
-                        for tile_x in 0..target_chunk_x {
-                            let mut tiles_y = Vec::new();
-                            for tile_y in 0..target_chunk_y {
-                                let lookup_x = (chunk_x * target_chunk_x) + tile_x;
-                                let lookup_y = (chunk_y * target_chunk_y) + tile_y;
+    /// Name of a tileset in Tiled, by its first gid.
+    pub fn tileset_name(&self, first_gid: u32) -> Option<&str> {
+        self.tileset(first_gid).map(|tileset| tileset.name.as_str())
+    }
 
-                                // Get chunk tile.
-                                let chunk_tile = if lookup_x < map.width as usize
-                                    && lookup_y < map.height as usize
-                                {
-                                    // New Tiled crate code:
-                                    let map_tile = match &layer.tiles {
-                                        tiled::LayerData::Finite(tiles) => {
-                                            &tiles[lookup_y][lookup_x]
-                                        }
-                                        _ => panic!("Infinte maps not supported"),
-                                    };
-
-                                    let tile = map_tile.gid;
-                                    if tile < tileset.first_gid
-                                        || tile >= tileset.first_gid + tileset.tilecount.unwrap()
-                                    {
-                                        continue;
-                                    }
+    fn tileset(&self, first_gid: u32) -> Option<&tiled::Tileset> {
+        self.map
+            .tilesets
+            .iter()
+            .find(|tileset| tileset.first_gid == first_gid)
+    }
 
-                                    let tile = (TiledMapLoader::remove_tile_flags(tile) as f32)
-                                        - tileset.first_gid as f32;
-
-                                    // This calculation is much simpler we only care about getting the remainder
-                                    // and multiplying that by the tile width.
-                                    let sprite_sheet_x: f32 =
-                                        ((tile % columns) * (tile_width + tile_space) - tile_space)
-                                            .floor();
-
-                                    // Calculation here is (tile / columns).round_down * (tile_space + tile_height) - tile_space
-                                    // Example: tile 30 / 28 columns = 1.0714 rounded down to 1 * 16 tile_height = 16 Y
-                                    // which is the 2nd row in the sprite sheet.
-                                    // Example2: tile 10 / 28 columns = 0.3571 rounded down to 0 * 16 tile_height = 0 Y
-                                    // which is the 1st row in the sprite sheet.
-                                    let sprite_sheet_y: f32 = (tile / columns).floor()
-                                        * (tile_height + tile_space)
-                                        - tile_space;
-
-                                    // Calculate positions
-                                    let (start_x, end_x, start_y, end_y) = match map.orientation {
-                                        tiled::Orientation::Orthogonal => {
-                                            let center = Map::project_ortho(
-                                                Vec2::new(lookup_x as f32, lookup_y as f32),
-                                                tile_width,
-                                                tile_height,
-                                            );
-
-                                            let start = Vec2::new(
-                                                center.x,
-                                                center.y - tile_height - tile_space,
-                                            );
-
-                                            let end = Vec2::new(
-                                                center.x + tile_width + tile_space,
-                                                center.y,
-                                            );
-
-                                            (start.x, end.x, start.y, end.y)
-                                        }
-                                        tiled::Orientation::Isometric => {
-                                            let center = Map::project_iso(
-                                                Vec2::new(lookup_x as f32, lookup_y as f32),
-                                                tile_width,
-                                                tile_height,
-                                            );
-
-                                            let start = Vec2::new(
-                                                center.x - tile_width / 2.0,
-                                                center.y - tile_height,
-                                            );
-
-                                            let end =
-                                                Vec2::new(center.x + tile_width / 2.0, center.y);
-
-                                            (start.x, end.x, start.y, end.y)
-                                        }
-                                        _ => {
-                                            panic!("Unsupported orientation {:?}", map.orientation)
-                                        }
-                                    };
-
-                                    // Calculate UV:
-                                    let start_u: f32 = sprite_sheet_x / texture_width;
-                                    let end_u: f32 = (sprite_sheet_x + tile_width) / texture_width;
-                                    let start_v: f32 = sprite_sheet_y / texture_height;
-                                    let end_v: f32 =
-                                        (sprite_sheet_y + tile_height) / texture_height;
+    /// Collision shapes drawn on a tile in the tileset's collision editor. Each object's `x`/`y`
+    /// is its offset in pixels from the tile's top-left corner, and `shape` its outline.
+    pub fn tile_collision(&self, gid: u32) -> Option<&[tiled::Object]> {
+        self.tileset_tile(gid)?
+            .objectgroup
+            .as_ref()
+            .map(|group| group.objects.as_slice())
+    }
 
-                                    Tile {
-                                        tile_id: map_tile.gid,
-                                        pos: Vec2::new(tile_x as f32, tile_y as f32),
-                                        vertex: Vec4::new(start_x, start_y, end_x, end_y),
-                                        uv: Vec4::new(start_u, start_v, end_u, end_v),
-                                        flip_d: map_tile.flip_d,
-                                        flip_h: map_tile.flip_h,
-                                        flip_v: map_tile.flip_v,
-                                    }
-                                } else {
-                                    // Empty tile
-                                    Tile {
-                                        tile_id: 0,
-                                        pos: Vec2::new(tile_x as f32, tile_y as f32),
-                                        vertex: Vec4::new(0.0, 0.0, 0.0, 0.0),
-                                        uv: Vec4::new(0.0, 0.0, 0.0, 0.0),
-                                        flip_d: false,
-                                        flip_h: false,
-                                        flip_v: false,
-                                    }
-                                };
+    /// Finds a loaded layer by its name in Tiled.
+    pub fn layer_by_name(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
 
-                                tiles_y.push(chunk_tile);
-                            }
-                            tiles.push(tiles_y);
-                        }
+    /// Objects of a type in Tiled, across all object layers.
+    pub fn objects_of_type<'a>(&'a self, obj_type: &'a str) -> impl Iterator<Item = &'a Object> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.objects.iter())
+            .filter(move |object| object.obj_type == obj_type)
+    }
+
+    /// Custom properties of a loaded layer, by the `layer_id` used for chunk entities.
+    pub fn layer_properties(&self, layer_id: usize) -> Option<&Properties> {
+        self.layers.get(layer_id).map(|layer| &layer.properties)
+    }
+
+    /// Transform that centers the map on `origin`. `None` for unsupported orientations.
+    pub fn center(&self, origin: Transform) -> Option<Transform> {
+        let tile_size = Vec2::new(self.map.tile_width as f32, self.map.tile_height as f32);
+        let map_center = Vec2::new(self.map.width as f32 / 2.0, self.map.height as f32 / 2.0);
+        match self.map.orientation {
+            tiled::Orientation::Orthogonal => {
+                let center = Map::project_ortho(map_center, tile_size.x, tile_size.y);
+                Some(Transform::from_matrix(
+                    origin.compute_matrix() * Mat4::from_translation(-center.extend(0.0)),
+                ))
+            }
+            tiled::Orientation::Isometric => {
+                let center = Map::project_iso(map_center, tile_size.x, tile_size.y);
+                Some(Transform::from_matrix(
+                    origin.compute_matrix() * Mat4::from_translation(-center.extend(0.0)),
+                ))
+            }
+            _ => {
+                warn!(
+                    "bevy_tiled: can't center maps with {:?} orientation",
+                    self.map.orientation
+                );
+                None
+            }
+        }
+    }
+
+    pub fn try_from_bytes(
+        asset_path: &Path,
+        bytes: Vec<u8>,
+        config: &TiledConfig,
+    ) -> Result<Map, TiledMapError> {
+        // external tilesets are read from the file system, relative to `asset_path`
+        let tilesets = Map::external_tileset_paths(asset_path, &bytes)
+            .into_iter()
+            .filter_map(|(path, _)| Some((path.clone(), std::fs::read(path).ok()?)))
+            .collect();
+        Map::try_from_bytes_with_tilesets(asset_path, bytes, &tilesets, config)
+    }
+
+    /// Like `try_from_bytes`, but with the contents of the map's external TSX tilesets by
+    /// their asset path (see `external_tileset_paths`) instead of reading them from disk,
+    /// so maps can be loaded from any `AssetIo`.
+    pub fn try_from_bytes_with_tilesets(
+        asset_path: &Path,
+        bytes: Vec<u8>,
+        tilesets: &HashMap<PathBuf, Vec<u8>>,
+        config: &TiledConfig,
+    ) -> Result<Map, TiledMapError> {
+        let folder = asset_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut text = String::from_utf8_lossy(&bytes).into_owned();
+        // tiled only parses tilesets from separate files when it can open them itself, so
+        // each external tileset tag is replaced by the tileset's contents
+        for tag in tmx_start_tags(&String::from_utf8_lossy(&bytes), "tileset") {
+            let first_gid = tmx_attribute(tag, "firstgid").and_then(|gid| gid.parse().ok());
+            let (source, first_gid) = match (tmx_attribute(tag, "source"), first_gid) {
+                (Some(source), Some(first_gid)) => (source, first_gid),
+                _ => continue,
+            };
+            let tileset = tilesets
+                .get(&normalize_path(&folder.join(source)))
+                .map(|tileset| String::from_utf8_lossy(tileset))
+                .ok_or_else(|| TiledMapError::MissingTileset {
+                    source: source.to_string(),
+                })?;
+            let inline = inline_tileset(&tileset, first_gid).ok_or_else(|| {
+                TiledMapError::ParseError(tiled::TiledError::Other(format!(
+                    "No tileset in {}",
+                    source
+                )))
+            })?;
+            text = text.replacen(&format!("{}>", tag), &inline, 1);
+        }
+        let mut map = tiled::parse(BufReader::new(text.as_bytes()))?;
+        set_object_classes(&mut map, &text);
+        let mut map = Map::try_from_parsed(asset_path, &bytes, map, config)?;
+        map.set_object_alignments(ObjectAlignment::from_tmx(&text));
+        map.terrain_sets = TerrainSet::from_tmx(&text);
+        map.set_classes(&text);
+        Ok(map)
+    }
+
+    // the `class` attributes of Tiled 1.9+, which tiled 0.9 doesn't parse
+    fn set_classes(&mut self, text: &str) {
+        let content = tmx_map_content(text);
+        let map_class = tmx_start_tags(text, "map")
+            .first()
+            .and_then(|tag| tmx_attribute(tag, "class"));
+        self.class = class_from(map_class, &self.map.properties);
+        self.layer_classes = tmx_start_tags(content, "layer")
+            .into_iter()
+            .map(|tag| tmx_attribute(tag, "class").map(String::from))
+            .collect();
+        for layer in self.layers.iter_mut() {
+            let class = self.layer_classes.get(layer.layer_index);
+            layer.class = class_from(class.and_then(Option::as_deref), &layer.properties);
+        }
+        let group_tags = tmx_start_tags(content, "objectgroup");
+        for (group, tag) in self.groups.iter_mut().zip(group_tags) {
+            group.class = class_from(tmx_attribute(tag, "class"), &group.properties);
+        }
+    }
+
+    // tile objects are placed by the alignment of their tileset
+    fn set_object_alignments(&mut self, alignments: HashMap<u32, ObjectAlignment>) {
+        for object in self
+            .groups
+            .iter_mut()
+            .flat_map(|group| group.objects.iter_mut())
+        {
+            object.alignment = object
+                .tileset_gid
+                .and_then(|gid| alignments.get(&gid))
+                .copied()
+                .unwrap_or(ObjectAlignment::Unspecified);
+        }
+        self.object_alignments = alignments;
+    }
+
+    // converts an object added at runtime the same way as the objects of the loaded map
+    pub(crate) fn object_from_tiled(&self, object: &tiled::Object) -> Object {
+        let mut object = Object::new_with_tile_ids(object, &Map::tile_gids(&self.map));
+        if let Some(alignment) = object
+            .tileset_gid
+            .and_then(|gid| self.object_alignments.get(&gid))
+        {
+            object.alignment = *alignment;
+        }
+        object
+    }
+
+    /// Asset paths of the external TSX tilesets a map uses, and the first gid they are used at.
+    pub fn external_tileset_paths(asset_path: &Path, bytes: &[u8]) -> Vec<(PathBuf, u32)> {
+        let folder = asset_path.parent().unwrap_or_else(|| Path::new(""));
+        let text = String::from_utf8_lossy(bytes);
+        tmx_start_tags(&text, "tileset")
+            .into_iter()
+            .filter_map(|tag| {
+                let source = tmx_attribute(tag, "source")?;
+                let first_gid = tmx_attribute(tag, "firstgid")?.parse().ok()?;
+                Some((normalize_path(&folder.join(source)), first_gid))
+            })
+            .collect()
+    }
+
+    fn try_from_parsed(
+        asset_path: &Path,
+        bytes: &[u8],
+        map: tiled::Map,
+        config: &TiledConfig,
+    ) -> Result<Map, TiledMapError> {
+        let image_folder: PathBuf = asset_path
+            .parent()
+            .ok_or_else(|| TiledMapError::InvalidAssetPath(asset_path.into()))?
+            .into();
+        let render_order = RenderOrder::from_tmx(bytes);
+
+        let mut map = Map::try_from_tiled(map, image_folder, render_order, config)?;
+        map.parallax_origin = parallax_origin_from_tmx(bytes);
+        // external tilesets are tracked for hot reload
+        map.external_tilesets = Map::external_tileset_paths(asset_path, bytes);
+        // images of external tilesets are relative to their TSX file
+        map.asset_dependencies = map.image_dependencies(config);
+        map.asset_dependencies
+            .extend(map.external_tilesets.iter().map(|(path, _)| path.clone()));
+        Ok(map)
+    }
+
+    /// Builds a map asset from an already parsed tiled map. Tileset images are resolved
+    /// relative to `image_folder`.
+    pub fn try_from_tiled(
+        map: tiled::Map,
+        image_folder: PathBuf,
+        render_order: RenderOrder,
+        config: &TiledConfig,
+    ) -> Result<Map, TiledMapError> {
+        match map.orientation {
+            tiled::Orientation::Orthogonal | tiled::Orientation::Isometric => {}
+            orientation => return Err(TiledMapError::UnsupportedOrientation(orientation)),
+        }
+        for tileset in map.tilesets.iter() {
+            if tileset.tilecount.is_none() {
+                return Err(TiledMapError::MissingTileCount {
+                    tileset: tileset.name.clone(),
+                });
+            }
+            if tileset.images.is_empty() {
+                return Err(TiledMapError::MissingImage {
+                    tileset: tileset.name.clone(),
+                });
+            }
+        }
+
+        let mut groups = Vec::new();
+
+        // gids are only unique within a map, so tile ids are resolved per map
+        let tile_gids = Map::tile_gids(&map);
+
+        let mut object_gids: HashSet<u32> = Default::default();
+        // tiled 0.9 skips group layer tags but still parses the layers nested in them, so
+        // object groups inside group layers are already flattened into `object_groups`
+        for object_group in map.object_groups.iter() {
+            // recursively creates objects in the groups:
+            let tiled_o_g = ObjectGroup::new_with_tile_ids(object_group, &tile_gids);
+            // keep track of which objects will need to have tiles loaded
+            tiled_o_g.objects.iter().for_each(|o| {
+                tile_gids.get(&o.gid).map(|first_gid| {
+                    object_gids.insert(*first_gid);
+                });
+            });
+            groups.push(tiled_o_g);
+        }
+
+        let tile_size = Vec2::new(map.tile_width as f32, map.tile_height as f32);
+
+        let class = class_from(None, &map.properties);
+        let mut map = Map {
+            map,
+            class,
+            render_order,
+            parallax_origin: Vec2::ZERO,
+            layers: Vec::new(),
+            groups,
+            tile_size,
+            image_folder,
+            asset_dependencies: Vec::new(),
+            tileset_textures: Default::default(),
+            external_tilesets: Vec::new(),
+            original_tiles: Default::default(),
+            object_alignments: Default::default(),
+            terrain_sets: Vec::new(),
+            layer_classes: Vec::new(),
+        };
+        map.asset_dependencies = map.image_dependencies(config);
+        map.rebuild(config)?;
+
+        Ok(map)
+    }
+
+    /// Generates a new map using the tilesets of an already loaded `template` map, e.g. for
+    /// procedurally generated levels. Each entry of `layers` becomes a tile layer, given as
+    /// rows of gids indexed `[y][x]`. All layers and rows must have the same size, otherwise
+    /// `TiledMapError::LayerSizeMismatch` is returned.
+    ///
+    /// Add the result to `Assets<Map>` and spawn it with a `TiledMapBundle` like a loaded map.
+    pub fn generate(
+        template: &Map,
+        layers: Vec<Vec<Vec<u32>>>,
+        config: &TiledConfig,
+    ) -> Result<Map, TiledMapError> {
+        let height = layers.first().map(|rows| rows.len()).unwrap_or(0);
+        let width = layers
+            .first()
+            .and_then(|rows| rows.first())
+            .map(|row| row.len())
+            .unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err(TiledMapError::EmptyMap);
+        }
+        for (layer, rows) in layers.iter().enumerate() {
+            // a ragged row is reported with its own width
+            let row_width = rows
+                .iter()
+                .map(|row| row.len())
+                .find(|row_width| *row_width != width)
+                .unwrap_or(width);
+            if rows.len() != height || row_width != width {
+                return Err(TiledMapError::LayerSizeMismatch {
+                    layer,
+                    expected: (width, height),
+                    found: (row_width, rows.len()),
+                });
+            }
+        }
+
+        let map = tiled::Map {
+            version: template.map.version.clone(),
+            orientation: template.map.orientation,
+            width: width as u32,
+            height: height as u32,
+            tile_width: template.map.tile_width,
+            tile_height: template.map.tile_height,
+            tilesets: template.map.tilesets.clone(),
+            layers: layers
+                .into_iter()
+                .enumerate()
+                .map(|(layer_index, rows)| tiled::Layer {
+                    name: format!("Layer {}", layer_index + 1),
+                    opacity: 1.0,
+                    visible: true,
+                    offset_x: 0.0,
+                    offset_y: 0.0,
+                    tiles: LayerData::Finite(
+                        rows.into_iter()
+                            .map(|row| row.into_iter().map(tiled::LayerTile::new).collect())
+                            .collect(),
+                    ),
+                    properties: Default::default(),
+                    layer_index: layer_index as u32,
+                })
+                .collect(),
+            image_layers: Vec::new(),
+            object_groups: Vec::new(),
+            properties: Default::default(),
+            background_colour: None,
+            infinite: false,
+        };
+
+        let mut map = Map::try_from_tiled(
+            map,
+            template.image_folder.clone(),
+            template.render_order,
+            config,
+        )?;
+        map.parallax_origin = template.parallax_origin;
+        map.terrain_sets = template.terrain_sets.clone();
+        map.tileset_textures = template.tileset_textures.clone();
+        map.external_tilesets = template.external_tilesets.clone();
+        Ok(map)
+    }
+
+    /// Asset path of a tileset's image. Images of external tilesets are relative to their
+    /// TSX file, others to `image_folder`; `..` components are resolved.
+    pub fn tileset_image_path(&self, tileset: &tiled::Tileset) -> PathBuf {
+        let folder = self
+            .external_tilesets
+            .iter()
+            .find(|(_, first_gid)| *first_gid == tileset.first_gid)
+            .and_then(|(path, _)| path.parent())
+            .unwrap_or(&self.image_folder);
+        normalize_path(&folder.join(tileset.images[0].source.as_str()))
+    }
+
+    // tileset images are only needed for rendering
+    fn image_dependencies(&self, config: &TiledConfig) -> Vec<PathBuf> {
+        if config.headless {
+            return Vec::new();
+        }
+        self.map
+            .tilesets
+            .iter()
+            .map(|tileset| self.tileset_image_path(tileset))
+            .collect()
+    }
+
+    /// Drops the tiles of chunks once their meshes are built, keeping `map` for queries.
+    /// `rebuild` restores them. Chunks of baked layers are kept, they are drawn from their tiles.
+    pub fn discard_chunk_data(&mut self) {
+        for layer in self.layers.iter_mut() {
+            if layer.properties.get_bool(BAKE_PROPERTY) == Some(true) {
+                continue;
+            }
+            let chunks = layer
+                .tileset_layers
+                .iter_mut()
+                .flat_map(|tileset_layer| tileset_layer.chunks.iter_mut().flatten());
+            for chunk in chunks {
+                if !chunk.tiles.is_empty() {
+                    chunk.tiles = Vec::new();
+                    chunk.discarded = true;
+                }
+            }
+        }
+    }
+
+    /// Regenerates the tile layers from the tiled map data, e.g. after editing tiles at runtime.
+    /// Chunk meshes are built when the map is spawned, for the layers that changed.
+    pub fn rebuild(&mut self, config: &TiledConfig) -> Result<(), TiledMapError> {
+        let map = &self.map;
+        let tile_gids = Map::tile_gids(map);
+        let mut layers = Vec::new();
+
+        let (target_chunk_x, target_chunk_y) = if config.merge_layer_chunks {
+            (map.width.max(1) as usize, map.height.max(1) as usize)
+        } else {
+            (
+                config.chunk_size.x.max(1) as usize,
+                config.chunk_size.y.max(1) as usize,
+            )
+        };
+
+        let chunk_size_x = (map.width as f32 / target_chunk_x as f32).ceil().max(1.0) as usize;
+        let chunk_size_y = (map.height as f32 / target_chunk_y as f32).ceil().max(1.0) as usize;
+
+        // tiles are emitted in the render order, and the depth test lets later tiles of a chunk
+        // draw over earlier ones at the same depth, at any map size. They are also nudged towards
+        // the camera within the spacing between layers, which only breaks ties where the nudges
+        // are still apart in the depth buffer, e.g. between chunks.
+        let map_size = UVec2::new(map.width, map.height);
+        let z_step = config.layer_z_spacing * 0.5 / (map.width * map.height).max(1) as f32;
+
+        let occluders = if config.occlude_hidden_tiles {
+            Map::tile_occluders(map, &tile_gids)
+        } else {
+            HashMap::default()
+        };
+
+        for (layer_index, layer) in map.layers.iter().enumerate() {
+            let mut tileset_layers = Vec::new();
+            let blend_mode = BlendMode::from_properties(&layer.properties);
+            let layer_tiles = match &layer.tiles {
+                tiled::LayerData::Finite(tiles) => tiles,
+                tiled::LayerData::Infinite(_) => return Err(TiledMapError::InfiniteMap),
+            };
+
+            // only build tileset layers for tilesets this layer actually references
+            let used_tilesets: HashSet<u32> = layer_tiles
+                .iter()
+                .flat_map(|row| row.iter())
+                .filter_map(|tile| tile_gids.get(&tile.gid).cloned())
+                .collect();
+
+            for tileset in map
+                .tilesets
+                .iter()
+                .filter(|tileset| used_tilesets.contains(&tileset.first_gid))
+            {
+                let tile_width = tileset.tile_width as f32;
+                let tile_height = tileset.tile_height as f32;
+                let map_tile_width = map.tile_width as f32;
+                let map_tile_height = map.tile_height as f32;
+                let tile_count = tileset.tilecount.unwrap_or(1);
+                let (tile_margin, tile_space, image_size) =
+                    Map::tileset_image_layout(tileset, config.tile_padding());
+                let tile_margin = tile_margin as f32;
+                let tile_space = tile_space as f32;
+                let texture_width = image_size.x as f32;
+                let texture_height = image_size.y as f32;
+                let columns = Map::tileset_grid(tileset).0.max(1) as f32;
+
+                // UVs of a tile of this tileset by its id in the tileset
+                let tile_uv = |tile: f32| {
+                    // This calculation is much simpler we only care about getting the remainder
+                    // and multiplying that by the tile width.
+                    let sprite_sheet_x: f32 =
+                        ((tile % columns) * (tile_width + tile_space) + tile_margin).floor();
+
+                    // Calculation here is (tile / columns).round_down * (tile_space + tile_height) + tile_margin
+                    // Example: tile 30 / 28 columns = 1.0714 rounded down to 1 * 16 tile_height = 16 Y
+                    // which is the 2nd row in the sprite sheet.
+                    // Example2: tile 10 / 28 columns = 0.3571 rounded down to 0 * 16 tile_height = 0 Y
+                    // which is the 1st row in the sprite sheet.
+                    let sprite_sheet_y: f32 =
+                        (tile / columns).floor() * (tile_height + tile_space) + tile_margin;
+
+                    Vec4::new(
+                        sprite_sheet_x / texture_width,
+                        sprite_sheet_y / texture_height,
+                        (sprite_sheet_x + tile_width) / texture_width,
+                        (sprite_sheet_y + tile_height) / texture_height,
+                    )
+                };
+                // frames of the animated tiles of this tileset by tile id, with their UVs
+                let animations: HashMap<u32, (TileAnimation, Vec<Vec4>)> = tileset
+                    .tiles
+                    .iter()
+                    .filter_map(|tile| {
+                        let animation =
+                            TileAnimation::from_tiled(map, tileset.first_gid + tile.id)?;
+                        let frame_uvs = animation
+                            .frames
+                            .iter()
+                            .map(|(tile_id, _)| tile_uv(*tile_id as f32))
+                            .collect();
+                        Some((tile.id, (animation, frame_uvs)))
+                    })
+                    .collect();
+
+                let mut chunks = Vec::new();
+                for chunk_x in 0..chunk_size_x {
+                    let mut chunks_y = Vec::new();
+                    for chunk_y in 0..chunk_size_y {
+                        let mut tiles = Vec::new();
+
+                        // only non-empty tiles are stored, keyed by their position in the chunk
+                        for tile_x in 0..target_chunk_x {
+                            for tile_y in 0..target_chunk_y {
+                                let lookup_x = (chunk_x * target_chunk_x) + tile_x;
+                                let lookup_y = (chunk_y * target_chunk_y) + tile_y;
+
+                                if lookup_x >= map.width as usize || lookup_y >= map.height as usize
+                                {
+                                    continue;
+                                }
+
+                                let map_tile = &layer_tiles[lookup_y][lookup_x];
+
+                                let tile = map_tile.gid;
+                                if tile < tileset.first_gid
+                                    || tile >= tileset.first_gid + tile_count
+                                {
+                                    continue;
+                                }
+
+                                // tiles beneath an opaque tile at least as large are never seen
+                                let covers = occluders.get(&(lookup_x, lookup_y));
+                                let hidden = covers.iter().flat_map(|covers| covers.iter()).any(
+                                    |(z_index, size)| {
+                                        *z_index > layer.layer_index
+                                            && size.x >= tileset.tile_width
+                                            && size.y >= tileset.tile_height
+                                    },
+                                );
+                                if hidden {
+                                    continue;
+                                }
+
+                                let tile_id =
+                                    TiledMapLoader::remove_tile_flags(tile) - tileset.first_gid;
+                                let animation = animations.get(&tile_id);
+
+                                // Calculate positions. Tiles are anchored at the bottom of their
+                                // cell, so tiles larger than the map grid overhang upward and right.
+                                let (start_x, end_x, start_y, end_y) = match map.orientation {
+                                    tiled::Orientation::Orthogonal => {
+                                        let center = Map::project_ortho(
+                                            Vec2::new(lookup_x as f32, lookup_y as f32),
+                                            map_tile_width,
+                                            map_tile_height,
+                                        );
+
+                                        let start = Vec2::new(center.x, center.y - map_tile_height);
+
+                                        let end =
+                                            Vec2::new(center.x + tile_width, start.y + tile_height);
+
+                                        (start.x, end.x, start.y, end.y)
+                                    }
+                                    tiled::Orientation::Isometric => {
+                                        let center = Map::project_iso(
+                                            Vec2::new(lookup_x as f32, lookup_y as f32),
+                                            map_tile_width,
+                                            map_tile_height,
+                                        );
+
+                                        let start = Vec2::new(
+                                            center.x - tile_width / 2.0,
+                                            center.y - map_tile_height,
+                                        );
+
+                                        let end = Vec2::new(
+                                            center.x + tile_width / 2.0,
+                                            start.y + tile_height,
+                                        );
+
+                                        (start.x, end.x, start.y, end.y)
+                                    }
+                                    orientation => {
+                                        return Err(TiledMapError::UnsupportedOrientation(
+                                            orientation,
+                                        ))
+                                    }
+                                };
+
+                                let render_index = self
+                                    .render_order
+                                    .index(UVec2::new(lookup_x as u32, lookup_y as u32), map_size);
+                                tiles.push((
+                                    render_index,
+                                    animation,
+                                    Tile {
+                                        tile_id: map_tile.gid,
+                                        pos: Vec2::new(tile_x as f32, tile_y as f32),
+                                        vertex: Vec4::new(start_x, start_y, end_x, end_y),
+                                        uv: tile_uv(tile_id as f32),
+                                        z: render_index as f32 * z_step,
+                                        flip_d: map_tile.flip_d,
+                                        flip_h: map_tile.flip_h,
+                                        flip_v: map_tile.flip_v,
+                                    },
+                                ));
+                            }
+                        }
+
+                        tiles.sort_by_key(|(render_index, _, _)| *render_index);
+                        let animated_tiles = tiles
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(index, (_, animation, tile))| {
+                                let (animation, frame_uvs) = (*animation)?;
+                                Some(AnimatedTile {
+                                    index,
+                                    animation: animation.clone(),
+                                    frame_uvs: frame_uvs.clone(),
+                                    flip_d: tile.flip_d,
+                                    flip_h: tile.flip_h,
+                                    flip_v: tile.flip_v,
+                                })
+                            })
+                            .collect();
+                        let tiles = tiles.into_iter().map(|(_, _, tile)| tile).collect();
 
                         let chunk = Chunk {
                             position: Vec2::new(chunk_x as f32, chunk_y as f32),
                             tiles,
+                            animated_tiles,
+                            discarded: false,
                         };
                         chunks_y.push(chunk);
                     }
                     chunks.push(chunks_y);
                 }
 
+                let mut hasher = DefaultHasher::new();
+                chunks.hash(&mut hasher);
+                blend_mode.hash(&mut hasher);
+                layer.visible.hash(&mut hasher);
+                layer.layer_index.hash(&mut hasher);
+                // centered maps move with their size
+                map_size.hash(&mut hasher);
+                // a different image at the same gids needs a new material
+                tileset
+                    .images
+                    .first()
+                    .map(|image| &image.source)
+                    .hash(&mut hasher);
                 let tileset_layer = TilesetLayer {
                     tile_size: Vec2::new(tile_width, tile_height),
                     chunks,
                     tileset_guid: tileset.first_gid,
+                    content_hash: hasher.finish(),
                 };
                 tileset_layers.push(tileset_layer);
             }
 
-            let layer = Layer { tileset_layers };
+            let bounds = tileset_layers
+                .iter()
+                .flat_map(|tileset_layer| tileset_layer.chunks.iter().flatten())
+                .filter_map(|chunk| chunk.bounds())
+                .reduce(Bounds::union);
+            let layer = Layer {
+                name: layer.name.clone(),
+                layer_index,
+                z_index: layer.layer_index,
+                properties: layer.properties.clone(),
+                class: class_from(
+                    self.layer_classes
+                        .get(layer_index)
+                        .and_then(Option::as_deref),
+                    &layer.properties,
+                ),
+                blend_mode,
+                uv_scroll: UvScroll::from_properties(&layer.properties),
+                visible: layer.visible,
+                bounds,
+                tileset_layers,
+            };
             layers.push(layer);
         }
 
-        let mut meshes = Vec::new();
-        for (layer_id, layer) in layers.iter().enumerate() {
-            for tileset_layer in layer.tileset_layers.iter() {
-                for x in 0..tileset_layer.chunks.len() {
-                    let chunk_x = &tileset_layer.chunks[x];
-                    for y in 0..chunk_x.len() {
-                        let chunk = &chunk_x[y];
+        self.layers = layers;
+        Ok(())
+    }
 
-                        let mut positions: Vec<[f32; 3]> = Vec::new();
-                        let mut uvs: Vec<[f32; 2]> = Vec::new();
-                        let mut indices: Vec<u32> = Vec::new();
+    /// Hash of the parsed map data (map settings, tilesets, layers, objects and their custom
+    /// properties), stable across platforms and builds, e.g. to check that multiplayer peers
+    /// loaded the same map.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::default();
+        let map = &self.map;
+        hasher.write_u32(map.width);
+        hasher.write_u32(map.height);
+        hasher.write_u32(map.tile_width);
+        hasher.write_u32(map.tile_height);
+        hasher.write_u32(match map.orientation {
+            tiled::Orientation::Orthogonal => 0,
+            tiled::Orientation::Isometric => 1,
+            tiled::Orientation::Staggered => 2,
+            tiled::Orientation::Hexagonal => 3,
+        });
+        hasher.write_properties(&map.properties);
 
-                        let mut i = 0;
-                        for tile in chunk.tiles.iter().flat_map(|tiles_y| tiles_y.iter()) {
-                            if tile.tile_id < tileset_layer.tileset_guid {
-                                continue;
-                            }
+        hasher.write_u32(map.tilesets.len() as u32);
+        for tileset in map.tilesets.iter() {
+            hasher.write_u32(tileset.first_gid);
+            hasher.write_str(&tileset.name);
+            hasher.write_u32(tileset.tile_width);
+            hasher.write_u32(tileset.tile_height);
+            hasher.write_u32(tileset.spacing);
+            hasher.write_u32(tileset.margin);
+            hasher.write_u32(tileset.tilecount.unwrap_or(0));
+            hasher.write_u32(tileset.images.len() as u32);
+            for image in tileset.images.iter() {
+                hasher.write_str(&image.source);
+                hasher.write_u32(image.width as u32);
+                hasher.write_u32(image.height as u32);
+            }
+            hasher.write_properties(&tileset.properties);
+            hasher.write_u32(tileset.tiles.len() as u32);
+            for tile in tileset.tiles.iter() {
+                hasher.write_u32(tile.id);
+                hasher.write_properties(&tile.properties);
+            }
+        }
 
-                            // X, Y
-                            positions.push([tile.vertex.x, tile.vertex.y, 0.0]);
-                            // X, Y + 1
-                            positions.push([tile.vertex.x, tile.vertex.w, 0.0]);
-                            // X + 1, Y + 1
-                            positions.push([tile.vertex.z, tile.vertex.w, 0.0]);
-                            // X + 1, Y
-                            positions.push([tile.vertex.z, tile.vertex.y, 0.0]);
-
-                            let mut next_uvs = [
-                                // X, Y
-                                [tile.uv.x, tile.uv.w],
-                                // X, Y + 1
-                                [tile.uv.x, tile.uv.y],
-                                // X + 1, Y + 1
-                                [tile.uv.z, tile.uv.y],
-                                // X + 1, Y
-                                [tile.uv.z, tile.uv.w],
-                            ];
-                            if tile.flip_d {
-                                next_uvs.swap(0, 2);
-                            }
-                            if tile.flip_h {
-                                next_uvs.reverse();
-                            }
-                            if tile.flip_v {
-                                next_uvs.reverse();
-                                next_uvs.swap(0, 2);
-                                next_uvs.swap(1, 3);
-                            }
+        hasher.write_u32(map.layers.len() as u32);
+        for layer in map.layers.iter() {
+            hasher.write_str(&layer.name);
+            hasher.write_u32(layer.layer_index);
+            hasher.write_u32(layer.visible as u32);
+            hasher.write_f32(layer.opacity);
+            hasher.write_f32(layer.offset_x);
+            hasher.write_f32(layer.offset_y);
+            hasher.write_properties(&layer.properties);
+            if let LayerData::Finite(rows) = &layer.tiles {
+                for tile in rows.iter().flatten() {
+                    hasher.write_u32(tile.gid);
+                    hasher.write_u32(
+                        tile.flip_h as u32 | (tile.flip_v as u32) << 1 | (tile.flip_d as u32) << 2,
+                    );
+                }
+            }
+        }
 
-                            next_uvs.iter().for_each(|uv| uvs.push(*uv));
+        hasher.write_u32(map.object_groups.len() as u32);
+        for group in map.object_groups.iter() {
+            hasher.write_str(&group.name);
+            hasher.write_u32(group.layer_index.unwrap_or(0));
+            hasher.write_u32(group.visible as u32);
+            hasher.write_f32(group.opacity);
+            hasher.write_properties(&group.properties);
+            hasher.write_u32(group.objects.len() as u32);
+            for object in group.objects.iter() {
+                hasher.write_u32(object.id);
+                hasher.write_u32(object.gid);
+                hasher.write_str(&object.name);
+                hasher.write_str(&object.obj_type);
+                for value in [
+                    object.x,
+                    object.y,
+                    object.width,
+                    object.height,
+                    object.rotation,
+                ]
+                .iter()
+                {
+                    hasher.write_f32(*value);
+                }
+                hasher.write_u32(object.visible as u32);
+                hasher.write_shape(&object.shape);
+                hasher.write_properties(&object.properties);
+            }
+        }
 
-                            indices.extend_from_slice(&[i + 0, i + 2, i + 1, i + 0, i + 3, i + 2]);
+        hasher.write_u32(map.image_layers.len() as u32);
+        for image_layer in map.image_layers.iter() {
+            hasher.write_str(&image_layer.name);
+            hasher.write_u32(image_layer.layer_index);
+            hasher.write_u32(image_layer.visible as u32);
+            hasher.write_f32(image_layer.opacity);
+            hasher.write_f32(image_layer.offset_x);
+            hasher.write_f32(image_layer.offset_y);
+            hasher.write_str(
+                image_layer
+                    .image
+                    .as_ref()
+                    .map_or("", |image| image.source.as_str()),
+            );
+            hasher.write_properties(&image_layer.properties);
+        }
+        hasher.0
+    }
 
-                            i += 4;
-                        }
+    // maps every gid to the first gid of its tileset
+    fn tile_gids(map: &tiled::Map) -> HashMap<u32, u32> {
+        let mut tile_gids: HashMap<u32, u32> = Default::default();
+        for tileset in &map.tilesets {
+            for i in tileset.first_gid..(tileset.first_gid + tileset.tilecount.unwrap_or(1)) {
+                tile_gids.insert(i, tileset.first_gid);
+            }
+        }
+        tile_gids
+    }
 
-                        if positions.len() > 0 {
-                            let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-                            mesh.set_attribute(
-                                "Vertex_Position",
-                                VertexAttributeValues::Float3(positions),
-                            );
-                            mesh.set_attribute("Vertex_Uv", VertexAttributeValues::Float2(uvs));
-                            mesh.set_indices(Some(Indices::U32(indices)));
-                            meshes.push((layer_id as u32, tileset_layer.tileset_guid, mesh));
-                        }
+    // margin, spacing and size of a tileset's image as drawn. Images padded for mipmaps are
+    // relaid without margin, with every tile extruded by `padding` pixels on each side.
+    pub(crate) fn tileset_image_layout(
+        tileset: &tiled::Tileset,
+        padding: u32,
+    ) -> (u32, u32, UVec2) {
+        if padding == 0 {
+            let image = &tileset.images[0];
+            let size = UVec2::new(image.width.max(0) as u32, image.height.max(0) as u32);
+            return (tileset.margin, tileset.spacing, size);
+        }
+        let (columns, rows) = Map::tileset_grid(tileset);
+        let size = UVec2::new(
+            columns * (tileset.tile_width + 2 * padding),
+            rows * (tileset.tile_height + 2 * padding),
+        );
+        (padding, 2 * padding, size)
+    }
+
+    // tiles of visible layers with the `opaque` property, by position, with their layer's index
+    // among all layers and their tile size, see `TiledConfig::occlude_hidden_tiles`
+    fn tile_occluders(
+        map: &tiled::Map,
+        tile_gids: &HashMap<u32, u32>,
+    ) -> HashMap<(usize, usize), Vec<(u32, UVec2)>> {
+        let mut occluders: HashMap<(usize, usize), Vec<(u32, UVec2)>> = HashMap::default();
+        let layers = map.layers.iter().filter(|layer| {
+            layer.visible
+                && layer.properties.get_bool(OPAQUE_PROPERTY) == Some(true)
+                && BlendMode::from_properties(&layer.properties) == BlendMode::Alpha
+                // scrolled texture coordinates may show other tiles of the tileset
+                && UvScroll::from_properties(&layer.properties).is_none()
+        });
+        for layer in layers {
+            let rows = match &layer.tiles {
+                tiled::LayerData::Finite(rows) => rows,
+                tiled::LayerData::Infinite(_) => continue,
+            };
+            for (y, row) in rows.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    let tileset = tile_gids
+                        .get(&tile.gid)
+                        .and_then(|first_gid| map.get_tileset_by_gid(*first_gid));
+                    if let Some(tileset) = tileset {
+                        occluders.entry((x, y)).or_default().push((
+                            layer.layer_index,
+                            UVec2::new(tileset.tile_width, tileset.tile_height),
+                        ));
                     }
                 }
             }
         }
+        occluders
+    }
 
-        let map = Map {
-            map,
-            meshes,
-            layers,
-            groups,
-            tile_size,
-            image_folder,
-            asset_dependencies,
+    // columns and rows of tiles in a tileset image, accounting for its margin and spacing
+    pub(crate) fn tileset_grid(tileset: &tiled::Tileset) -> (u32, u32) {
+        let image = &tileset.images[0];
+        let fit = |size: i32, tile_size: u32| {
+            (size.max(0) as u32 + tileset.spacing).saturating_sub(tileset.margin)
+                / (tile_size + tileset.spacing).max(1)
         };
-
-        Ok(map)
+        (
+            fit(image.width, tileset.tile_width),
+            fit(image.height, tileset.tile_height),
+        )
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Reflect)]
+#[reflect(Component)]
 pub struct TiledMapCenter(pub bool);
 
 #[derive(Debug)]
 pub struct ObjectGroup {
     pub name: String,
-    opacity: f32,
+    pub opacity: f32,
     pub visible: bool,
+    /// Position of the group among all layers in Tiled, which determines its z.
+    pub z_index: u32,
+    pub properties: Properties,
+    /// The group's class, from its `class` attribute or else its `class` property.
+    pub class: Option<String>,
+    /// The group's color in Tiled, used to draw its debug shapes.
+    pub color: Option<Color>,
     pub objects: Vec<Object>,
 }
 
@@ -417,6 +1824,12 @@ impl ObjectGroup {
             name: inner.name.to_string(),
             opacity: inner.opacity,
             visible: inner.visible,
+            z_index: inner.layer_index.unwrap_or(0),
+            properties: inner.properties.clone(),
+            class: class_from(None, &inner.properties),
+            color: inner
+                .colour
+                .map(|colour| Color::rgb_u8(colour.red, colour.green, colour.blue)),
             objects: inner
                 .objects
                 .iter()
@@ -426,18 +1839,89 @@ impl ObjectGroup {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Marks a spawned rect object, with its size in pixels.
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct RectObject {
+    pub size: Vec2,
+}
+
+/// Marks a spawned ellipse object, with the size of its bounding box in pixels.
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct EllipseObject {
+    pub size: Vec2,
+}
+
+/// Marks a spawned polygon object, with its points relative to the object's transform (y up).
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct PolygonObject {
+    pub points: Vec<Vec2>,
+}
+
+/// Marks a spawned polyline object, with its points relative to the object's transform (y up).
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct PolylineObject {
+    pub points: Vec<Vec2>,
+}
+
+/// Points of a polyline object with the map transform applied, e.g. for patrol routes or
+/// moving platform tracks. They are in world space, or relative to the map's parent entity.
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct ObjectPath(pub Vec<Vec2>);
+
+/// Marks a spawned point object.
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct PointObject;
+
+// shape and properties are tiled types that can't be reflected
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
 pub struct Object {
+    #[reflect(ignore)]
     pub shape: tiled::ObjectShape,
+    #[reflect(ignore)]
     pub props: tiled::Properties,
     pub position: Vec2,
+    /// Rotation in degrees clockwise, as set in Tiled. It isn't applied to spawned objects yet.
+    pub rotation: f32,
+    /// The object's id in Tiled, unique within its map.
+    pub id: u32,
     pub name: String,
+    /// The object's type in Tiled, written as its class by Tiled 1.9.
+    pub obj_type: String,
     pub visible: bool,
+    /// Alignment of the object's tileset, for tile objects.
+    #[reflect(ignore)]
+    pub alignment: ObjectAlignment,
     gid: u32,                 // sprite ID from tiled::Object
     tileset_gid: Option<u32>, // AKA first_gid
     sprite_index: Option<u32>,
 }
 
+impl Default for Object {
+    fn default() -> Self {
+        Self {
+            shape: tiled::ObjectShape::Point(0.0, 0.0),
+            props: Default::default(),
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            id: 0,
+            name: String::new(),
+            obj_type: String::new(),
+            visible: true,
+            alignment: ObjectAlignment::Unspecified,
+            gid: 0,
+            tileset_gid: None,
+            sprite_index: None,
+        }
+    }
+}
+
 impl Object {
     pub fn new(original_object: &tiled::Object) -> Object {
         // println!("obj {} {}", original_object.name, original_object.visible.to_string());
@@ -446,10 +1930,14 @@ impl Object {
             props: original_object.properties.clone(),
             gid: original_object.gid, // zero for most non-tile objects
             visible: original_object.visible,
+            alignment: ObjectAlignment::Unspecified,
             tileset_gid: None,
             sprite_index: None,
             position: Vec2::new(original_object.x, original_object.y),
+            rotation: original_object.rotation,
+            id: original_object.id,
             name: original_object.name.clone(),
+            obj_type: original_object.obj_type.clone(),
         }
     }
 
@@ -457,6 +1945,11 @@ impl Object {
         self.tileset_gid.is_none()
     }
 
+    /// The gid of a tile object, 0 for other objects.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
     pub fn new_with_tile_ids(
         original_object: &tiled::Object,
         tile_gids: &HashMap<u32, u32>,
@@ -471,12 +1964,16 @@ impl Object {
         self.sprite_index = self.tileset_gid.map(|first_gid| &self.gid - first_gid);
     }
 
+    /// Places the object relative to `map_transform`. In isometric maps only the object's
+    /// position is projected, its shape is not skewed to the tile grid. `None` for unsupported
+    /// orientations.
     pub fn transform_from_map(
         &self,
         map: &tiled::Map,
         map_transform: &Transform,
         tile_scale: Option<Vec3>,
-    ) -> Transform {
+        config: &TiledConfig,
+    ) -> Option<Transform> {
         // tile scale being None means this is not a tile object
 
         // clone entire map transform
@@ -489,51 +1986,72 @@ impl Object {
         // transform.translation -= map_transform.scale * Vec3::new(map_tile_width, -map_tile_height, 0.0) / 2.0;
 
         let map_orientation: tiled::Orientation = map.orientation;
-        // replacing map Z with something far in front for objects -- should probably be configurable
-        // transform.translation.z = 1000.0;
-        let z_relative_to_map = 15.0; // used for a range of 5-25 above tile Z coordinate for items (max 20k map)
-        match self.shape {
-            tiled::ObjectShape::Rect { width, height } => {
-                match map_orientation {
-                    tiled::Orientation::Orthogonal => {
-                        let mut center_offset = Vec2::new(self.position.x, -self.position.y);
-                        match tile_scale {
-                            None => {
-                                // shape object x/y represent top left corner
-                                center_offset += Vec2::new(width, -height) / 2.0;
-                            }
-                            Some(tile_scale) => {
-                                // tile object x/y represents bottom left corner
-                                center_offset += Vec2::new(width, height) / 2.0;
-                                // tile object scale based on map scale and passed-in scale from image dimensions
-                                transform.scale = tile_scale * transform.scale;
-                            }
-                        }
-                        // apply map scale to object position, if this is a tile
-                        center_offset *= map_transform.scale.truncate();
-                        // offset transform by object position
-                        transform.translation +=
-                            center_offset.extend(z_relative_to_map - center_offset.y / 2000.0);
-                        // ^ HACK only support up to 20k pixels maps, TODO: configure in API
-                    }
-                    // tiled::Orientation::Isometric => {
-
-                    // }
-                    _ => panic!("Sorry, {:?} objects aren't supported -- please hide this object layer for now.", map_orientation),
-                }
-            }
-            tiled::ObjectShape::Ellipse {
-                width: _,
-                height: _,
-            } => {}
-            tiled::ObjectShape::Polyline { points: _ } => {}
-            tiled::ObjectShape::Polygon { points: _ } => {}
-            tiled::ObjectShape::Point(_, _) => {}
+        if let tiled::Orientation::Staggered | tiled::Orientation::Hexagonal = map_orientation {
+            warn!(
+                "bevy_tiled: can't place objects of maps with {:?} orientation",
+                map_orientation
+            );
+            return None;
         }
-        transform
-    }
-
-    pub fn spawn<'a, 'b>(
+        let tile_width = map.tile_width as f32;
+        let tile_height = map.tile_height as f32;
+        // projects an object's pixel position in Tiled to its position relative to the map
+        let project = |position: Vec2| match map_orientation {
+            // isometric object positions count tile heights along both axes of the tile grid
+            tiled::Orientation::Isometric => {
+                Map::project_iso(position / tile_height, tile_width, tile_height)
+            }
+            _ => Vec2::new(position.x, -position.y),
+        };
+        // the object's center, and the point it stands on for `ObjectZPolicy::YSortedByBase`
+        let (mut center_offset, base) = match self.shape {
+            tiled::ObjectShape::Rect { width, height } => match tile_scale {
+                None => {
+                    // shape object x/y represent top left corner
+                    (
+                        project(self.position + Vec2::new(width, height) / 2.0),
+                        project(self.position + Vec2::new(width, height)),
+                    )
+                }
+                Some(tile_scale) => {
+                    // tile object x/y is the point set by its tileset's alignment, and its
+                    // sprite stays upright on screen
+                    let anchor = self.alignment.anchor(map_orientation);
+                    // tile object scale based on map scale and passed-in scale from image dimensions
+                    transform.scale = tile_scale * transform.scale;
+                    let center = project(self.position)
+                        + Vec2::new((0.5 - anchor.x) * width, (anchor.y - 0.5) * height);
+                    (center, center - Vec2::new(0.0, height / 2.0))
+                }
+            },
+            tiled::ObjectShape::Ellipse { width, height } => {
+                // ellipse x/y represent top left corner of its bounding box
+                (
+                    project(self.position + Vec2::new(width, height) / 2.0),
+                    project(self.position + Vec2::new(width, height)),
+                )
+            }
+            // polylines, polygons and points are placed at their origin
+            tiled::ObjectShape::Polyline { .. }
+            | tiled::ObjectShape::Polygon { .. }
+            | tiled::ObjectShape::Point(_, _) => {
+                let origin = project(self.position);
+                (origin, origin)
+            }
+        };
+        // apply map scale to object position, if this is a tile
+        center_offset *= map_transform.scale.truncate();
+        let sort_y = match config.object_z {
+            ObjectZPolicy::YSortedByBase { .. } => base.y * map_transform.scale.y,
+            _ => center_offset.y,
+        };
+        // offset transform by object position
+        transform.translation += center_offset.extend(config.object_z.object_z(sort_y));
+        Some(transform)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn<'a, 'b>(
         &self,
         commands: &'b mut Commands<'a>,
         texture_atlas: Option<&Handle<TextureAtlas>>,
@@ -541,30 +2059,22 @@ impl Object {
         map_handle: Handle<Map>,
         tile_map_transform: &Transform,
         debug_config: &DebugConfig,
+        debug_material: &Handle<ColorMaterial>,
+        meshes: &mut Assets<Mesh>,
+        config: &TiledConfig,
+        opacity: f32,
     ) -> EntityCommands<'a, 'b> {
         let mut new_entity_commands = if let Some(texture_atlas) = texture_atlas {
             let sprite_index = self.sprite_index.expect("missing sprite index");
-            let tileset_gid = self.tileset_gid.expect("missing tileset");
-
-            // fetch tile for this object if it exists
-            let object_tile_size = map
-                .tilesets
-                .iter()
-                .find(|ts| ts.first_gid == tileset_gid)
-                .map(|ts| Vec2::new(ts.tile_width as f32, ts.tile_height as f32));
-            // object dimensions
-            let dims = self.dimensions();
-            // use object dimensions and tile size to determine extra scale to apply for tile objects
-            let tile_scale = if let (Some(dims), Some(size)) = (dims, object_tile_size) {
-                Some((dims / size).extend(1.0))
-            } else {
-                None
-            };
-            commands.spawn_bundle(SpriteSheetBundle {
-                transform: self.transform_from_map(&map, tile_map_transform, tile_scale),
+            let tile_scale = self.tile_scale(map);
+            let mut sprite_commands = commands.spawn_bundle(SpriteSheetBundle {
+                transform: self
+                    .transform_from_map(&map, tile_map_transform, tile_scale, config)
+                    .unwrap_or(*tile_map_transform),
                 texture_atlas: texture_atlas.clone(),
                 sprite: TextureAtlasSprite {
                     index: sprite_index,
+                    color: Color::rgba(1.0, 1.0, 1.0, opacity),
                     ..Default::default()
                 },
                 visible: Visible {
@@ -573,24 +2083,44 @@ impl Object {
                     ..Default::default()
                 },
                 ..Default::default()
+            });
+            if let Some(animation) = TileAnimation::from_tiled(map, self.gid) {
+                sprite_commands.insert(animation);
+            }
+            sprite_commands
+        } else if let Some(outline) = self.outline_mesh() {
+            let transform = self
+                .transform_from_map(map, tile_map_transform, None, config)
+                .unwrap_or(*tile_map_transform);
+            // Debug outline, drawn with the tile map pipeline.
+            commands.spawn_bundle(ChunkBundle {
+                chunk: TileMapChunk::default(),
+                material: debug_material.clone(),
+                mesh: meshes.add(outline),
+                map_parent: map_handle.clone(),
+                transform,
+                visible: Visible {
+                    is_visible: debug_config.enabled || config.debug_enabled,
+                    is_transparent: true,
+                },
+                ..Default::default()
             })
         } else {
             // commands.spawn((self.map_transform(&map.map, &tile_map_transform, None), GlobalTransform::default()))
             let dimensions = self
                 .dimensions()
                 .expect("Don't know how to handle object without dimensions");
-            let transform = self.transform_from_map(&map, &tile_map_transform, None);
+            let transform = self
+                .transform_from_map(&map, &tile_map_transform, None, config)
+                .unwrap_or(*tile_map_transform);
             commands
                 // Debug box.
                 .spawn_bundle(SpriteBundle {
-                    material: debug_config
-                        .material
-                        .clone()
-                        .unwrap_or_else(|| Handle::<ColorMaterial>::default()),
+                    material: debug_material.clone(),
                     sprite: Sprite::new(dimensions),
                     transform,
                     visible: Visible {
-                        is_visible: debug_config.enabled,
+                        is_visible: debug_config.enabled || config.debug_enabled,
                         is_transparent: true,
                         ..Default::default()
                     },
@@ -599,9 +2129,192 @@ impl Object {
         };
 
         new_entity_commands.insert_bundle((map_handle, self.clone()));
+        let tile_scale = texture_atlas.and_then(|_| self.tile_scale(map));
+        if let Some(bounds) = self.culling_bounds(tile_scale) {
+            new_entity_commands.insert(CullingBounds(bounds));
+        }
+        self.insert_shape(&mut new_entity_commands);
+        self.insert_path(&mut new_entity_commands, map, tile_map_transform, config);
+        new_entity_commands
+    }
+
+    // bounds of the object's sprite or shape relative to its transform, which for tile objects
+    // includes their tile scale
+    pub(crate) fn culling_bounds(&self, tile_scale: Option<Vec3>) -> Option<Bounds> {
+        let half_size = match (tile_scale, &self.shape) {
+            (Some(tile_scale), _) => self.dimensions()? / tile_scale.truncate() / 2.0,
+            (None, tiled::ObjectShape::Rect { width, height }) => Vec2::new(*width, *height) / 2.0,
+            _ => return mesh_bounds(&self.outline_mesh()?),
+        };
+        Some(Bounds {
+            min: -half_size,
+            max: half_size,
+        })
+    }
+
+    // adds an `ObjectPath` to polyline objects
+    fn insert_path(
+        &self,
+        entity_commands: &mut EntityCommands,
+        map: &tiled::Map,
+        tile_map_transform: &Transform,
+        config: &TiledConfig,
+    ) {
+        if let tiled::ObjectShape::Polyline { points } = &self.shape {
+            let transform = match self.transform_from_map(map, tile_map_transform, None, config) {
+                Some(transform) => transform,
+                None => return,
+            };
+            let points = points
+                .iter()
+                .map(|(x, y)| transform.mul_vec3(Vec3::new(*x, -*y, 0.0)).truncate())
+                .collect();
+            entity_commands.insert(ObjectPath(points));
+        }
+    }
+
+    // adds the marker component for the object's shape kind
+    fn insert_shape(&self, entity_commands: &mut EntityCommands) {
+        let points =
+            |points: &Vec<(f32, f32)>| points.iter().map(|(x, y)| Vec2::new(*x, -*y)).collect();
+        match &self.shape {
+            tiled::ObjectShape::Rect { width, height } => {
+                entity_commands.insert(RectObject {
+                    size: Vec2::new(*width, *height),
+                });
+            }
+            tiled::ObjectShape::Ellipse { width, height } => {
+                entity_commands.insert(EllipseObject {
+                    size: Vec2::new(*width, *height),
+                });
+            }
+            tiled::ObjectShape::Polygon { points: polygon } => {
+                entity_commands.insert(PolygonObject {
+                    points: points(polygon),
+                });
+            }
+            tiled::ObjectShape::Polyline { points: polyline } => {
+                entity_commands.insert(PolylineObject {
+                    points: points(polyline),
+                });
+            }
+            tiled::ObjectShape::Point(_, _) => {
+                entity_commands.insert(PointObject);
+            }
+        }
+    }
+
+    /// Spawns the object as a plain transform without any sprite or debug shape,
+    /// for apps running without rendering.
+    pub fn spawn_headless<'a, 'b>(
+        &self,
+        commands: &'b mut Commands<'a>,
+        map: &tiled::Map,
+        map_handle: Handle<Map>,
+        tile_map_transform: &Transform,
+        config: &TiledConfig,
+    ) -> EntityCommands<'a, 'b> {
+        let transform = self
+            .transform_from_map(map, tile_map_transform, self.tile_scale(map), config)
+            .unwrap_or(*tile_map_transform);
+        let mut new_entity_commands =
+            commands.spawn_bundle((transform, GlobalTransform::default()));
+        new_entity_commands.insert_bundle((map_handle, self.clone()));
+        self.insert_shape(&mut new_entity_commands);
+        self.insert_path(&mut new_entity_commands, map, tile_map_transform, config);
         new_entity_commands
     }
 
+    // extra scale to apply to tile objects so their sprite fills the object dimensions
+    pub(crate) fn tile_scale(&self, map: &tiled::Map) -> Option<Vec3> {
+        let tileset_gid = self.tileset_gid?;
+        // fetch tile for this object if it exists
+        let object_tile_size = map
+            .tilesets
+            .iter()
+            .find(|ts| ts.first_gid == tileset_gid)
+            .map(|ts| Vec2::new(ts.tile_width as f32, ts.tile_height as f32))?;
+        // use object dimensions and tile size to determine extra scale to apply for tile objects
+        self.dimensions()
+            .map(|dims| (dims / object_tile_size).extend(1.0))
+    }
+
+    /// Builds a line mesh tracing this object's shape, relative to its transform.
+    /// Rects are drawn as sprites instead and return `None`.
+    pub fn outline_mesh(&self) -> Option<Mesh> {
+        const ELLIPSE_SEGMENTS: usize = 32;
+        const POINT_RADIUS: f32 = 3.0;
+
+        let positions: Vec<[f32; 3]> = match &self.shape {
+            tiled::ObjectShape::Rect { .. } => return None,
+            tiled::ObjectShape::Ellipse { width, height } => (0..=ELLIPSE_SEGMENTS)
+                .map(|i| {
+                    let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                    [angle.cos() * width / 2.0, angle.sin() * height / 2.0, 0.0]
+                })
+                .collect(),
+            tiled::ObjectShape::Polyline { points } => {
+                points.iter().map(|(x, y)| [*x, -*y, 0.0]).collect()
+            }
+            tiled::ObjectShape::Polygon { points } => points
+                .iter()
+                .chain(points.first())
+                .map(|(x, y)| [*x, -*y, 0.0])
+                .collect(),
+            tiled::ObjectShape::Point(_, _) => vec![
+                [-POINT_RADIUS, 0.0, 0.0],
+                [0.0, POINT_RADIUS, 0.0],
+                [POINT_RADIUS, 0.0, 0.0],
+                [0.0, -POINT_RADIUS, 0.0],
+                [-POINT_RADIUS, 0.0, 0.0],
+            ],
+        };
+
+        let uvs = vec![[0.0, 0.0]; positions.len()];
+        let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+        mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(positions));
+        mesh.set_attribute("Vertex_Uv", VertexAttributeValues::Float2(uvs));
+        Some(mesh)
+    }
+
+    /// Builds a filled triangle mesh for polygon and ellipse objects, relative to their
+    /// transform. Self-intersecting polygons are only partially filled.
+    pub fn fill_mesh(&self) -> Option<Mesh> {
+        const ELLIPSE_SEGMENTS: usize = 32;
+
+        let (positions, indices): (Vec<[f32; 3]>, Vec<u32>) = match &self.shape {
+            tiled::ObjectShape::Ellipse { width, height } => {
+                // triangle fan around the center
+                let positions = std::iter::once([0.0, 0.0, 0.0])
+                    .chain((0..ELLIPSE_SEGMENTS).map(|i| {
+                        let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                        [angle.cos() * width / 2.0, angle.sin() * height / 2.0, 0.0]
+                    }))
+                    .collect();
+                let indices = (0..ELLIPSE_SEGMENTS as u32)
+                    .flat_map(|i| vec![0, i + 1, (i + 1) % ELLIPSE_SEGMENTS as u32 + 1])
+                    .collect();
+                (positions, indices)
+            }
+            tiled::ObjectShape::Polygon { points } => {
+                let points: Vec<Vec2> = points.iter().map(|(x, y)| Vec2::new(*x, -*y)).collect();
+                let indices = triangulate(&points);
+                (points.iter().map(|p| [p.x, p.y, 0.0]).collect(), indices)
+            }
+            _ => return None,
+        };
+        if indices.is_empty() {
+            return None;
+        }
+
+        let uvs = vec![[0.0, 0.0]; positions.len()];
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(positions));
+        mesh.set_attribute("Vertex_Uv", VertexAttributeValues::Float2(uvs));
+        mesh.set_indices(Some(Indices::U32(indices)));
+        Some(mesh)
+    }
+
     pub fn dimensions(&self) -> Option<Vec2> {
         match self.shape {
             tiled::ObjectShape::Rect { width, height }
@@ -613,6 +2326,87 @@ impl Object {
     }
 }
 
+// ear clipping triangulation of a simple polygon, returning counter-clockwise triangles
+fn triangulate(points: &[Vec2]) -> Vec<u32> {
+    let cross = |o: Vec2, a: Vec2, b: Vec2| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    let n = points.len();
+    let area: f32 = (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    let mut remaining: Vec<usize> = if area > 0.0 {
+        (0..n).collect()
+    } else {
+        (0..n).rev().collect()
+    };
+
+    let mut indices = Vec::new();
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + len - 1) % len],
+                remaining[i],
+                remaining[(i + 1) % len],
+            )
+        };
+        let ear = (0..len).find(|&i| {
+            let (prev, curr, next) = corner(i);
+            let (a, b, c) = (points[prev], points[curr], points[next]);
+            // convex corner with no other vertex inside its triangle
+            cross(a, b, c) > 0.0
+                && remaining.iter().all(|&j| {
+                    let p = points[j];
+                    j == prev
+                        || j == curr
+                        || j == next
+                        || cross(a, b, p) < 0.0
+                        || cross(b, c, p) < 0.0
+                        || cross(c, a, p) < 0.0
+                })
+        });
+        // self-intersecting or collinear polygons may have no ear left, keep what was found so far
+        let i = match ear {
+            Some(i) => i,
+            None => {
+                warn!(
+                    "bevy_tiled: polygon with {} points is not simple, {} of them are left out of its mesh",
+                    n,
+                    remaining.len()
+                );
+                return indices;
+            }
+        };
+        let (prev, curr, next) = corner(i);
+        indices.extend_from_slice(&[prev as u32, curr as u32, next as u32]);
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 {
+        indices.extend(remaining.iter().map(|i| *i as u32));
+    }
+    indices
+}
+
+/// Spawned for each visible object group; the group's objects are its children, so
+/// moving, hiding or despawning this entity applies to the whole object layer.
+// properties are a tiled type that can't be reflected
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct ObjectLayer {
+    pub name: String,
+    /// The group's class, from its `class` attribute or else its `class` property.
+    pub class: Option<String>,
+    pub opacity: f32,
+    /// The group's color in Tiled.
+    pub color: Option<Color>,
+    #[reflect(ignore)]
+    pub properties: Properties,
+}
+
+#[derive(Default, Reflect)]
+#[reflect(Component)]
 pub struct MapRoot; // used so consuming application can query for parent
 
 pub struct DebugConfig {
@@ -639,6 +2433,11 @@ pub struct TiledMapBundle {
     pub origin: Transform,
     pub center: TiledMapCenter,
     pub debug_config: DebugConfig,
+    /// Which tile and object layers to spawn.
+    pub layer_filter: LayerFilter,
+    /// Which objects to spawn from the object layers allowed by `layer_filter`.
+    pub object_filter: ObjectFilter,
+    pub pipelines: MapPipelines,
     pub created_entities: CreatedMapEntities,
 }
 
@@ -652,17 +2451,250 @@ impl Default for TiledMapBundle {
             center: TiledMapCenter::default(),
             origin: Transform::default(),
             debug_config: Default::default(),
+            layer_filter: Default::default(),
+            object_filter: Default::default(),
+            pipelines: Default::default(),
             created_entities: Default::default(),
         }
     }
 }
 
+/// Chooses the layers a `TiledMapBundle` spawns, e.g. to spawn the background and foreground
+/// layers of one map from two bundles with different parents.
+#[derive(Debug, Clone)]
+pub enum LayerFilter {
+    All,
+    /// Only layers matching any of the rules.
+    Include(Vec<LayerRule>),
+    /// All layers except those matching any of the rules.
+    Exclude(Vec<LayerRule>),
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for LayerFilter {
+    fn default() -> Self {
+        LayerFilter::All
+    }
+}
+
+impl LayerFilter {
+    pub fn allows(&self, name: &str, properties: &Properties) -> bool {
+        let matches_any =
+            |rules: &Vec<LayerRule>| rules.iter().any(|rule| rule.matches(name, properties));
+        match self {
+            LayerFilter::All => true,
+            LayerFilter::Include(rules) => matches_any(rules),
+            LayerFilter::Exclude(rules) => !matches_any(rules),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayerRule {
+    /// Layers with this name.
+    Name(String),
+    /// Layers with this boolean property set to `true`.
+    Property(String),
+}
+
+impl LayerRule {
+    pub fn matches(&self, name: &str, properties: &Properties) -> bool {
+        match self {
+            LayerRule::Name(rule_name) => rule_name == name,
+            LayerRule::Property(property) => properties.get_bool(property) == Some(true),
+        }
+    }
+}
+
+/// Chooses the objects a `TiledMapBundle` spawns, e.g. to spawn only logic objects on a server.
+#[derive(Debug, Clone)]
+pub enum ObjectFilter {
+    All,
+    /// Only objects matching any of the rules.
+    Include(Vec<ObjectRule>),
+    /// All objects except those matching any of the rules.
+    Exclude(Vec<ObjectRule>),
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for ObjectFilter {
+    fn default() -> Self {
+        ObjectFilter::All
+    }
+}
+
+impl ObjectFilter {
+    pub fn allows(&self, layer_name: &str, object: &Object) -> bool {
+        let matches_any =
+            |rules: &Vec<ObjectRule>| rules.iter().any(|rule| rule.matches(layer_name, object));
+        match self {
+            ObjectFilter::All => true,
+            ObjectFilter::Include(rules) => matches_any(rules),
+            ObjectFilter::Exclude(rules) => !matches_any(rules),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum ObjectRule {
+    /// Objects in the object layer with this name.
+    Layer(String),
+    /// Objects with this type.
+    Type(String),
+    /// Objects for which the function returns `true`.
+    Custom(Arc<dyn Fn(&Object) -> bool + Send + Sync>),
+}
+
+impl ObjectRule {
+    pub fn matches(&self, layer_name: &str, object: &Object) -> bool {
+        match self {
+            ObjectRule::Layer(name) => name == layer_name,
+            ObjectRule::Type(obj_type) => *obj_type == object.obj_type,
+            ObjectRule::Custom(predicate) => predicate(object),
+        }
+    }
+}
+
+impl fmt::Debug for ObjectRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectRule::Layer(name) => f.debug_tuple("Layer").field(name).finish(),
+            ObjectRule::Type(obj_type) => f.debug_tuple("Type").field(obj_type).finish(),
+            ObjectRule::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct CreatedMapEntities {
     // maps layer id and tileset_gid to mesh entities
     created_layer_entities: HashMap<(usize, u32), Vec<Entity>>,
-    // maps object guid to texture atlas sprite entity
-    created_object_entities: HashMap<u32, Vec<Entity>>,
+    // maps layer id and tileset_gid to the `layer_spawn_hash` the entities were built from
+    created_layer_hashes: HashMap<(usize, u32), u64>,
+    // maps object group index to its object layer entity and objects
+    created_object_groups: HashMap<usize, CreatedObjectGroup>,
+    // chunks of a layer that was respawned, kept until the new chunks can be drawn
+    replaced_layer_entities: HashMap<(usize, u32), Vec<Entity>>,
+}
+
+#[derive(Debug)]
+struct CreatedObjectGroup {
+    layer_entity: Entity,
+    object_entities: Vec<Entity>,
+    // the `object_group_spawn_hash` the objects were spawned from, once all of them are
+    spawn_hash: Option<u64>,
+}
+
+impl CreatedMapEntities {
+    // whether the entities of a layer were spawned from the same data and placement
+    fn is_layer_current(&self, layer_key: (usize, u32), spawn_hash: u64) -> bool {
+        self.created_layer_entities.contains_key(&layer_key)
+            && self.created_layer_hashes.get(&layer_key) == Some(&spawn_hash)
+    }
+
+    // whether all objects of a group were spawned from the same data and placement
+    fn is_object_group_current(&self, group_index: usize, spawn_hash: u64) -> bool {
+        self.created_object_groups
+            .get(&group_index)
+            .and_then(|created| created.spawn_hash)
+            == Some(spawn_hash)
+    }
+
+    // forgets the object groups that are no longer spawned as they were, and returns their
+    // object layer entities and objects
+    fn take_stale_object_groups(
+        &mut self,
+        spawn_hashes: &HashMap<usize, u64>,
+    ) -> Vec<CreatedObjectGroup> {
+        let stale: Vec<usize> = self
+            .created_object_groups
+            .keys()
+            .filter(|group_index| match spawn_hashes.get(group_index) {
+                Some(spawn_hash) => !self.is_object_group_current(**group_index, *spawn_hash),
+                None => true,
+            })
+            .copied()
+            .collect();
+        stale
+            .iter()
+            .filter_map(|group_index| self.created_object_groups.remove(group_index))
+            .collect()
+    }
+}
+
+// content hash of a tileset layer combined with where its chunks are placed, which also
+// depends on the bundle and the config
+fn layer_spawn_hash(
+    tileset_layer: &TilesetLayer,
+    layer: &Layer,
+    tile_map_transform: &Transform,
+    layer_z_spacing: f32,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tileset_layer.content_hash.hash(&mut hasher);
+    (layer.z_index as f32 * layer_z_spacing)
+        .to_bits()
+        .hash(&mut hasher);
+    let translation: [f32; 3] = tile_map_transform.translation.into();
+    let rotation: [f32; 4] = tile_map_transform.rotation.into();
+    let scale: [f32; 3] = tile_map_transform.scale.into();
+    for value in translation
+        .iter()
+        .chain(rotation.iter())
+        .chain(scale.iter())
+    {
+        value.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// content of an object group combined with where its objects are placed, which also depends
+// on the bundle and the config
+fn object_group_spawn_hash(
+    group: &ObjectGroup,
+    tile_map_transform: &Transform,
+    config: &TiledConfig,
+) -> u64 {
+    let mut hasher = ContentHasher::default();
+    hasher.write_str(&group.name);
+    hasher.write_u32(group.z_index);
+    hasher.write_f32(group.opacity);
+    hasher.write_str(group.class.as_deref().unwrap_or_default());
+    hasher.write_u32(group.color.is_some() as u32);
+    for value in group.color.unwrap_or_default().as_rgba_f32().iter() {
+        hasher.write_f32(*value);
+    }
+    hasher.write_properties(&group.properties);
+    hasher.write_u32(group.objects.len() as u32);
+    for object in group.objects.iter() {
+        hasher.write_u32(object.id);
+        hasher.write_u32(object.gid);
+        hasher.write_str(&object.name);
+        hasher.write_str(&object.obj_type);
+        hasher.write_f32(object.position.x);
+        hasher.write_f32(object.position.y);
+        hasher.write_f32(object.rotation);
+        hasher.write_u32(object.visible as u32);
+        hasher.write_u32(object.alignment as u32);
+        hasher.write_shape(&object.shape);
+        hasher.write_properties(&object.props);
+    }
+
+    let translation: [f32; 3] = tile_map_transform.translation.into();
+    let rotation: [f32; 4] = tile_map_transform.rotation.into();
+    let scale: [f32; 3] = tile_map_transform.scale.into();
+    for value in translation
+        .iter()
+        .chain(rotation.iter())
+        .chain(scale.iter())
+    {
+        hasher.write_f32(*value);
+    }
+    hasher.write_str(&format!("{:?}", config.object_z));
+    hasher.write_f32(config.layer_z_spacing);
+    hasher.write_u32(config.fill_shapes as u32);
+    hasher.write_u32(config.debug_enabled as u32);
+    hasher.0
 }
 
 #[derive(Bundle)]
@@ -701,16 +2733,35 @@ impl Default for ChunkBundle {
     }
 }
 
+// spawning left to do for a map entity, when `TiledConfig::max_spawns_per_frame` ran out
+#[derive(Default)]
+pub struct PendingSpawn {
+    meshes: Vec<(u32, u32, UVec2, Handle<Mesh>)>,
+    // `object_group_spawn_hash` of the groups to spawn, once stale groups are despawned
+    object_group_hashes: Option<HashMap<usize, u64>>,
+    // objects spawned so far, counted across the visible object groups
+    objects_spawned: usize,
+    // object layer entity and debug material by object group index
+    object_layers: HashMap<usize, (Entity, Handle<ColorMaterial>)>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn process_loaded_tile_maps(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    config: Res<TiledConfig>,
     mut map_events: EventReader<AssetEvent<Map>>,
+    mut drained_maps: Local<HashSet<Handle<Map>>>,
+    mut pending: Local<HashMap<Entity, PendingSpawn>>,
     mut ready_events: EventWriter<ObjectReadyEvent>,
     mut map_ready_events: EventWriter<MapReadyEvent>,
+    mut chunk_events: EventWriter<ChunkSpawnedEvent>,
     mut maps: ResMut<Assets<Map>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    object_spawners: Res<ObjectSpawners>,
+    class_spawners: Res<ClassSpawners>,
     mut query: Query<(
         Entity,
         &TiledMapCenter,
@@ -720,6 +2771,9 @@ pub fn process_loaded_tile_maps(
         &mut HashMap<u32, Handle<TextureAtlas>>,
         &Transform,
         &mut DebugConfig,
+        &LayerFilter,
+        &ObjectFilter,
+        &MapPipelines,
         &mut CreatedMapEntities,
     )>,
 ) {
@@ -730,9 +2784,14 @@ pub fn process_loaded_tile_maps(
                 changed_maps.insert(handle.clone());
             }
             AssetEvent::Modified { handle } => {
+                // draining the meshes below modifies the map, which is not a real change
+                if drained_maps.remove(handle) {
+                    continue;
+                }
                 changed_maps.insert(handle.clone());
             }
             AssetEvent::Removed { handle } => {
+                drained_maps.remove(handle);
                 // if mesh was modified and removed in the same update, ignore the modification
                 // events are ordered so future modification events are ok
                 changed_maps.remove(handle);
@@ -740,87 +2799,168 @@ pub fn process_loaded_tile_maps(
         }
     }
 
-    let mut new_meshes = HashMap::<&Handle<Map>, Vec<(u32, u32, Handle<Mesh>)>>::default();
+    let mut new_meshes = HashMap::<&Handle<Map>, Vec<(u32, u32, UVec2, Handle<Mesh>)>>::default();
 
     for changed_map in changed_maps.iter() {
         let map = maps.get_mut(changed_map).unwrap();
+        drained_maps.insert(changed_map.clone());
 
-        for (_, _, map_handle, _, mut materials_map, mut texture_atlas_map, _, _, _) in
+        for (_, _, map_handle, _, mut materials_map, mut texture_atlas_map, _, _, _, _, _, _) in
             query.iter_mut()
         {
             // only deal with currently changed map
-            if map_handle != changed_map {
+            if map_handle != changed_map || config.headless {
                 continue;
             }
 
+            // materials and atlases belong to the bundle, which may have been spawned with
+            // another map or an older version of this one using other tilesets at the same gids
+            let tileset_textures: HashMap<u32, Handle<Texture>> = map
+                .map
+                .tilesets
+                .iter()
+                .map(|tileset| {
+                    let texture_handle = match map.tileset_textures.get(&tileset.first_gid) {
+                        Some(texture_handle) => texture_handle.clone(),
+                        None => asset_server.load(map.tileset_image_path(tileset)),
+                    };
+                    (tileset.first_gid, texture_handle)
+                })
+                .collect();
+            let tile_gids = Map::tile_gids(&map.map);
+            materials_map.retain(|first_gid, material| {
+                materials
+                    .get(&*material)
+                    .and_then(|material| material.texture.as_ref())
+                    == tileset_textures.get(first_gid)
+            });
+            texture_atlas_map.retain(|gid, atlas| {
+                texture_atlases.get(&*atlas).map(|atlas| &atlas.texture)
+                    == tile_gids
+                        .get(gid)
+                        .and_then(|first_gid| tileset_textures.get(first_gid))
+            });
+
             for tileset in &map.map.tilesets {
+                let texture_handle = tileset_textures[&tileset.first_gid].clone();
                 if !materials_map.contains_key(&tileset.first_gid) {
-                    let texture_path = map
-                        .image_folder
-                        .join(tileset.images.first().unwrap().source.as_str());
-                    let texture_handle = asset_server.load(texture_path);
                     materials_map.insert(
                         tileset.first_gid,
                         materials.add(texture_handle.clone().into()),
                     );
+                }
 
-                    // only generate texture_atlas for tilesets used in objects
-                    let object_gids: Vec<_> = map
-                        .groups
-                        .iter()
-                        .flat_map(|og| og.objects.iter().map(|o| o.tileset_gid))
-                        .collect();
-                    if object_gids.contains(&Some(tileset.first_gid)) {
-                        // For simplicity use textureAtlasSprite for object layers
-                        // these insertions should be limited to sprites referenced by objects
-                        let tile_width = tileset.tile_width as f32;
-                        let tile_height = tileset.tile_height as f32;
-                        let image = tileset.images.first().unwrap();
-                        let texture_width = image.width as f32;
-                        let texture_height = image.height as f32;
-                        let columns = (texture_width / tile_width).floor() as usize;
-                        let rows = (texture_height / tile_height).floor() as usize;
-
-                        let has_new = (0..(columns * rows) as u32).fold(false, |total, next| {
-                            total || !texture_atlas_map.contains_key(&(tileset.first_gid + next))
-                        });
-                        if has_new {
-                            let atlas = TextureAtlas::from_grid(
-                                texture_handle.clone(),
-                                Vec2::new(tile_width, tile_height),
-                                columns,
-                                rows,
-                            );
-                            let atlas_handle = texture_atlases.add(atlas);
-                            for i in 0..(columns * rows) as u32 {
-                                if texture_atlas_map.contains_key(&(tileset.first_gid + i)) {
-                                    continue;
-                                }
-                                // println!("insert: {}", tileset.first_gid + i);
-                                texture_atlas_map
-                                    .insert(tileset.first_gid + i, atlas_handle.clone());
+                // only generate texture_atlas for tilesets used in objects
+                let object_gids: Vec<_> = map
+                    .groups
+                    .iter()
+                    .flat_map(|og| og.objects.iter().map(|o| o.tileset_gid))
+                    .collect();
+                if object_gids.contains(&Some(tileset.first_gid)) {
+                    // For simplicity use textureAtlasSprite for object layers
+                    // these insertions should be limited to sprites referenced by objects
+                    let tile_width = tileset.tile_width as f32;
+                    let tile_height = tileset.tile_height as f32;
+                    let (tile_margin, tile_space, image_size) =
+                        Map::tileset_image_layout(tileset, config.tile_padding());
+                    let tile_margin = tile_margin as f32;
+                    let tile_space = tile_space as f32;
+                    let texture_width = image_size.x as f32;
+                    let texture_height = image_size.y as f32;
+                    let (columns, rows) = Map::tileset_grid(tileset);
+                    let (columns, rows) = (columns as usize, rows as usize);
+
+                    let has_new = (0..(columns * rows) as u32).fold(false, |total, next| {
+                        total || !texture_atlas_map.contains_key(&(tileset.first_gid + next))
+                    });
+                    if has_new {
+                        let mut atlas = TextureAtlas::new_empty(
+                            texture_handle.clone(),
+                            Vec2::new(texture_width, texture_height),
+                        );
+                        for row in 0..rows {
+                            for column in 0..columns {
+                                let min = Vec2::new(
+                                    tile_margin + column as f32 * (tile_width + tile_space),
+                                    tile_margin + row as f32 * (tile_height + tile_space),
+                                );
+                                atlas.add_texture(bevy::sprite::Rect {
+                                    min,
+                                    max: min + Vec2::new(tile_width, tile_height),
+                                });
+                            }
+                        }
+                        let atlas_handle = texture_atlases.add(atlas);
+                        for i in 0..(columns * rows) as u32 {
+                            if texture_atlas_map.contains_key(&(tileset.first_gid + i)) {
+                                continue;
                             }
+                            // println!("insert: {}", tileset.first_gid + i);
+                            texture_atlas_map.insert(tileset.first_gid + i, atlas_handle.clone());
                         }
                     }
                 }
             }
         }
 
-        for mesh in map.meshes.drain(0..map.meshes.len()) {
-            let handle = meshes.add(mesh.2);
-            if new_meshes.contains_key(changed_map) {
-                let mesh_list = new_meshes.get_mut(changed_map).unwrap();
-                mesh_list.push((mesh.0, mesh.1, handle));
-            } else {
-                let mut mesh_list = Vec::new();
-                mesh_list.push((mesh.0, mesh.1, handle));
-                new_meshes.insert(changed_map, mesh_list);
+        // meshes are only built for layers that some bundle of this map has to respawn
+        let mut respawned_layer_keys = HashSet::<(usize, u32)>::default();
+        for (_, center, map_handle, _, _, _, origin, _, layer_filter, _, _, created_entities) in
+            query.iter_mut()
+        {
+            if map_handle != changed_map {
+                continue;
+            }
+            let tile_map_transform = map.tile_map_transform(origin, center);
+            for (layer_id, layer) in map.layers.iter().enumerate() {
+                if !layer_filter.allows(&layer.name, &layer.properties) {
+                    continue;
+                }
+                for tileset_layer in layer.tileset_layers.iter() {
+                    let layer_key = (layer_id, tileset_layer.tileset_guid);
+                    let spawn_hash = layer_spawn_hash(
+                        tileset_layer,
+                        layer,
+                        &tile_map_transform,
+                        config.layer_z_spacing,
+                    );
+                    if !created_entities.is_layer_current(layer_key, spawn_hash) {
+                        respawned_layer_keys.insert(layer_key);
+                    }
+                }
             }
         }
+        if !config.headless {
+            let mesh_list = new_meshes.entry(changed_map).or_insert_with(Vec::new);
+            for (layer_id, layer) in map.layers.iter().enumerate() {
+                for tileset_layer in layer.tileset_layers.iter() {
+                    if !respawned_layer_keys.contains(&(layer_id, tileset_layer.tileset_guid)) {
+                        continue;
+                    }
+                    for chunk in tileset_layer.chunks.iter().flatten() {
+                        if let Some(mesh) = chunk.build_mesh() {
+                            mesh_list.push((
+                                layer_id as u32,
+                                tileset_layer.tileset_guid,
+                                UVec2::new(chunk.position.x as u32, chunk.position.y as u32),
+                                meshes.add(mesh),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        if config.discard_chunk_data {
+            map.discard_chunk_data();
+        }
     }
 
+    // maps whose entity was despawned before they finished spawning are dropped
+    pending.retain(|entity, _| query.get_mut(*entity).is_ok());
+    let mut budget = config.max_spawns_per_frame.unwrap_or(usize::MAX);
+
     for (
-        _,
+        entity,
         center,
         map_handle,
         optional_parent,
@@ -828,145 +2968,485 @@ pub fn process_loaded_tile_maps(
         texture_atlas_map,
         origin,
         mut debug_config,
+        layer_filter,
+        object_filter,
+        map_pipelines,
         mut created_entities,
     ) in query.iter_mut()
     {
-        if new_meshes.contains_key(map_handle) {
+        if changed_maps.contains(map_handle) {
+            // a change restarts spawning, layers that are already spawned and unchanged are kept
+            pending.insert(
+                entity,
+                PendingSpawn {
+                    meshes: new_meshes.get(map_handle).cloned().unwrap_or_default(),
+                    ..Default::default()
+                },
+            );
+        }
+        // the asset may be removed while its bundle entity is still around
+        if maps.get(map_handle).is_none() {
+            pending.remove(&entity);
+            continue;
+        }
+        let pending_spawn = match pending.get_mut(&entity) {
+            Some(pending_spawn) => pending_spawn,
+            None => continue,
+        };
+        if budget == 0 {
+            continue;
+        }
+        {
             let map = maps.get(map_handle).unwrap();
 
-            let tile_map_transform = if center.0 {
-                map.center(origin.clone())
-            } else {
-                origin.clone()
-            };
+            let tile_map_transform = map.tile_map_transform(origin, center);
 
-            let mesh_list = new_meshes.get_mut(map_handle).unwrap();
+            // headless maps have no meshes or materials, only object entities are spawned
+            if !config.headless {
+                let mesh_list = pending_spawn.meshes.as_slice();
 
-            for (layer_id, layer) in map.layers.iter().enumerate() {
-                for tileset_layer in layer.tileset_layers.iter() {
-                    let material_handle = materials_map.get(&tileset_layer.tileset_guid).unwrap();
-                    // let mut mesh_list = mesh_list.iter_mut().filter(|(mesh_layer_id, _)| *mesh_layer_id == layer_id as u32).drain(0..mesh_list.len()).collect::<Vec<_>>();
-                    let chunk_mesh_list = mesh_list
-                        .iter()
-                        .filter(|(mesh_layer_id, tileset_guid, _)| {
-                            *mesh_layer_id == layer_id as u32
-                                && *tileset_guid == tileset_layer.tileset_guid
-                        })
-                        .collect::<Vec<_>>();
-
-                    // removing entities consumes the record of created entities
-                    created_entities
-                        .created_layer_entities
-                        .remove(&(layer_id, tileset_layer.tileset_guid))
-                        .map(|entities| {
-                            // println!("Despawning previously-created mesh for this chunk");
-                            for entity in entities.iter() {
-                                // println!("calling despawn on {:?}", entity);
-                                commands.entity(*entity).despawn();
+                // layers that no longer exist in the reloaded map must be torn down
+                let current_layer_keys: HashSet<(usize, u32)> = map
+                    .layers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, layer)| layer_filter.allows(&layer.name, &layer.properties))
+                    .flat_map(|(layer_id, layer)| {
+                        layer
+                            .tileset_layers
+                            .iter()
+                            .map(move |tileset_layer| (layer_id, tileset_layer.tileset_guid))
+                    })
+                    .collect();
+                let stale_layer_keys: Vec<(usize, u32)> = created_entities
+                    .created_layer_entities
+                    .keys()
+                    .filter(|key| !current_layer_keys.contains(key))
+                    .cloned()
+                    .collect();
+                for key in stale_layer_keys {
+                    created_entities.created_layer_hashes.remove(&key);
+                    let created = created_entities.created_layer_entities.remove(&key);
+                    let replaced = created_entities.replaced_layer_entities.remove(&key);
+                    for entity in created.into_iter().chain(replaced).flatten() {
+                        commands.entity(entity).despawn();
+                    }
+                }
+
+                let mut layers_pending = false;
+                'layers: for (layer_id, layer) in map.layers.iter().enumerate() {
+                    if !layer_filter.allows(&layer.name, &layer.properties) {
+                        continue;
+                    }
+                    for tileset_layer in layer.tileset_layers.iter() {
+                        let layer_key = (layer_id, tileset_layer.tileset_guid);
+                        let spawn_hash = layer_spawn_hash(
+                            tileset_layer,
+                            layer,
+                            &tile_map_transform,
+                            config.layer_z_spacing,
+                        );
+                        // skip layers whose data and placement did not change since their
+                        // entities were spawned
+                        if created_entities.is_layer_current(layer_key, spawn_hash) {
+                            continue;
+                        }
+                        // layers are spawned whole, once the budget is used up the rest waits
+                        if budget == 0 {
+                            layers_pending = true;
+                            break 'layers;
+                        }
+                        let material_handle =
+                            materials_map.get(&tileset_layer.tileset_guid).unwrap();
+                        let baked = layer.properties.get_bool(BAKE_PROPERTY) == Some(true);
+                        // let mut mesh_list = mesh_list.iter_mut().filter(|(mesh_layer_id, _)| *mesh_layer_id == layer_id as u32).drain(0..mesh_list.len()).collect::<Vec<_>>();
+                        let chunk_mesh_list = mesh_list
+                            .iter()
+                            .filter(|(mesh_layer_id, tileset_guid, _, _)| {
+                                *mesh_layer_id == layer_id as u32
+                                    && *tileset_guid == tileset_layer.tileset_guid
+                            })
+                            .collect::<Vec<_>>();
+
+                        // old chunks are despawned by `despawn_replaced_chunks` once the new ones
+                        // can be drawn, chunks replaced before that were never seen
+                        if let Some(entities) =
+                            created_entities.created_layer_entities.remove(&layer_key)
+                        {
+                            match created_entities.replaced_layer_entities.entry(layer_key) {
+                                Entry::Occupied(_) => {
+                                    for entity in entities.iter() {
+                                        commands.entity(*entity).despawn();
+                                    }
+                                }
+                                Entry::Vacant(entry) => {
+                                    entry.insert(entities);
+                                }
                             }
-                        });
-                    let mut chunk_entities: Vec<Entity> = Default::default();
+                        }
+                        created_entities
+                            .created_layer_hashes
+                            .insert(layer_key, spawn_hash);
+                        let mut chunk_entities: Vec<Entity> = Default::default();
+                        budget = budget.saturating_sub(chunk_mesh_list.len().max(1));
 
-                    for (_, tileset_guid, mesh) in chunk_mesh_list.iter() {
-                        // TODO: Sadly bevy doesn't support multiple meshes on a single entity with multiple materials.
-                        // Change this once it does.
+                        for (_, tileset_guid, chunk_position, mesh) in chunk_mesh_list.iter() {
+                            // TODO: Sadly bevy doesn't support multiple meshes on a single entity with multiple materials.
+                            // Change this once it does.
 
-                        // Instead for now spawn a new entity per chunk.
-                        let chunk_entity = commands
-                            .spawn_bundle(ChunkBundle {
-                                chunk: TileMapChunk {
+                            // Instead for now spawn a new entity per chunk.
+                            let location = ChunkLocation {
+                                layer_id,
+                                tileset_guid: *tileset_guid,
+                                position: *chunk_position,
+                            };
+                            let chunk_entity = commands
+                                .spawn_bundle(ChunkBundle {
                                     // TODO: Support more layers here..
-                                    layer_id: layer_id as f32,
-                                },
-                                material: material_handle.clone(),
-                                mesh: mesh.clone(),
-                                map_parent: map_handle.clone(),
-                                transform: tile_map_transform.clone(),
-                                ..Default::default()
-                            })
-                            .id();
+                                    chunk: TileMapChunk::new(
+                                        layer.z_index as f32 * config.layer_z_spacing,
+                                    ),
+                                    material: material_handle.clone(),
+                                    mesh: mesh.clone(),
+                                    map_parent: map_handle.clone_weak(),
+                                    transform: tile_map_transform.clone(),
+                                    render_pipeline: RenderPipelines::from_pipelines(vec![
+                                        RenderPipeline::new(
+                                            map_pipelines.pipeline(layer_id, layer.blend_mode),
+                                        ),
+                                    ]),
+                                    visible: Visible {
+                                        is_visible: layer.visible,
+                                        is_transparent: true,
+                                    },
+                                    ..Default::default()
+                                })
+                                .insert(location)
+                                .id();
+                            if let Some(bounds) = meshes.get(mesh).and_then(mesh_bounds) {
+                                commands.entity(chunk_entity).insert(CullingBounds(bounds));
+                            }
+                            // baked chunks are drawn from a texture of their first frames
+                            let animated_tiles = tileset_layer
+                                .chunks
+                                .get(chunk_position.x as usize)
+                                .and_then(|chunks| chunks.get(chunk_position.y as usize))
+                                .map(|chunk| chunk.animated_tiles.clone())
+                                .unwrap_or_default();
+                            if !animated_tiles.is_empty() && !baked {
+                                commands
+                                    .entity(chunk_entity)
+                                    .insert(AnimatedChunk::new(animated_tiles));
+                            }
+                            class_spawners.spawn(
+                                layer.class.as_deref(),
+                                chunk_entity,
+                                &mut commands,
+                            );
+                            chunk_events.send(ChunkSpawnedEvent {
+                                entity: chunk_entity,
+                                location,
+                                map_handle: map_handle.clone(),
+                                map_entity_option: *optional_parent,
+                            });
 
-                        // println!("added created_entry after spawn");
-                        created_entities
-                            .created_layer_entities
-                            .entry((layer_id, *tileset_guid))
-                            .or_insert_with(|| Vec::new())
-                            .push(chunk_entity);
-                        chunk_entities.push(chunk_entity);
-                    }
-                    // if parent was passed in add children and mark it as MapRoot (temp until map bundle returns real entity)
-                    if let Some(parent_entity) = optional_parent {
-                        commands
-                            .entity(parent_entity.clone())
-                            .push_children(&chunk_entities)
-                            .insert(MapRoot);
+                            // println!("added created_entry after spawn");
+                            created_entities
+                                .created_layer_entities
+                                .entry((layer_id, *tileset_guid))
+                                .or_insert_with(|| Vec::new())
+                                .push(chunk_entity);
+                            chunk_entities.push(chunk_entity);
+                        }
+                        // if parent was passed in add children and mark it as MapRoot (temp until map bundle returns real entity)
+                        if let Some(parent_entity) = optional_parent {
+                            commands
+                                .entity(parent_entity.clone())
+                                .push_children(&chunk_entities)
+                                .insert(MapRoot);
+                        }
                     }
                 }
+                if layers_pending {
+                    continue;
+                }
             }
 
-            if debug_config.enabled && debug_config.material.is_none() {
+            if (debug_config.enabled || config.debug_enabled)
+                && debug_config.material.is_none()
+                && !config.headless
+            {
                 debug_config.material =
-                    Some(materials.add(ColorMaterial::from(Color::rgba(0.4, 0.4, 0.9, 0.5))));
+                    Some(materials.add(ColorMaterial::from(config.debug_color)));
             }
-            for object_group in map.groups.iter() {
-                for object in object_group.objects.iter() {
-                    created_entities
-                        .created_object_entities
-                        .remove(&object.gid)
-                        .map(|entities| {
-                            // println!("Despawning previously-created object sprite");
-                            for entity in entities.iter() {
-                                // println!("calling despawn on {:?}", entity);
-                                commands.entity(*entity).despawn();
-                            }
-                        });
+            // groups whose objects or placement changed are respawned, unchanged ones are kept
+            let object_group_hashes = pending_spawn.object_group_hashes.get_or_insert_with(|| {
+                let spawn_hashes = map
+                    .groups
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, object_group)| {
+                        object_group.visible
+                            && layer_filter.allows(&object_group.name, &object_group.properties)
+                    })
+                    .map(|(group_index, object_group)| {
+                        let spawn_hash =
+                            object_group_spawn_hash(object_group, &tile_map_transform, &config);
+                        (group_index, spawn_hash)
+                    })
+                    .collect();
+                for stale in created_entities.take_stale_object_groups(&spawn_hashes) {
+                    commands.entity(stale.layer_entity).despawn();
+                    for entity in stale.object_entities.iter() {
+                        // filled shapes are children of their object
+                        commands.entity(*entity).despawn_recursive();
+                    }
                 }
-                if !object_group.visible {
+                spawn_hashes
+            });
+
+            // objects of visible groups are counted in order, to resume where the budget ran out
+            let mut object_index = 0;
+            let mut objects_pending = false;
+            for (group_index, object_group) in map.groups.iter().enumerate() {
+                let spawn_hash = match object_group_hashes.get(&group_index) {
+                    Some(spawn_hash) => *spawn_hash,
+                    None => continue,
+                };
+                if created_entities.is_object_group_current(group_index, spawn_hash) {
+                    // counted as spawned, so resuming doesn't depend on which groups were kept
+                    object_index += object_group.objects.len();
+                    pending_spawn.objects_spawned = pending_spawn.objects_spawned.max(object_index);
+                    continue;
+                }
+                if object_index + object_group.objects.len() <= pending_spawn.objects_spawned {
+                    object_index += object_group.objects.len();
                     continue;
                 }
+                if budget == 0 {
+                    objects_pending = true;
+                    break;
+                }
+
+                // objects keep their map-relative transforms, the layer entity only places them
+                // in z between the tile layers around it
+                let (object_layer_entity, debug_material) = pending_spawn
+                    .object_layers
+                    .entry(group_index)
+                    .or_insert_with(|| {
+                        let object_layer_entity = commands
+                            .spawn_bundle((
+                                ObjectLayer {
+                                    name: object_group.name.clone(),
+                                    class: object_group.class.clone(),
+                                    opacity: object_group.opacity,
+                                    color: object_group.color,
+                                    properties: object_group.properties.clone(),
+                                },
+                                Visible::default(),
+                                Transform::from_xyz(
+                                    0.0,
+                                    0.0,
+                                    config
+                                        .object_z
+                                        .layer_z(object_group.z_index, config.layer_z_spacing),
+                                ),
+                                GlobalTransform::default(),
+                                map_handle.clone_weak(),
+                            ))
+                            .id();
+                        created_entities.created_object_groups.insert(
+                            group_index,
+                            CreatedObjectGroup {
+                                layer_entity: object_layer_entity,
+                                object_entities: Vec::new(),
+                                spawn_hash: None,
+                            },
+                        );
+                        class_spawners.spawn(
+                            object_group.class.as_deref(),
+                            object_layer_entity,
+                            &mut commands,
+                        );
+
+                        // if parent was passed in add children
+                        if let Some(parent_entity) = optional_parent {
+                            commands
+                                .entity(parent_entity.clone())
+                                .push_children(&[object_layer_entity]);
+                        }
+
+                        // debug shapes use the group's color when it has one
+                        let debug_material = match object_group.color {
+                            Some(mut color) if !config.headless => {
+                                color.set_a(config.debug_color.a());
+                                materials.add(ColorMaterial::from(color))
+                            }
+                            _ => debug_config.material.clone().unwrap_or_default(),
+                        };
+                        (object_layer_entity, debug_material)
+                    })
+                    .clone();
 
                 let mut object_entities: Vec<Entity> = Default::default();
 
-                // TODO: use object_group.name, opacity, colour (properties)
                 for object in object_group.objects.iter() {
+                    if object_index < pending_spawn.objects_spawned {
+                        object_index += 1;
+                        continue;
+                    }
+                    // filtered out objects count as spawned without using the budget
+                    if !object_filter.allows(&object_group.name, object) {
+                        object_index += 1;
+                        pending_spawn.objects_spawned += 1;
+                        continue;
+                    }
+                    if budget == 0 {
+                        objects_pending = true;
+                        break;
+                    }
+                    object_index += 1;
+                    pending_spawn.objects_spawned += 1;
+                    budget -= 1;
+
                     // println!("in object_group {}, object {:?}, grp: {}", object_group.name, &object.tileset_gid, object.gid);
                     let atlas_handle = object
                         .tileset_gid
                         .and_then(|tileset_gid| texture_atlas_map.get(&tileset_gid));
 
-                    let entity = object
-                        .spawn(
-                            &mut commands,
-                            atlas_handle,
-                            &map.map,
-                            map_handle.clone(),
-                            &tile_map_transform,
-                            &debug_config,
-                        )
-                        .id();
+                    // registered spawners get a plain entity, like in headless mode
+                    let spawner = object_spawners.get(&object.obj_type);
+                    let plain = config.headless || spawner.is_some();
+                    let entity = if let Some(spawner) = spawner {
+                        let entity = object
+                            .spawn_headless(
+                                &mut commands,
+                                &map.map,
+                                map_handle.clone_weak(),
+                                &tile_map_transform,
+                                &config,
+                            )
+                            .id();
+                        let context = ObjectSpawnContext {
+                            entity,
+                            object: object.clone(),
+                            map_handle: map_handle.clone_weak(),
+                            texture_atlas: atlas_handle.cloned(),
+                            sprite_index: object.sprite_index,
+                            opacity: object_group.opacity,
+                        };
+                        spawner(context, &mut commands);
+                        entity
+                    } else if config.headless {
+                        object
+                            .spawn_headless(
+                                &mut commands,
+                                &map.map,
+                                map_handle.clone_weak(),
+                                &tile_map_transform,
+                                &config,
+                            )
+                            .id()
+                    } else {
+                        object
+                            .spawn(
+                                &mut commands,
+                                atlas_handle,
+                                &map.map,
+                                map_handle.clone_weak(),
+                                &tile_map_transform,
+                                &debug_config,
+                                &debug_material,
+                                &mut meshes,
+                                &config,
+                                object_group.opacity,
+                            )
+                            .id()
+                    };
+                    if config.fill_shapes && !plain {
+                        if let Some(fill_mesh) = object.fill_mesh() {
+                            // an object's own color property takes precedence over its layer's
+                            let mut color = object
+                                .props
+                                .get_color("color")
+                                .or(object_group.color)
+                                .unwrap_or(config.debug_color);
+                            color.set_a(color.a() * object_group.opacity);
+                            let fill_entity = commands
+                                .spawn_bundle(ChunkBundle {
+                                    chunk: TileMapChunk::default(),
+                                    material: materials.add(ColorMaterial::from(color)),
+                                    mesh: meshes.add(fill_mesh),
+                                    map_parent: map_handle.clone_weak(),
+                                    visible: Visible {
+                                        is_visible: object.visible,
+                                        is_transparent: true,
+                                    },
+                                    ..Default::default()
+                                })
+                                .id();
+                            if let Some(bounds) = object.culling_bounds(None) {
+                                commands.entity(fill_entity).insert(CullingBounds(bounds));
+                            }
+                            commands.entity(entity).push_children(&[fill_entity]);
+                        }
+                    }
+
+                    if object.obj_type == TriggerRegion::OBJECT_TYPE
+                        || object_group.name == TriggerRegion::LAYER_NAME
+                    {
+                        // tile objects are scaled whenever they are spawned as sprites
+                        let tile_scale = if plain || atlas_handle.is_some() {
+                            object.tile_scale(&map.map)
+                        } else {
+                            None
+                        };
+                        commands
+                            .entity(entity)
+                            .insert(TriggerRegion::new(object, tile_scale));
+                    }
+
                     // when done spawning, fire event
                     let evt = ObjectReadyEvent {
                         entity: entity.clone(),
                         map_handle: map_handle.clone(),
                         map_entity_option: optional_parent.clone(),
+                        object_id: object.id,
+                        name: object.name.clone(),
+                        obj_type: object.obj_type.clone(),
+                        layer_name: object_group.name.clone(),
+                        properties: object.props.clone(),
                     };
                     ready_events.send(evt);
 
-                    created_entities
-                        .created_object_entities
-                        .entry(object.gid)
-                        .or_insert_with(|| Vec::new())
-                        .push(entity);
                     object_entities.push(entity);
                 }
 
-                // if parent was passed in add children
-                if let Some(parent_entity) = optional_parent {
-                    commands
-                        .entity(parent_entity.clone())
-                        .push_children(&object_entities);
+                commands
+                    .entity(object_layer_entity)
+                    .push_children(&object_entities);
+                if let Some(created) = created_entities.created_object_groups.get_mut(&group_index)
+                {
+                    created.object_entities.extend(object_entities);
+                    if !objects_pending {
+                        created.spawn_hash = Some(spawn_hash);
+                    }
+                }
+                if objects_pending {
+                    break;
                 }
             }
+            if objects_pending {
+                continue;
+            }
+
+            pending.remove(&entity);
+            // the map's class applies to its parent entity, or to its bundle entity without one
+            class_spawners.spawn(
+                map.class.as_deref(),
+                optional_parent.unwrap_or(entity),
+                &mut commands,
+            );
             let evt = MapReadyEvent {
                 map_handle: map_handle.clone(),
                 map_entity_option: optional_parent.clone(),
@@ -976,15 +3456,753 @@ pub fn process_loaded_tile_maps(
     }
 }
 
+/// Copies the visibility of object layer entities to their objects when it changes. Hidden
+/// tile objects stay hidden.
+#[allow(clippy::type_complexity)]
+pub fn propagate_object_layer_visibility(
+    layers: Query<(&Visible, &Children), (With<ObjectLayer>, Changed<Visible>)>,
+    mut objects: Query<(&mut Visible, Option<&Object>), Without<ObjectLayer>>,
+) {
+    for (layer_visible, children) in layers.iter() {
+        for child in children.iter() {
+            if let Ok((mut visible, object)) = objects.get_mut(*child) {
+                let object_visible = object
+                    .map(|object| object.visible || object.is_shape())
+                    .unwrap_or(true);
+                visible.is_visible = layer_visible.is_visible && object_visible;
+            }
+        }
+    }
+}
+
+/// Applies `TiledConfig::texture_filter` and `anisotropy` to tileset textures as they finish loading,
+/// and again when they are hot reloaded.
+pub fn apply_tileset_texture_filter(
+    config: Res<TiledConfig>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    materials: Res<Assets<ColorMaterial>>,
+    mut textures: ResMut<Assets<Texture>>,
+    query: Query<&HashMap<u32, Handle<ColorMaterial>>, With<Handle<Map>>>,
+) {
+    for event in texture_events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            let is_tileset_texture = query.iter().any(|materials_map| {
+                materials_map.values().any(|material| {
+                    materials
+                        .get(material)
+                        .and_then(|material| material.texture.as_ref())
+                        == Some(handle)
+                })
+            });
+            if !is_tileset_texture {
+                continue;
+            }
+            // setting the filter modifies the texture, so only do it when it differs
+            let needs_filter = match textures.get(handle) {
+                Some(texture) => {
+                    texture.sampler.mag_filter != config.texture_filter
+                        || texture.sampler.min_filter != config.texture_filter
+                        || texture.sampler.anisotropy_clamp != config.anisotropy
+                }
+                None => false,
+            };
+            if needs_filter {
+                let texture = textures.get_mut(handle).unwrap();
+                texture.sampler.mag_filter = config.texture_filter;
+                texture.sampler.min_filter = config.texture_filter;
+                texture.sampler.anisotropy_clamp = config.anisotropy;
+            }
+        }
+    }
+}
+
+/// Despawns the chunks, objects and object layers of maps whose asset was removed, e.g. when
+/// the last strong handle to it is dropped by despawning its `TiledMapBundle`. Spawned entities
+/// only hold weak handles to their map.
+#[allow(clippy::type_complexity)]
+pub fn despawn_removed_maps(
+    mut commands: Commands,
+    mut map_events: EventReader<AssetEvent<Map>>,
+    spawned: Query<(Entity, &Handle<Map>), Without<TiledMapCenter>>,
+    mut map_entities: Query<(
+        &Handle<Map>,
+        &mut HashMap<u32, Handle<ColorMaterial>>,
+        &mut HashMap<u32, Handle<TextureAtlas>>,
+        &mut CreatedMapEntities,
+    )>,
+) {
+    let removed: HashSet<Handle<Map>> = map_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Removed { handle } => Some(handle.clone_weak()),
+            _ => None,
+        })
+        .collect();
+    if removed.is_empty() {
+        return;
+    }
+
+    for (entity, map_handle) in spawned.iter() {
+        if removed.contains(map_handle) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    // bundles that are still around drop their materials and atlases, freeing them
+    for (map_handle, mut materials, mut atlases, mut created_entities) in map_entities.iter_mut() {
+        if removed.contains(map_handle) {
+            materials.clear();
+            atlases.clear();
+            *created_entities = Default::default();
+        }
+    }
+}
+
+/// Despawns the chunks of respawned layers once the tileset textures of the new chunks are
+/// loaded, so that a hot reloaded map never shows a blank frame. Until then both are spawned,
+/// but chunks without their texture aren't drawn.
+// `Option::is_none_or` needs a newer Rust than the crate supports
+#[allow(clippy::unnecessary_map_or)]
+pub fn despawn_replaced_chunks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<Assets<ColorMaterial>>,
+    textures: Option<Res<Assets<Texture>>>,
+    mut map_entities: Query<(
+        &HashMap<u32, Handle<ColorMaterial>>,
+        &mut CreatedMapEntities,
+    )>,
+) {
+    for (materials_map, mut created_entities) in map_entities.iter_mut() {
+        if created_entities.replaced_layer_entities.is_empty() {
+            continue;
+        }
+        let ready: Vec<(usize, u32)> = created_entities
+            .replaced_layer_entities
+            .keys()
+            .filter(|(_, tileset_guid)| {
+                let texture = materials_map
+                    .get(tileset_guid)
+                    .and_then(|material| materials.get(material))
+                    .and_then(|material| material.texture.as_ref());
+                match texture {
+                    // a texture that failed to load would keep the old chunks forever
+                    Some(texture) => {
+                        textures
+                            .as_ref()
+                            .map_or(true, |textures| textures.get(texture).is_some())
+                            || asset_server.get_load_state(texture) == LoadState::Failed
+                    }
+                    None => true,
+                }
+            })
+            .cloned()
+            .collect();
+        for key in ready {
+            let entities = created_entities.replaced_layer_entities.remove(&key);
+            for entity in entities.into_iter().flatten() {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// Rebuilds maps when one of their external tilesets is hot reloaded.
+pub fn reload_external_tilesets(
+    config: Res<TiledConfig>,
+    asset_server: Res<AssetServer>,
+    mut tileset_events: EventReader<AssetEvent<ExternalTileset>>,
+    tilesets: Res<Assets<ExternalTileset>>,
+    mut maps: ResMut<Assets<Map>>,
+) {
+    for event in tileset_events.iter() {
+        let handle = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+        let (path, tileset) = match (asset_server.get_handle_path(handle), tilesets.get(handle)) {
+            (Some(path), Some(tileset)) => (path.path().to_path_buf(), tileset),
+            _ => continue,
+        };
+        let dependent_maps: Vec<bevy::asset::HandleId> = maps
+            .iter()
+            .filter(|(_, map)| map.external_tilesets.iter().any(|(p, _)| *p == path))
+            .map(|(id, _)| id)
+            .collect();
+        for id in dependent_maps {
+            // only mutate maps that use the tileset, get_mut marks them as modified
+            let map = maps.get_mut(id).unwrap();
+            let first_gids: Vec<u32> = map
+                .external_tilesets
+                .iter()
+                .filter(|(p, _)| *p == path)
+                .map(|(_, first_gid)| *first_gid)
+                .collect();
+            let mut alignments = map.object_alignments.clone();
+            for first_gid in first_gids {
+                if let Some(map_tileset) = map
+                    .map
+                    .tilesets
+                    .iter_mut()
+                    .find(|map_tileset| map_tileset.first_gid == first_gid)
+                {
+                    *map_tileset = tiled::Tileset {
+                        first_gid,
+                        ..tileset.tileset.clone()
+                    };
+                }
+                // alignments and wang sets aren't parsed by tiled, so they're read again
+                let text = inline_tileset(&tileset.text, first_gid).unwrap_or_default();
+                alignments.remove(&first_gid);
+                alignments.extend(ObjectAlignment::from_tmx(&text));
+                map.terrain_sets
+                    .retain(|terrain_set| terrain_set.first_gid != first_gid);
+                map.terrain_sets.extend(TerrainSet::from_tmx(&text));
+            }
+            map.terrain_sets
+                .sort_by_key(|terrain_set| terrain_set.first_gid);
+            map.set_object_alignments(alignments);
+            if let Err(e) = map.rebuild(&config) {
+                warn!(
+                    "failed to rebuild map after reloading {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
 // events fired when entity has been created
 
 pub struct ObjectReadyEvent {
     pub entity: Entity,
     pub map_handle: Handle<Map>,
     pub map_entity_option: Option<Entity>,
+    /// The object's id in Tiled.
+    pub object_id: u32,
+    pub name: String,
+    /// The object's type in Tiled.
+    pub obj_type: String,
+    /// Name of the object layer the object is in.
+    pub layer_name: String,
+    pub properties: Properties,
 }
 
 pub struct MapReadyEvent {
     pub map_handle: Handle<Map>,
     pub map_entity_option: Option<Entity>,
 }
+
+/// Fired for each chunk entity spawned, e.g. to add physics or culling components per chunk.
+pub struct ChunkSpawnedEvent {
+    pub entity: Entity,
+    pub location: ChunkLocation,
+    pub map_handle: Handle<Map>,
+    pub map_entity_option: Option<Entity>,
+}
+
+/// Fired once when a map spawned with a `TiledMapBundle` fails to load,
+/// or one of its tileset images can't be loaded.
+#[derive(Debug)]
+pub struct MapLoadFailedEvent {
+    pub map_handle: Handle<Map>,
+    pub path: PathBuf,
+    pub error: TiledMapError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_maps::fixture_map, RegisterObjectSpawner};
+
+    #[test]
+    fn content_hash_is_pinned() {
+        // a change of this value breaks comparing hashes with peers on older builds
+        let map = fixture_map("content_hash.tmx");
+        assert_eq!(map.content_hash(), 9219553971286399927);
+    }
+
+    #[test]
+    fn content_hash_covers_layers_and_objects() {
+        let hash = fixture_map("content_hash.tmx").content_hash();
+        let edits: Vec<fn(&mut tiled::Map)> = vec![
+            |map| map.layers[0].offset_x += 1.0,
+            |map| map.layers[0].opacity = 1.0,
+            |map| {
+                map.layers[0]
+                    .properties
+                    .insert("depth".to_string(), PropertyValue::IntValue(3));
+            },
+            |map| map.properties.clear(),
+            |map| {
+                map.object_groups[0].objects[1].shape = ObjectShape::Ellipse {
+                    width: 32.0,
+                    height: 16.0,
+                }
+            },
+            |map| {
+                if let ObjectShape::Polygon { points } = &mut map.object_groups[0].objects[4].shape
+                {
+                    points[2].1 = 7.0;
+                }
+            },
+            |map| map.object_groups[0].objects[3].rotation = 0.0,
+            |map| {
+                map.object_groups[0].objects[0].properties.insert(
+                    "facing".to_string(),
+                    PropertyValue::StringValue("right".into()),
+                );
+            },
+            |map| map.orientation = tiled::Orientation::Isometric,
+        ];
+        for (index, edit) in edits.iter().enumerate() {
+            let mut map = fixture_map("content_hash.tmx");
+            edit(&mut map.map);
+            assert_ne!(map.content_hash(), hash, "edit {} kept the hash", index);
+        }
+    }
+
+    fn triangle_area(points: &[Vec2], indices: &[u32]) -> f32 {
+        indices
+            .chunks(3)
+            .map(|triangle| {
+                let (a, b, c) = (
+                    points[triangle[0] as usize],
+                    points[triangle[1] as usize],
+                    points[triangle[2] as usize],
+                );
+                // counter-clockwise triangles have a positive area
+                ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)) / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn concave_polygons_are_triangulated() {
+        // an arrow pointing right, concave at its two inner corners, in either winding
+        let arrow = vec![
+            Vec2::new(0.0, 1.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(4.0, 2.0),
+            Vec2::new(2.0, 4.0),
+            Vec2::new(2.0, 3.0),
+            Vec2::new(0.0, 3.0),
+        ];
+        let mut reversed = arrow.clone();
+        reversed.reverse();
+        for points in &[arrow, reversed] {
+            let indices = triangulate(points);
+            assert_eq!(indices.len(), (points.len() - 2) * 3);
+            assert!((triangle_area(points, &indices) - 8.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn degenerate_polygons_are_not_triangulated() {
+        let line = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(3.0, 0.0),
+        ];
+        assert!(triangulate(&line).is_empty());
+    }
+
+    #[test]
+    fn render_orders_are_read_and_index_tiles() {
+        let tmx = |order: &str| format!(r#"<map version="1.4" renderorder="{}">"#, order);
+        assert_eq!(RenderOrder::from_tmx(b"<map>"), RenderOrder::RightDown);
+        assert_eq!(
+            RenderOrder::from_tmx(tmx("left-up").as_bytes()),
+            RenderOrder::LeftUp
+        );
+
+        // the last tile drawn of a 3x2 map for each render order
+        let map_size = UVec2::new(3, 2);
+        let orders = [
+            ("right-down", UVec2::new(2, 1)),
+            ("right-up", UVec2::new(2, 0)),
+            ("left-down", UVec2::new(0, 1)),
+            ("left-up", UVec2::new(0, 0)),
+        ];
+        for (order, last) in orders.iter() {
+            let order = RenderOrder::from_tmx(tmx(order).as_bytes());
+            let mut indices: Vec<u32> = (0..2)
+                .flat_map(|y| (0..3).map(move |x| UVec2::new(x, y)))
+                .map(|tile| order.index(tile, map_size))
+                .collect();
+            assert_eq!(order.index(*last, map_size), 5, "{:?}", order);
+            indices.sort_unstable();
+            assert_eq!(indices, [0, 1, 2, 3, 4, 5], "{:?}", order);
+        }
+
+        // chunks store their tiles in the render order
+        let chunk_tiles = |map: &Map| -> Vec<Vec2> {
+            map.layers[0].tileset_layers[0].chunks[0][0]
+                .tiles
+                .iter()
+                .map(|tile| tile.pos)
+                .collect()
+        };
+        let mut map = fixture_map("tiles.tmx");
+        let mut right_down = chunk_tiles(&map);
+        map.render_order = RenderOrder::LeftUp;
+        map.rebuild(&TiledConfig::default()).unwrap();
+        right_down.reverse();
+        assert_eq!(chunk_tiles(&map), right_down);
+    }
+
+    #[test]
+    fn object_alignments_are_read_from_tileset_tags() {
+        let text = r#"<map version="1.4">
+ <tileset firstgid="1" name="a" objectalignment="topleft"/>
+ <tileset
+    firstgid='5'
+    name="b"
+    objectalignment = 'center'
+ >
+ <tileset firstgid="9" name="c"/>
+ <tileset firstgid="13" name="d" objectalignment="sideways"/>
+</map>"#;
+        let alignments = ObjectAlignment::from_tmx(text);
+        assert_eq!(alignments.len(), 3);
+        assert_eq!(alignments[&1], ObjectAlignment::TopLeft);
+        assert_eq!(alignments[&5], ObjectAlignment::Center);
+        assert_eq!(alignments[&13], ObjectAlignment::Unspecified);
+    }
+
+    #[test]
+    fn tmx_attributes_match_whole_names() {
+        let tag = "<map\n\tversion=\"1.9\" tileversion='2' renderorder=\"left-up\"";
+        assert_eq!(tmx_attribute(tag, "version"), Some("1.9"));
+        assert_eq!(tmx_attribute(tag, "renderorder"), Some("left-up"));
+        assert_eq!(tmx_attribute(tag, "order"), None);
+        assert_eq!(
+            tmx_start_tags("<mapping a=\"1\"><map\nb=\"2\">", "map").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn classes_are_read_from_attributes_and_properties() {
+        let map = fixture_map("classes.tmx");
+        assert_eq!(map.class.as_deref(), Some("Level"));
+        let layer_classes: Vec<Option<&str>> = map
+            .layers
+            .iter()
+            .map(|layer| layer.class.as_deref())
+            .collect();
+        assert_eq!(layer_classes, [Some("Hazard"), Some("Swim")]);
+    }
+
+    #[test]
+    fn object_group_classes_are_read_from_attributes_and_properties() {
+        let map = fixture_map("classes.tmx");
+        let group_classes: Vec<Option<&str>> = map
+            .groups
+            .iter()
+            .map(|group| group.class.as_deref())
+            .collect();
+        assert_eq!(group_classes, [Some("SpawnLayer"), Some("Comments")]);
+    }
+
+    #[test]
+    fn object_types_fall_back_to_classes() {
+        let map = fixture_map("classes.tmx");
+        let types: Vec<&str> = map.groups[0]
+            .objects
+            .iter()
+            .map(|object| object.obj_type.as_str())
+            .collect();
+        assert_eq!(types, ["Chest", "Door"]);
+        assert_eq!(map.objects_of_type("Chest").count(), 1);
+    }
+
+    // an app without rendering that spawns the map from a bundle
+    fn map_app(map: Map, configure: impl FnOnce(&mut AppBuilder)) -> (App, Handle<Map>) {
+        let mut app = App::build();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<Texture>()
+            .add_asset::<ColorMaterial>()
+            .add_asset::<TextureAtlas>()
+            .add_plugin(crate::TiledObjectPlugin::default());
+        configure(&mut app);
+        let map_handle = app
+            .world_mut()
+            .get_resource_mut::<Assets<Map>>()
+            .unwrap()
+            .add(map);
+        app.world_mut().spawn().insert_bundle(TiledMapBundle {
+            map_asset: map_handle.clone(),
+            ..Default::default()
+        });
+        (app.app, map_handle)
+    }
+
+    #[test]
+    fn layer_filters_match_names_and_properties() {
+        let mut foreground = Properties::new();
+        foreground.insert("foreground".to_string(), PropertyValue::BoolValue(true));
+        let mut background = Properties::new();
+        background.insert("foreground".to_string(), PropertyValue::BoolValue(false));
+        let rules = vec![
+            LayerRule::Name("Sky".to_string()),
+            LayerRule::Property("foreground".to_string()),
+        ];
+
+        let include = LayerFilter::Include(rules.clone());
+        assert!(include.allows("Sky", &background));
+        assert!(include.allows("Trees", &foreground));
+        assert!(!include.allows("Ground", &background));
+        assert!(!include.allows("Ground", &Properties::new()));
+
+        let exclude = LayerFilter::Exclude(rules);
+        assert!(!exclude.allows("Sky", &background));
+        assert!(!exclude.allows("Trees", &foreground));
+        assert!(exclude.allows("Ground", &background));
+        assert!(LayerFilter::All.allows("Sky", &foreground));
+
+        // filtered tile and object layers aren't spawned
+        let (mut app, _map_handle) = map_app(fixture_map("respawn.tmx"), |_| {});
+        for mut filter in app
+            .world
+            .query::<&mut LayerFilter>()
+            .iter_mut(&mut app.world)
+        {
+            *filter = LayerFilter::Exclude(vec![
+                LayerRule::Name("Ground".to_string()),
+                LayerRule::Name("Zones".to_string()),
+            ]);
+        }
+        for _ in 0..3 {
+            app.update();
+        }
+        let mut layer_ids: Vec<usize> = app
+            .world
+            .query::<&ChunkLocation>()
+            .iter(&app.world)
+            .map(|location| location.layer_id)
+            .collect();
+        layer_ids.dedup();
+        assert_eq!(layer_ids, [1]);
+        let object_layers: Vec<String> = app
+            .world
+            .query::<&ObjectLayer>()
+            .iter(&app.world)
+            .map(|layer| layer.name.clone())
+            .collect();
+        assert_eq!(object_layers, ["Spawns"]);
+    }
+
+    #[test]
+    fn object_filters_match_layers_types_and_predicates() {
+        let map = fixture_map("content_hash.tmx");
+        let objects = &map.groups[0].objects;
+        let allowed = |filter: &ObjectFilter, layer_name: &str| -> Vec<u32> {
+            objects
+                .iter()
+                .filter(|object| filter.allows(layer_name, object))
+                .map(|object| object.id)
+                .collect()
+        };
+        let rules = vec![
+            ObjectRule::Type("Spawn".to_string()),
+            ObjectRule::Custom(Arc::new(|object: &Object| object.name == "rock")),
+        ];
+        assert_eq!(
+            allowed(&ObjectFilter::Include(rules.clone()), "Objects"),
+            [1, 5]
+        );
+        assert_eq!(allowed(&ObjectFilter::Exclude(rules), "Objects"), [2, 3, 4]);
+
+        let layer = ObjectFilter::Include(vec![ObjectRule::Layer("Objects".to_string())]);
+        assert_eq!(allowed(&layer, "Objects").len(), 5);
+        assert!(allowed(&layer, "Logic").is_empty());
+        assert_eq!(allowed(&ObjectFilter::All, "Logic").len(), 5);
+    }
+
+    #[test]
+    fn registered_spawners_build_objects_of_their_type() {
+        struct Spawned(String);
+        let (mut app, _map_handle) = map_app(fixture_map("content_hash.tmx"), |app| {
+            app.register_object_spawner("Spawn", |context, commands| {
+                let facing = context
+                    .object
+                    .props
+                    .get_string("facing")
+                    .unwrap()
+                    .to_string();
+                commands.entity(context.entity).insert(Spawned(facing));
+            });
+        });
+        for _ in 0..3 {
+            app.update();
+        }
+        let spawned: Vec<(u32, String)> = app
+            .world
+            .query::<(&Object, &Spawned)>()
+            .iter(&app.world)
+            .map(|(object, spawned)| (object.id, spawned.0.clone()))
+            .collect();
+        assert_eq!(spawned, [(1, "left".to_string())]);
+        // the other objects are still spawned by default
+        assert_eq!(app.world.query::<&Object>().iter(&app.world).count(), 5);
+    }
+
+    #[test]
+    fn removed_maps_despawn_their_entities() {
+        let (mut app, map_handle) = map_app(fixture_map("respawn.tmx"), |_| {});
+        let spawned = |world: &mut World| world.query::<&Handle<Map>>().iter(world).count();
+        for _ in 0..3 {
+            app.update();
+        }
+        // the bundle, a chunk per tile layer, two object layers and three objects
+        assert_eq!(spawned(&mut app.world), 8);
+
+        // despawning the bundle drops the last strong handle, which removes the map
+        let bundle = app
+            .world
+            .query_filtered::<Entity, With<TiledMapCenter>>()
+            .iter(&app.world)
+            .next()
+            .unwrap();
+        app.world.despawn(bundle);
+        drop(map_handle);
+        // frees the map, sends its removed event, reads it and applies the despawns
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(spawned(&mut app.world), 0);
+        assert_eq!(app.world.get_resource::<Assets<Map>>().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn reloading_keeps_unchanged_layers_and_object_groups() {
+        let (mut app, map_handle) = map_app(fixture_map("respawn.tmx"), |_| {});
+        let chunks = |world: &mut World, layer_id: usize| -> HashSet<Entity> {
+            world
+                .query::<(Entity, &ChunkLocation)>()
+                .iter(world)
+                .filter(|(_, location)| location.layer_id == layer_id)
+                .map(|(entity, _)| entity)
+                .collect()
+        };
+        let object = |world: &mut World, id: u32| -> Vec<Entity> {
+            world
+                .query::<(Entity, &Object)>()
+                .iter(world)
+                .filter(|(_, object)| object.id == id)
+                .map(|(entity, _)| entity)
+                .collect()
+        };
+        for _ in 0..3 {
+            app.update();
+        }
+        let ground = chunks(&mut app.world, 0);
+        let decoration = chunks(&mut app.world, 1);
+        let player = object(&mut app.world, 1);
+        let water = object(&mut app.world, 2);
+        assert_eq!((ground.len(), decoration.len()), (1, 1));
+        assert_eq!((player.len(), water.len()), (1, 1));
+
+        // edit the ground layer and the zones group
+        {
+            let mut maps = app.world.get_resource_mut::<Assets<Map>>().unwrap();
+            let map = maps.get_mut(&map_handle).unwrap();
+            map.set_tile(0, UVec2::new(0, 0), 4);
+            map.groups[1].objects[0].position.x += 4.0;
+            map.rebuild(&TiledConfig::default()).unwrap();
+        }
+        for _ in 0..3 {
+            app.update();
+        }
+        // replaced ground chunks are kept until the tileset texture is loaded
+        assert!(!chunks(&mut app.world, 0).is_subset(&ground));
+        assert_eq!(chunks(&mut app.world, 1), decoration);
+        assert_eq!(object(&mut app.world, 1), player);
+        let moved_water = object(&mut app.world, 2);
+        assert_eq!(moved_water.len(), 1);
+        assert_ne!(moved_water, water);
+    }
+
+    #[test]
+    fn tiles_convert_to_and_from_world_positions() {
+        let mut map = fixture_map("tiles.tmx");
+        let map_transform = Transform {
+            translation: Vec3::new(100.0, 50.0, 0.0),
+            scale: Vec3::splat(2.0),
+            ..Default::default()
+        };
+        // tiles are 16 pixels, rows go down from the map transform
+        let world = map.tile_to_world(UVec2::new(1, 0), &map_transform);
+        assert_eq!(world, Some(Vec2::new(148.0, 34.0)));
+        let tile = map.world_to_tile(Vec2::new(135.0, 19.0), &map_transform);
+        assert_eq!(tile, Some(UVec2::new(1, 0)));
+        assert_eq!(
+            map.world_to_tile(Vec2::new(99.0, 40.0), &map_transform),
+            None
+        );
+        assert_eq!(
+            map.world_to_tile(Vec2::new(120.0, -20.0), &map_transform),
+            None
+        );
+
+        for orientation in &[
+            tiled::Orientation::Orthogonal,
+            tiled::Orientation::Isometric,
+        ] {
+            map.map.orientation = *orientation;
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+                let tile = UVec2::new(*x, *y);
+                let world = map.tile_to_world(tile, &map_transform).unwrap();
+                assert_eq!(map.world_to_tile(world, &map_transform), Some(tile));
+            }
+        }
+    }
+
+    #[test]
+    fn object_z_follows_the_policy() {
+        let map = fixture_map("content_hash.tmx");
+        let (zone, pond) = (&map.groups[0].objects[1], &map.groups[0].objects[2]);
+        let z = |object: &Object, object_z: ObjectZPolicy| {
+            let config = TiledConfig {
+                object_z,
+                ..Default::default()
+            };
+            object
+                .transform_from_map(&map.map, &Transform::identity(), None, &config)
+                .unwrap()
+                .translation
+                .z
+        };
+        assert_eq!(z(zone, ObjectZPolicy::Fixed(15.0)), 15.0);
+        assert_eq!(z(pond, ObjectZPolicy::LayerOrder), 0.0);
+        // the zone's center is 8 pixels down, the pond's 20 and its bottom 24
+        let range = 100.0;
+        assert_eq!(z(zone, ObjectZPolicy::YSorted { range }), 0.08);
+        assert_eq!(z(pond, ObjectZPolicy::YSorted { range }), 0.2);
+        assert_eq!(z(pond, ObjectZPolicy::YSortedByBase { range }), 0.24);
+
+        assert_eq!(ObjectZPolicy::Fixed(15.0).layer_z(3, 10.0), 0.0);
+        assert_eq!(ObjectZPolicy::LayerOrder.layer_z(3, 10.0), 30.0);
+        assert_eq!(
+            ObjectZPolicy::YSortedByBase { range }.layer_z(3, 10.0),
+            30.0
+        );
+    }
+
+    #[test]
+    fn unsupported_orientations_are_not_placed() {
+        let mut map = fixture_map("content_hash.tmx");
+        map.map.orientation = tiled::Orientation::Hexagonal;
+        assert!(map.center(Transform::identity()).is_none());
+        let object = &map.groups[0].objects[0];
+        let config = TiledConfig::default();
+        assert!(object
+            .transform_from_map(&map.map, &Transform::identity(), None, &config)
+            .is_none());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{Map, TiledConfig};
+use bevy::{
+    asset::HandleId,
+    prelude::*,
+    render::{
+        render_graph::{Node, ResourceSlots},
+        renderer::{
+            BufferInfo, BufferUsage, RenderContext, RenderResourceContext, RenderResourceId,
+            TextureId,
+        },
+        texture::{Extent3d, TextureDescriptor, TextureFormat, TEXTURE_ASSET_INDEX},
+    },
+    utils::{HashMap, HashSet},
+};
+
+/// Tileset textures and tileset arrays uploaded with mip levels, by handle, with their number
+/// of levels and the size they have once padded.
+#[derive(Default)]
+pub struct MipmappedTextures {
+    textures: HashMap<HandleId, (u32, Extent3d)>,
+    // levels after the first of textures created this frame, uploaded by `TilesetMipmapNode`
+    uploads: Vec<MipUpload>,
+}
+
+impl MipmappedTextures {
+    pub(crate) fn insert(&mut self, handle: &Handle<Texture>, levels: u32, size: Extent3d) {
+        self.textures.insert(handle.id, (levels, size));
+    }
+}
+
+struct MipUpload {
+    texture: TextureId,
+    pixel_size: usize,
+    levels: Vec<(Extent3d, Vec<u8>)>,
+}
+
+/// Extrudes the tiles of tileset images by `TiledConfig::tile_padding` pixels once they are
+/// loaded, and again when they are hot reloaded, so that they can be mipmapped.
+pub fn pad_tileset_textures(
+    config: Res<TiledConfig>,
+    maps: Res<Assets<Map>>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut mipmapped: ResMut<MipmappedTextures>,
+) {
+    let padding = config.tile_padding();
+    if padding == 0 {
+        return;
+    }
+    for event in texture_events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { handle } => {
+                mipmapped.textures.remove(&handle.id);
+                continue;
+            }
+        };
+        let tileset = maps.iter().find_map(|(_, map)| {
+            map.map
+                .tilesets
+                .iter()
+                .find(|tileset| map.tileset_textures.get(&tileset.first_gid) == Some(handle))
+        });
+        let (tileset, texture) = match (tileset, textures.get(handle)) {
+            (Some(tileset), Some(texture)) => (tileset, texture),
+            _ => continue,
+        };
+        let (_, _, padded_size) = Map::tileset_image_layout(tileset, padding);
+        let padded_size = Extent3d::new(padded_size.x, padded_size.y, 1);
+        // padding the image modifies it, which brings it back here
+        if texture.size != padded_size {
+            match pad_tileset_image(texture, tileset, padding) {
+                Some(padded) => {
+                    let texture = textures.get_mut(handle).unwrap();
+                    texture.data = padded;
+                    texture.size = padded_size;
+                    texture.sampler.mipmap_filter = config.texture_filter;
+                }
+                None => {
+                    warn!(
+                        "bevy_tiled: can't pad tileset image {:?} for mipmaps, it must be 8 bit RGBA and match the size in its tileset",
+                        handle
+                    );
+                    continue;
+                }
+            }
+        }
+        mipmapped.insert(handle, config.mipmap_levels, padded_size);
+    }
+}
+
+// relays a tileset image without margin, with every tile extruded by `padding` pixels
+fn pad_tileset_image(texture: &Texture, tileset: &tiled::Tileset, padding: u32) -> Option<Vec<u8>> {
+    let image = &tileset.images[0];
+    if mip_format(texture.format).is_none()
+        || texture.size != Extent3d::new(image.width as u32, image.height as u32, 1)
+    {
+        return None;
+    }
+    let pixel_size = texture.format.pixel_size();
+    let (columns, rows) = Map::tileset_grid(tileset);
+    let (_, _, padded_size) = Map::tileset_image_layout(tileset, padding);
+    let (tile_width, tile_height) = (tileset.tile_width, tileset.tile_height);
+    let cell_width = tile_width + 2 * padding;
+    let cell_height = tile_height + 2 * padding;
+
+    let mut data = vec![0; (padded_size.x * padded_size.y) as usize * pixel_size];
+    for row in 0..rows {
+        for column in 0..columns {
+            let tile_x = tileset.margin + column * (tile_width + tileset.spacing);
+            let tile_y = tileset.margin + row * (tile_height + tileset.spacing);
+            for y in 0..cell_height {
+                // pixels of the padding repeat the tile's edge
+                let source_y =
+                    tile_y + (y.max(padding) - padding).min(tile_height.saturating_sub(1));
+                for x in 0..cell_width {
+                    let source_x =
+                        tile_x + (x.max(padding) - padding).min(tile_width.saturating_sub(1));
+                    let source = (source_y * texture.size.width + source_x) as usize * pixel_size;
+                    let target = ((row * cell_height + y) * padded_size.x + column * cell_width + x)
+                        as usize
+                        * pixel_size;
+                    data[target..target + pixel_size]
+                        .copy_from_slice(texture.data.get(source..source + pixel_size)?);
+                }
+            }
+        }
+    }
+    Some(data)
+}
+
+// formats whose mip levels can be averaged per byte
+fn mip_format(format: TextureFormat) -> Option<TextureFormat> {
+    match format {
+        TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Rgba8Unorm
+        | TextureFormat::Bgra8UnormSrgb
+        | TextureFormat::Bgra8Unorm => Some(format),
+        _ => None,
+    }
+}
+
+/// Replaces the render textures of mipmapped tileset textures, as they are created by bevy, with
+/// ones that have room for their mip levels, and queues the levels for `TilesetMipmapNode`.
+/// Runs between bevy's texture resources and the render graph systems that bind them.
+pub(crate) fn create_mipmapped_textures(
+    render_resource_context: Res<Box<dyn RenderResourceContext>>,
+    textures: Res<Assets<Texture>>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut mipmapped: ResMut<MipmappedTextures>,
+) {
+    let render_resource_context = &**render_resource_context;
+    let mut changed = HashSet::default();
+    for event in texture_events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            changed.insert(handle.clone_weak());
+        }
+    }
+    for handle in changed {
+        let (levels, size) = match mipmapped.textures.get(&handle.id) {
+            Some(mipmapped) => *mipmapped,
+            None => continue,
+        };
+        let texture = match textures.get(&handle) {
+            Some(texture) if texture.size == size && mip_format(texture.format).is_some() => {
+                texture
+            }
+            _ => continue,
+        };
+        let mip_levels = mip_chain(texture, levels);
+        let descriptor = TextureDescriptor {
+            mip_level_count: mip_levels.len() as u32 + 1,
+            ..texture.into()
+        };
+        let mipmapped_texture = render_resource_context.create_texture(descriptor);
+        // nothing is bound to the texture bevy just created yet
+        if let Some(RenderResourceId::Texture(created)) =
+            render_resource_context.get_asset_resource(&handle, TEXTURE_ASSET_INDEX)
+        {
+            render_resource_context.remove_texture(created);
+        }
+        render_resource_context.set_asset_resource(
+            &handle,
+            RenderResourceId::Texture(mipmapped_texture),
+            TEXTURE_ASSET_INDEX,
+        );
+        mipmapped.uploads.push(MipUpload {
+            texture: mipmapped_texture,
+            pixel_size: texture.format.pixel_size(),
+            levels: mip_levels,
+        });
+    }
+}
+
+// levels after the first, each averaging 2x2 pixels of the one before, for every array layer
+fn mip_chain(texture: &Texture, levels: u32) -> Vec<(Extent3d, Vec<u8>)> {
+    let pixel_size = texture.format.pixel_size();
+    let layers = texture.size.depth as usize;
+    let mut chain: Vec<(Extent3d, Vec<u8>)> = Vec::new();
+    let mut size = texture.size;
+    let mut data = texture.data.clone();
+    while (chain.len() as u32 + 1) < levels && (size.width > 1 || size.height > 1) {
+        let (width, height) = (size.width as usize, size.height as usize);
+        let next = Extent3d::new(
+            (size.width / 2).max(1),
+            (size.height / 2).max(1),
+            size.depth,
+        );
+        let (next_width, next_height) = (next.width as usize, next.height as usize);
+        let mut next_data = vec![0; next_width * next_height * layers * pixel_size];
+        for layer in 0..layers {
+            let pixel = |x: usize, y: usize| {
+                let x = x.min(width - 1);
+                let y = y.min(height - 1);
+                ((layer * height + y) * width + x) * pixel_size
+            };
+            for y in 0..next_height {
+                for x in 0..next_width {
+                    let target = ((layer * next_height + y) * next_width + x) * pixel_size;
+                    let sources = [
+                        pixel(2 * x, 2 * y),
+                        pixel(2 * x + 1, 2 * y),
+                        pixel(2 * x, 2 * y + 1),
+                        pixel(2 * x + 1, 2 * y + 1),
+                    ];
+                    // averaged as stored, which slightly darkens srgb textures
+                    for channel in 0..pixel_size {
+                        let sum: u32 = sources
+                            .iter()
+                            .map(|source| data[source + channel] as u32)
+                            .sum();
+                        next_data[target + channel] = ((sum + 2) / 4) as u8;
+                    }
+                }
+            }
+        }
+        chain.push((next, next_data.clone()));
+        size = next;
+        data = next_data;
+    }
+    chain
+}
+
+/// Uploads the mip levels queued by `create_mipmapped_textures`, after bevy's texture copy
+/// node has uploaded the first level.
+#[derive(Default)]
+pub struct TilesetMipmapNode {
+    uploads: Vec<MipUpload>,
+}
+
+impl Node for TilesetMipmapNode {
+    fn prepare(&mut self, world: &mut World) {
+        if let Some(mut mipmapped) = world.get_resource_mut::<MipmappedTextures>() {
+            self.uploads.append(&mut mipmapped.uploads);
+        }
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        _output: &mut ResourceSlots,
+    ) {
+        for upload in self.uploads.drain(..) {
+            for (level, (size, data)) in upload.levels.iter().enumerate() {
+                // rows of texture copies are aligned, as in bevy's texture copy node
+                let width = size.width as usize;
+                let row_size = width * upload.pixel_size;
+                let aligned_width = render_context.resources().get_aligned_texture_size(width);
+                let aligned_row_size = aligned_width * upload.pixel_size;
+                let mut aligned_data = vec![0; aligned_row_size * data.len() / row_size];
+                data.chunks_exact(row_size)
+                    .enumerate()
+                    .for_each(|(index, row)| {
+                        let offset = index * aligned_row_size;
+                        aligned_data[offset..offset + row_size].copy_from_slice(row);
+                    });
+                let buffer = render_context.resources().create_buffer_with_data(
+                    BufferInfo {
+                        buffer_usage: BufferUsage::COPY_SRC,
+                        ..Default::default()
+                    },
+                    &aligned_data,
+                );
+                render_context.copy_buffer_to_texture(
+                    buffer,
+                    0,
+                    aligned_row_size as u32,
+                    upload.texture,
+                    [0, 0, 0],
+                    level as u32 + 1,
+                    *size,
+                );
+                render_context.resources().remove_buffer(buffer);
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::Map;
+use bevy::prelude::*;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+/// Movement costs of every tile of a map, for pathfinding.
+///
+/// Costs are computed from the tiles of all tile layers: a cell is blocked if the cost
+/// function returns `None` for any of its tiles, and otherwise costs the most expensive of
+/// them. Empty cells cost 1.0.
+#[derive(Debug, Clone)]
+pub struct NavGrid {
+    pub size: UVec2,
+    /// Allows diagonal moves, which never cut past blocked corners.
+    pub allow_diagonal: bool,
+    costs: Vec<Option<f32>>,
+}
+
+impl NavGrid {
+    /// Builds a grid from the tiles of a map. `cost` receives each tile's gid and the custom
+    /// properties set on it in its tileset.
+    pub fn from_map(
+        map: &Map,
+        allow_diagonal: bool,
+        cost: impl Fn(u32, Option<&tiled::Properties>) -> Option<f32>,
+    ) -> NavGrid {
+        let size = UVec2::new(map.map.width, map.map.height);
+        let mut grid = NavGrid {
+            size,
+            allow_diagonal,
+            costs: vec![Some(1.0); (size.x * size.y) as usize],
+        };
+        let tiles: Vec<UVec2> = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .collect();
+        grid.update_tiles(map, &tiles, cost);
+        grid
+    }
+
+    /// Recomputes the cost of the given tiles, e.g. after editing them at runtime.
+    pub fn update_tiles(
+        &mut self,
+        map: &Map,
+        tiles: &[UVec2],
+        cost: impl Fn(u32, Option<&tiled::Properties>) -> Option<f32>,
+    ) {
+        for tile in tiles {
+            let index = match self.index(IVec2::new(tile.x as i32, tile.y as i32)) {
+                Some(index) => index,
+                None => continue,
+            };
+            let mut tile_cost = Some(1.0f32);
+            for layer_index in 0..map.map.layers.len() {
+                if let Some(gid) = map.gid_at(layer_index, *tile) {
+                    tile_cost = match (tile_cost, cost(gid, map.tile_properties(gid))) {
+                        (Some(a), Some(b)) => Some(a.max(b)),
+                        _ => None,
+                    };
+                }
+            }
+            self.costs[index] = tile_cost;
+        }
+    }
+
+    /// Cost of entering a tile, `None` if it is blocked or outside of the grid.
+    pub fn cost(&self, tile: IVec2) -> Option<f32> {
+        self.index(tile).and_then(|index| self.costs[index])
+    }
+
+    pub fn set_cost(&mut self, tile: IVec2, cost: Option<f32>) {
+        if let Some(index) = self.index(tile) {
+            self.costs[index] = cost;
+        }
+    }
+
+    /// Finds the cheapest path between two tiles with A*, including both ends.
+    pub fn find_path(&self, start: IVec2, goal: IVec2) -> Option<Vec<IVec2>> {
+        self.cost(start)?;
+        self.cost(goal)?;
+
+        // the heuristic must never overestimate, so it uses the cheapest cost in the grid
+        let min_cost = self
+            .costs
+            .iter()
+            .flatten()
+            .cloned()
+            .fold(f32::INFINITY, f32::min);
+        let heuristic = |tile: IVec2| {
+            let delta = (tile - goal).abs();
+            let (long, short) = (delta.x.max(delta.y) as f32, delta.x.min(delta.y) as f32);
+            min_cost
+                * if self.allow_diagonal {
+                    long + (std::f32::consts::SQRT_2 - 1.0) * short
+                } else {
+                    long + short
+                }
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::new();
+        let mut best: HashMap<IVec2, f32> = HashMap::new();
+        best.insert(start, 0.0);
+        open.push(OpenTile {
+            estimate: heuristic(start),
+            tile: start,
+        });
+
+        while let Some(OpenTile { tile, .. }) = open.pop() {
+            if tile == goal {
+                let mut path = vec![tile];
+                let mut current = tile;
+                while let Some(previous) = came_from.get(&current) {
+                    path.push(*previous);
+                    current = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let cost_so_far = best[&tile];
+            for (neighbour, step) in self.neighbours(tile) {
+                let next_cost = cost_so_far + step;
+                let improved = match best.get(&neighbour) {
+                    Some(cost) => next_cost < *cost,
+                    None => true,
+                };
+                if improved {
+                    best.insert(neighbour, next_cost);
+                    came_from.insert(neighbour, tile);
+                    open.push(OpenTile {
+                        estimate: next_cost + heuristic(neighbour),
+                        tile: neighbour,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // walkable neighbours and the cost of moving to them
+    fn neighbours(&self, tile: IVec2) -> Vec<(IVec2, f32)> {
+        let mut neighbours = Vec::new();
+        for (x, y) in [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ]
+        .iter()
+        {
+            let diagonal = *x != 0 && *y != 0;
+            if diagonal
+                && (!self.allow_diagonal
+                    || self.cost(tile + IVec2::new(*x, 0)).is_none()
+                    || self.cost(tile + IVec2::new(0, *y)).is_none())
+            {
+                continue;
+            }
+            let neighbour = tile + IVec2::new(*x, *y);
+            if let Some(cost) = self.cost(neighbour) {
+                let distance = if diagonal {
+                    std::f32::consts::SQRT_2
+                } else {
+                    1.0
+                };
+                neighbours.push((neighbour, cost * distance));
+            }
+        }
+        neighbours
+    }
+
+    fn index(&self, tile: IVec2) -> Option<usize> {
+        if tile.x < 0 || tile.y < 0 || tile.x >= self.size.x as i32 || tile.y >= self.size.y as i32
+        {
+            return None;
+        }
+        Some((tile.y as u32 * self.size.x + tile.x as u32) as usize)
+    }
+}
+
+// open set entry, ordered so the binary heap pops the lowest estimate first
+struct OpenTile {
+    estimate: f32,
+    tile: IVec2,
+}
+
+impl PartialEq for OpenTile {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenTile {}
+
+impl PartialOrd for OpenTile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenTile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_maps::ascii_map, PropertiesExt};
+
+    fn grid(rows: &[&str], allow_diagonal: bool) -> NavGrid {
+        NavGrid::from_map(
+            &ascii_map(rows),
+            allow_diagonal,
+            |_, properties| match properties {
+                Some(properties) if properties.get_bool("solid") == Some(true) => None,
+                Some(properties) => properties.get_float("cost").or(Some(1.0)),
+                None => Some(1.0),
+            },
+        )
+    }
+
+    fn tiles(tiles: &[(i32, i32)]) -> Vec<IVec2> {
+        tiles.iter().map(|(x, y)| IVec2::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn finds_straight_path() {
+        let grid = grid(&["....."], false);
+        let path = grid.find_path(IVec2::new(0, 0), IVec2::new(4, 0));
+        assert_eq!(path, Some(tiles(&[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)])));
+    }
+
+    #[test]
+    fn walks_around_walls() {
+        let grid = grid(&["#....", "..##.", "#...."], false);
+        let path = grid.find_path(IVec2::new(0, 1), IVec2::new(4, 1)).unwrap();
+        // up past the wall, along the top row and down again
+        assert_eq!(path.len(), 7);
+        assert_eq!(path.first(), Some(&IVec2::new(0, 1)));
+        assert_eq!(path.last(), Some(&IVec2::new(4, 1)));
+        for tile in path.iter() {
+            assert!(grid.cost(*tile).is_some(), "path crosses wall at {}", tile);
+        }
+        for step in path.windows(2) {
+            assert_eq!((step[1] - step[0]).abs().x + (step[1] - step[0]).abs().y, 1);
+        }
+    }
+
+    #[test]
+    fn moves_diagonally_only_past_free_corners() {
+        let open = grid(&["..", ".."], true);
+        assert_eq!(
+            open.find_path(IVec2::new(0, 0), IVec2::new(1, 1)),
+            Some(tiles(&[(0, 0), (1, 1)]))
+        );
+
+        let corner = grid(&["..", "#."], true);
+        assert_eq!(
+            corner.find_path(IVec2::new(0, 0), IVec2::new(1, 1)),
+            Some(tiles(&[(0, 0), (1, 0), (1, 1)]))
+        );
+
+        let closed = grid(&[".#", "#."], true);
+        assert_eq!(closed.find_path(IVec2::new(0, 0), IVec2::new(1, 1)), None);
+    }
+
+    #[test]
+    fn unreachable_goal_has_no_path() {
+        let grid = grid(&["..#..", "..#..", "..#.."], true);
+        assert_eq!(grid.find_path(IVec2::new(0, 0), IVec2::new(4, 2)), None);
+        // blocked and outside goals as well
+        assert_eq!(grid.find_path(IVec2::new(0, 0), IVec2::new(2, 1)), None);
+        assert_eq!(grid.find_path(IVec2::new(0, 0), IVec2::new(9, 0)), None);
+    }
+
+    #[test]
+    fn detours_around_expensive_tiles() {
+        let grid = grid(&["...", ".~.", "..."], false);
+        let path = grid.find_path(IVec2::new(0, 1), IVec2::new(2, 1)).unwrap();
+        // through the mud costs 6.0, around it 4.0
+        assert_eq!(path.len(), 5);
+        assert!(!path.contains(&IVec2::new(1, 1)));
+    }
+
+    #[test]
+    fn finds_cheapest_path_on_expensive_ground() {
+        // every tile costs 5.0, which the heuristic is scaled by
+        let grid = grid(&["~~~~", "~~~~"], false);
+        let path = grid.find_path(IVec2::new(0, 0), IVec2::new(3, 1)).unwrap();
+        assert_eq!(path.len(), 5);
+    }
+
+    #[test]
+    fn update_tiles_recomputes_costs() {
+        let map = ascii_map(&["...", "...", "..."]);
+        let mut grid = NavGrid::from_map(&map, false, |_, _| Some(1.0));
+        grid.update_tiles(&map, &[UVec2::new(1, 0), UVec2::new(1, 1)], |_, _| None);
+        assert_eq!(grid.cost(IVec2::new(1, 1)), None);
+        assert_eq!(grid.cost(IVec2::new(1, 2)), Some(1.0));
+        assert_eq!(
+            grid.find_path(IVec2::new(0, 0), IVec2::new(2, 0)),
+            Some(tiles(&[
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (2, 1),
+                (2, 0)
+            ]))
+        );
+    }
+}
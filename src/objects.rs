@@ -0,0 +1,330 @@
+use crate::{Map, Object};
+use bevy::{asset::HandleId, ecs::system::Command, prelude::*, utils::HashMap};
+
+/// A spawned object, by entity or by its id in Tiled within a map.
+#[derive(Debug, Clone)]
+pub enum ObjectRef {
+    Entity(Entity),
+    Id(Handle<Map>, u32),
+}
+
+impl From<Entity> for ObjectRef {
+    fn from(entity: Entity) -> Self {
+        ObjectRef::Entity(entity)
+    }
+}
+
+impl From<(Handle<Map>, u32)> for ObjectRef {
+    fn from((map_handle, id): (Handle<Map>, u32)) -> Self {
+        ObjectRef::Id(map_handle, id)
+    }
+}
+
+impl ObjectRef {
+    // the object's entities, one per bundle the map is spawned with
+    fn entities(&self, world: &mut World) -> Vec<Entity> {
+        match self {
+            ObjectRef::Entity(entity) => vec![*entity],
+            ObjectRef::Id(map_handle, id) => world
+                .query::<(Entity, &Object, &Handle<Map>)>()
+                .iter(world)
+                .filter(|(_, object, handle)| object.id == *id && *handle == map_handle)
+                .map(|(entity, ..)| entity)
+                .collect(),
+        }
+    }
+}
+
+pub trait ObjectCommands {
+    /// Shows or hides a spawned object by setting `Object::visible`, e.g. to reveal a prop
+    /// placed in Tiled during a scripted sequence without despawning it.
+    fn set_object_visible(&mut self, object: impl Into<ObjectRef>, visible: bool) -> &mut Self;
+}
+
+impl ObjectCommands for Commands<'_> {
+    fn set_object_visible(&mut self, object: impl Into<ObjectRef>, visible: bool) -> &mut Self {
+        self.add(SetObjectVisible {
+            object: object.into(),
+            visible,
+        });
+        self
+    }
+}
+
+struct SetObjectVisible {
+    object: ObjectRef,
+    visible: bool,
+}
+
+impl Command for SetObjectVisible {
+    fn write(self: Box<Self>, world: &mut World) {
+        for entity in self.object.entities(world) {
+            if let Some(mut object) = world.get_mut::<Object>(entity) {
+                // only mark objects as changed when they are
+                if object.visible != self.visible {
+                    object.visible = self.visible;
+                }
+            }
+        }
+    }
+}
+
+/// Applies changes of `Object::visible` to the sprites of tile objects and to the children of
+/// objects, such as filled shapes. Debug shapes keep their visibility.
+pub fn apply_object_visibility(
+    objects: Query<(Entity, &Object, Option<&Children>), Changed<Object>>,
+    mut visibles: Query<&mut Visible>,
+) {
+    for (entity, object, children) in objects.iter() {
+        if !object.is_shape() {
+            if let Ok(mut visible) = visibles.get_mut(entity) {
+                visible.is_visible = object.visible;
+            }
+        }
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if let Ok(mut visible) = visibles.get_mut(*child) {
+                visible.is_visible = object.visible;
+            }
+        }
+    }
+}
+
+/// Pools the entities of spawned objects that die and come back, such as pickups or enemies,
+/// once inserted as a resource. Released objects are hidden and marked `ReleasedObject` instead
+/// of being despawned, and `respawn` brings them back with the `Object` and `Transform` they
+/// were spawned with. Components added by game code are kept.
+///
+/// Objects are known by map and id, so for a map spawned by more than one bundle only the
+/// objects of the last one spawned are pooled.
+#[derive(Debug, Default)]
+pub struct ObjectRegistry {
+    // spawned objects by map and Tiled object id
+    objects: HashMap<(HandleId, u32), PooledObject>,
+}
+
+#[derive(Debug)]
+struct PooledObject {
+    entity: Entity,
+    object: Object,
+    transform: Transform,
+    released: bool,
+}
+
+/// Marks objects released with `ObjectRegistry::release`, e.g. to leave them out of collisions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReleasedObject;
+
+impl ObjectRegistry {
+    /// The entity an object is spawned as, released or not.
+    pub fn entity(&self, map_handle: &Handle<Map>, id: u32) -> Option<Entity> {
+        self.objects
+            .get(&(map_handle.id, id))
+            .map(|pooled| pooled.entity)
+    }
+
+    /// The entities of all pooled objects of a type in Tiled, e.g. to release every pickup.
+    pub fn entities_of_type<'a>(&'a self, obj_type: &'a str) -> impl Iterator<Item = Entity> + 'a {
+        self.objects
+            .values()
+            .filter(move |pooled| pooled.object.obj_type == obj_type)
+            .map(|pooled| pooled.entity)
+    }
+
+    pub fn is_released(&self, object: impl Into<ObjectRef>) -> bool {
+        self.key(object.into())
+            .and_then(|key| self.objects.get(&key))
+            .map(|pooled| pooled.released)
+            .unwrap_or(false)
+    }
+
+    /// Takes an object out of play instead of despawning it.
+    /// Returns `false` if it isn't known or already released.
+    pub fn release(&mut self, commands: &mut Commands, object: impl Into<ObjectRef>) -> bool {
+        match self.find(object.into()) {
+            Some(pooled) if !pooled.released => {
+                pooled.released = true;
+                commands.add(ReleaseObject {
+                    entity: pooled.entity,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Brings a released object back, reusing its entity, and returns the entity.
+    pub fn respawn(
+        &mut self,
+        commands: &mut Commands,
+        object: impl Into<ObjectRef>,
+    ) -> Option<Entity> {
+        let pooled = self.find(object.into()).filter(|pooled| pooled.released)?;
+        pooled.released = false;
+        commands.add(RespawnObject {
+            entity: pooled.entity,
+            object: pooled.object.clone(),
+            transform: pooled.transform,
+        });
+        Some(pooled.entity)
+    }
+
+    fn find(&mut self, object: ObjectRef) -> Option<&mut PooledObject> {
+        let key = self.key(object)?;
+        self.objects.get_mut(&key)
+    }
+
+    fn key(&self, object: ObjectRef) -> Option<(HandleId, u32)> {
+        match object {
+            ObjectRef::Entity(entity) => self
+                .objects
+                .iter()
+                .find(|(_, pooled)| pooled.entity == entity)
+                .map(|(key, _)| *key),
+            ObjectRef::Id(map_handle, id) => Some((map_handle.id, id)),
+        }
+    }
+}
+
+struct ReleaseObject {
+    entity: Entity,
+}
+
+impl Command for ReleaseObject {
+    fn write(self: Box<Self>, world: &mut World) {
+        if let Some(mut entity) = world.get_entity_mut(self.entity) {
+            entity.insert(ReleasedObject);
+            if let Some(mut object) = entity.get_mut::<Object>() {
+                object.visible = false;
+            }
+        }
+    }
+}
+
+struct RespawnObject {
+    entity: Entity,
+    object: Object,
+    transform: Transform,
+}
+
+impl Command for RespawnObject {
+    fn write(self: Box<Self>, world: &mut World) {
+        if let Some(mut entity) = world.get_entity_mut(self.entity) {
+            entity.remove::<ReleasedObject>();
+            entity.insert(self.object).insert(self.transform);
+        }
+    }
+}
+
+/// Records spawned objects in the `ObjectRegistry`, if there is one, and forgets despawned ones.
+/// Runs in `CoreStage::PostUpdate`, after objects are spawned and despawned in `CoreStage::Update`.
+pub fn register_pooled_objects(
+    registry: Option<ResMut<ObjectRegistry>>,
+    objects: Query<(Entity, &Object, &Handle<Map>, &Transform), Added<Object>>,
+    existing: Query<(), With<Object>>,
+) {
+    let mut registry = match registry {
+        Some(registry) => registry,
+        None => return,
+    };
+    // removal trackers are cleared every frame, so despawns in other stages would be missed
+    registry
+        .objects
+        .retain(|_, pooled| existing.get(pooled.entity).is_ok());
+    for (entity, object, map_handle, transform) in objects.iter() {
+        registry.objects.insert(
+            (map_handle.id, object.id),
+            PooledObject {
+                entity,
+                object: object.clone(),
+                transform: *transform,
+                released: false,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::CommandQueue;
+
+    fn pickup(world: &mut World, map_handle: &Handle<Map>) -> Entity {
+        let mut object = Object::default();
+        object.id = 7;
+        object.obj_type = "Pickup".to_string();
+        world
+            .spawn()
+            .insert_bundle((
+                object,
+                map_handle.clone(),
+                Transform::from_xyz(1.0, 2.0, 0.0),
+            ))
+            .id()
+    }
+
+    fn run(
+        world: &mut World,
+        stage: &mut SystemStage,
+        command: impl FnOnce(&mut ObjectRegistry, &mut Commands),
+    ) {
+        let mut registry = world.remove_resource::<ObjectRegistry>().unwrap();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        command(&mut registry, &mut commands);
+        queue.apply(world);
+        world.insert_resource(registry);
+        stage.run(world);
+    }
+
+    #[test]
+    fn released_objects_respawn_with_their_spawned_state() {
+        let mut world = World::default();
+        world.insert_resource(ObjectRegistry::default());
+        let mut stage = SystemStage::parallel();
+        stage.add_system(register_pooled_objects.system());
+        let map_handle = Handle::<Map>::weak(HandleId::random::<Map>());
+        let entity = pickup(&mut world, &map_handle);
+        stage.run(&mut world);
+
+        run(&mut world, &mut stage, |registry, commands| {
+            assert!(registry.release(commands, entity));
+            assert!(!registry.release(commands, entity));
+        });
+        assert!(world.get::<ReleasedObject>(entity).is_some());
+        assert!(!world.get::<Object>(entity).unwrap().visible);
+        world.get_mut::<Transform>(entity).unwrap().translation.x = 5.0;
+
+        run(&mut world, &mut stage, |registry, commands| {
+            assert!(registry.is_released((map_handle.clone(), 7)));
+            assert_eq!(
+                registry.respawn(commands, (map_handle.clone(), 7)),
+                Some(entity)
+            );
+        });
+        assert!(world.get::<ReleasedObject>(entity).is_none());
+        assert!(world.get::<Object>(entity).unwrap().visible);
+        assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 1.0);
+    }
+
+    #[test]
+    fn despawned_objects_are_forgotten() {
+        let mut world = World::default();
+        world.insert_resource(ObjectRegistry::default());
+        let mut stage = SystemStage::parallel();
+        stage.add_system(register_pooled_objects.system());
+        let map_handle = Handle::<Map>::weak(HandleId::random::<Map>());
+        let entity = pickup(&mut world, &map_handle);
+        stage.run(&mut world);
+        let registry = world.get_resource::<ObjectRegistry>().unwrap();
+        assert_eq!(registry.entity(&map_handle, 7), Some(entity));
+        assert_eq!(registry.entities_of_type("Pickup").count(), 1);
+
+        // despawned outside of the stage, where its removal can't be tracked
+        world.despawn(entity);
+        world.clear_trackers();
+        stage.run(&mut world);
+        let registry = world.get_resource::<ObjectRegistry>().unwrap();
+        assert_eq!(registry.entity(&map_handle, 7), None);
+        assert_eq!(registry.entities_of_type("Pickup").count(), 0);
+    }
+}
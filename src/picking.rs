@@ -0,0 +1,137 @@
+use crate::{Map, TiledMapCenter};
+use bevy::{
+    prelude::*,
+    render::{camera::Camera, render_graph::base::camera::CAMERA_2D},
+    utils::HashMap,
+};
+
+/// Adds `TileHoverEvent` and `TileClickEvent`, fired when the cursor of the primary
+/// window moves over or clicks on a tile of a spawned map.
+#[derive(Default)]
+pub struct TilePickingPlugin;
+
+impl Plugin for TilePickingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<TileHoverEvent>()
+            .add_event::<TileClickEvent>()
+            .add_system(tile_picking.system());
+    }
+}
+
+/// Fired when the cursor moves onto a different tile.
+pub struct TileHoverEvent {
+    pub map_entity: Entity,
+    pub map_handle: Handle<Map>,
+    /// Index of the topmost visible layer with a tile under the cursor, in `tiled::Map::layers`.
+    pub layer: usize,
+    pub tile_pos: UVec2,
+    pub gid: u32,
+}
+
+/// Fired when a mouse button is pressed over a tile.
+pub struct TileClickEvent {
+    pub map_entity: Entity,
+    pub map_handle: Handle<Map>,
+    /// Index of the topmost visible layer with a tile under the cursor, in `tiled::Map::layers`.
+    pub layer: usize,
+    pub tile_pos: UVec2,
+    pub gid: u32,
+    pub button: MouseButton,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn tile_picking(
+    windows: Res<Windows>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    maps: Res<Assets<Map>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    map_query: Query<(
+        Entity,
+        &Handle<Map>,
+        &TiledMapCenter,
+        &Transform,
+        &Option<Entity>,
+    )>,
+    parent_query: Query<&GlobalTransform>,
+    mut hovered: Local<HashMap<Entity, (usize, UVec2)>>,
+    mut hover_events: EventWriter<TileHoverEvent>,
+    mut click_events: EventWriter<TileClickEvent>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let cursor = match window.cursor_position() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    let (camera, camera_transform) = match cameras
+        .iter()
+        .find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))
+    {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    // cursor to normalized device coordinates, then through the camera into the world
+    let window_size = Vec2::new(window.width(), window.height());
+    let ndc = (cursor / window_size) * 2.0 - Vec2::ONE;
+    let world = camera_transform.compute_matrix()
+        * camera.projection_matrix.inverse()
+        * ndc.extend(0.0).extend(1.0);
+
+    for (map_entity, map_handle, center, origin, optional_parent) in map_query.iter() {
+        let map = match maps.get(map_handle) {
+            Some(map) => map,
+            None => continue,
+        };
+
+        let mut tile_map_transform = map.tile_map_transform(origin, center);
+        // chunks are children of the parent entity, if there is one
+        if let Some(parent_transform) = optional_parent.and_then(|p| parent_query.get(p).ok()) {
+            tile_map_transform = parent_transform.mul_transform(tile_map_transform).into();
+        }
+
+        let picked = map
+            .world_to_tile(world.truncate().truncate(), &tile_map_transform)
+            .and_then(|tile_pos| {
+                (0..map.map.layers.len())
+                    .rev()
+                    .filter(|layer| map.map.layers[*layer].visible)
+                    .find_map(|layer| {
+                        map.gid_at(layer, tile_pos)
+                            .map(|gid| (layer, tile_pos, gid))
+                    })
+            });
+
+        let (layer, tile_pos, gid) = match picked {
+            Some(picked) => picked,
+            None => {
+                hovered.remove(&map_entity);
+                continue;
+            }
+        };
+
+        if hovered.get(&map_entity) != Some(&(layer, tile_pos)) {
+            hovered.insert(map_entity, (layer, tile_pos));
+            hover_events.send(TileHoverEvent {
+                map_entity,
+                map_handle: map_handle.clone(),
+                layer,
+                tile_pos,
+                gid,
+            });
+        }
+
+        for button in mouse_buttons.get_just_pressed() {
+            click_events.send(TileClickEvent {
+                map_entity,
+                map_handle: map_handle.clone(),
+                layer,
+                tile_pos,
+                gid,
+                button: *button,
+            });
+        }
+    }
+}
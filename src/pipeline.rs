@@ -1,22 +1,335 @@
-use crate::TileMapChunk;
+use crate::{
+    loader::ALL_FLIP_FLAGS, BakedChunk, ChunkLocation, Map, PropertiesExt, Tile, TileMapChunk,
+    TiledConfig, TilesetArray, TilesetMipmapNode, TILE_MAP_ARRAY_PIPELINE_HANDLE,
+};
 use bevy::{
+    asset::HandleId,
     prelude::*,
     reflect::TypeUuid,
     render::{
         pipeline::{
             BlendFactor, BlendOperation, BlendState, ColorTargetState, ColorWrite, CompareFunction,
-            DepthBiasState, DepthStencilState, PipelineDescriptor, StencilFaceState, StencilState,
+            DepthBiasState, DepthStencilState, PipelineDescriptor, RenderPipeline,
+            StencilFaceState, StencilState,
         },
-        render_graph::{base, RenderGraph, RenderResourcesNode},
+        render_graph::{base, AssetRenderResourcesNode, RenderGraph, RenderResourcesNode},
         shader::{ShaderStage, ShaderStages},
         texture::TextureFormat,
     },
+    utils::{HashMap, HashSet},
 };
 
 pub const TILE_MAP_PIPELINE_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 4129645945969645246);
+pub const TILE_MAP_ADDITIVE_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 4129645945969645247);
+pub const TILE_MAP_MULTIPLY_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 4129645945969645248);
+pub const TILE_MAP_OPAQUE_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 4129645945969645250);
+
+/// Bool layer property that marks a tile layer as opaque or not, instead of detecting it from
+/// its tiles, see `TiledConfig::opaque_layers`.
+pub const OPAQUE_PROPERTY: &str = "opaque";
+
+/// How the tiles of a layer are blended with what is drawn beneath them.
+///
+/// Set per layer with a `blend_mode` string property in Tiled (`"additive"` or `"multiply"`),
+/// or at runtime with `Map::set_layer_blend_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Alpha,
+    Additive,
+    Multiply,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Alpha
+    }
+}
+
+impl BlendMode {
+    pub const PROPERTY: &'static str = "blend_mode";
+
+    pub fn from_properties(properties: &tiled::Properties) -> BlendMode {
+        match properties.get_string(BlendMode::PROPERTY) {
+            Some("additive") => BlendMode::Additive,
+            Some("multiply") => BlendMode::Multiply,
+            _ => BlendMode::Alpha,
+        }
+    }
+
+    pub fn property_value(&self) -> &'static str {
+        match self {
+            BlendMode::Alpha => "alpha",
+            BlendMode::Additive => "additive",
+            BlendMode::Multiply => "multiply",
+        }
+    }
+
+    pub fn pipeline_handle(&self) -> Handle<PipelineDescriptor> {
+        match self {
+            BlendMode::Alpha => TILE_MAP_PIPELINE_HANDLE.typed(),
+            BlendMode::Additive => TILE_MAP_ADDITIVE_PIPELINE_HANDLE.typed(),
+            BlendMode::Multiply => TILE_MAP_MULTIPLY_PIPELINE_HANDLE.typed(),
+        }
+    }
+
+    fn color_blend(&self) -> BlendState {
+        let (src_factor, dst_factor) = match self {
+            BlendMode::Alpha => (BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+            BlendMode::Additive => (BlendFactor::SrcAlpha, BlendFactor::One),
+            // assumes fully transparent texels are black, as most exported tilesets are
+            BlendMode::Multiply => (BlendFactor::DstColor, BlendFactor::OneMinusSrcAlpha),
+        };
+        BlendState {
+            src_factor,
+            dst_factor,
+            operation: BlendOperation::Add,
+        }
+    }
+}
+
+/// Custom render pipelines for the chunks of a `TiledMapBundle`, e.g. with a fog of war
+/// fragment shader built from `build_tile_map_pipeline`. Layers without one use the pipeline of
+/// their blend mode.
+#[derive(Debug, Default, Clone)]
+pub struct MapPipelines {
+    /// Pipeline for all tile layers of the map.
+    pub map: Option<Handle<PipelineDescriptor>>,
+    /// Pipelines by layer index, which take precedence over `map`.
+    pub layers: HashMap<usize, Handle<PipelineDescriptor>>,
+}
+
+impl MapPipelines {
+    pub fn pipeline(&self, layer_id: usize, blend_mode: BlendMode) -> Handle<PipelineDescriptor> {
+        self.layers
+            .get(&layer_id)
+            .or(self.map.as_ref())
+            .cloned()
+            .unwrap_or(blend_mode.pipeline_handle())
+    }
+}
+
+/// Switches the chunks of maps whose `MapPipelines` changed to their new pipelines.
+pub fn apply_map_pipelines(
+    maps: Res<Assets<Map>>,
+    map_entities: Query<(&Handle<Map>, &MapPipelines), Changed<MapPipelines>>,
+    mut chunks: Query<(&ChunkLocation, &Handle<Map>, &mut RenderPipelines)>,
+) {
+    for (changed_handle, pipelines) in map_entities.iter() {
+        let map = match maps.get(changed_handle) {
+            Some(map) => map,
+            None => continue,
+        };
+        for (location, map_handle, mut render_pipelines) in chunks.iter_mut() {
+            if map_handle != changed_handle {
+                continue;
+            }
+            if let Some(layer) = map.layers.get(location.layer_id) {
+                let pipeline = pipelines.pipeline(location.layer_id, layer.blend_mode);
+                *render_pipelines =
+                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(pipeline)]);
+            }
+        }
+    }
+}
+
+/// Marks chunks of opaque layers, which are drawn without blending in the opaque pass, front to
+/// back, see `TiledConfig::opaque_layers`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OpaqueChunk;
+
+/// Moves the chunks of opaque tile layers to the opaque pass, and back once their layer is tinted
+/// see-through or they are switched to another pipeline. Layers are opaque when their `opaque`
+/// property is set, or else when every tile they use is fully opaque in its tileset image.
+/// Only chunks using the pipeline of `BlendMode::Alpha` are moved.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn use_opaque_pipelines(
+    mut commands: Commands,
+    config: Res<TiledConfig>,
+    maps: Res<Assets<Map>>,
+    textures: Res<Assets<Texture>>,
+    mut map_events: EventReader<AssetEvent<Map>>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut chunks: Query<(
+        Entity,
+        &ChunkLocation,
+        &Handle<Map>,
+        &TileMapChunk,
+        &mut RenderPipelines,
+        &mut Visible,
+        Option<&OpaqueChunk>,
+        Option<&BakedChunk>,
+    )>,
+    // whether layers are opaque, by map and layer index
+    mut opaque_layers: Local<HashMap<(HandleId, usize), bool>>,
+) {
+    for event in map_events.iter() {
+        if let AssetEvent::Modified { handle } | AssetEvent::Removed { handle } = event {
+            opaque_layers.retain(|(map_id, _), _| *map_id != handle.id);
+        }
+    }
+    if texture_events.iter().next().is_some() {
+        opaque_layers.clear();
+    }
+
+    let alpha_pipeline = BlendMode::Alpha.pipeline_handle();
+    let opaque_pipeline: Handle<PipelineDescriptor> = TILE_MAP_OPAQUE_PIPELINE_HANDLE.typed();
+    for (entity, location, map_handle, chunk, mut render_pipelines, mut visible, marked, baked) in
+        chunks.iter_mut()
+    {
+        let pipeline = match render_pipelines.pipelines.as_slice() {
+            [pipeline] => pipeline.pipeline.clone(),
+            _ => continue,
+        };
+        if marked.is_some() && pipeline != opaque_pipeline {
+            // switched to another pipeline, e.g. by `MapPipelines`
+            visible.is_transparent = true;
+            commands.entity(entity).remove::<OpaqueChunk>();
+            continue;
+        }
+        if pipeline != alpha_pipeline && pipeline != opaque_pipeline {
+            continue;
+        }
 
-pub fn build_tile_map_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+        let opaque = config.opaque_layers
+            && baked.is_none()
+            && chunk.tint.w >= 1.0
+            && match opaque_layers.get(&(map_handle.id, location.layer_id)) {
+                Some(opaque) => *opaque,
+                None => match maps
+                    .get(map_handle)
+                    .and_then(|map| is_opaque_layer(map, location.layer_id, &textures))
+                {
+                    Some(opaque) => {
+                        opaque_layers.insert((map_handle.id, location.layer_id), opaque);
+                        opaque
+                    }
+                    // tileset images are still loading
+                    None => false,
+                },
+            };
+        if opaque && pipeline == alpha_pipeline {
+            *render_pipelines =
+                RenderPipelines::from_pipelines(vec![RenderPipeline::new(opaque_pipeline.clone())]);
+            visible.is_transparent = false;
+            commands.entity(entity).insert(OpaqueChunk);
+        } else if !opaque && pipeline == opaque_pipeline {
+            *render_pipelines =
+                RenderPipelines::from_pipelines(vec![RenderPipeline::new(alpha_pipeline.clone())]);
+            visible.is_transparent = true;
+            commands.entity(entity).remove::<OpaqueChunk>();
+        }
+    }
+}
+
+// whether every tile of a layer is fully opaque, `None` until its tileset images are loaded
+fn is_opaque_layer(map: &Map, layer_index: usize, textures: &Assets<Texture>) -> Option<bool> {
+    let layer = map.layers.get(layer_index)?;
+    if let Some(opaque) = layer.properties.get_bool(OPAQUE_PROPERTY) {
+        return Some(opaque);
+    }
+    if layer.blend_mode != BlendMode::Alpha {
+        return Some(false);
+    }
+    for tileset_layer in layer.tileset_layers.iter() {
+        let texture = textures.get(map.tileset_textures.get(&tileset_layer.tileset_guid)?)?;
+        match texture.format {
+            TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Rgba8Unorm
+            | TextureFormat::Bgra8UnormSrgb
+            | TextureFormat::Bgra8Unorm => {}
+            _ => return Some(false),
+        }
+        let mut checked = HashSet::default();
+        for chunk in tileset_layer.chunks.iter().flatten() {
+            if chunk.discarded {
+                return Some(false);
+            }
+            for tile in chunk.tiles.iter() {
+                if checked.insert(tile.tile_id & !ALL_FLIP_FLAGS) && !is_opaque_tile(tile, texture)
+                {
+                    return Some(false);
+                }
+            }
+        }
+    }
+    Some(true)
+}
+
+fn is_opaque_tile(tile: &Tile, texture: &Texture) -> bool {
+    let size = Vec2::new(texture.size.width as f32, texture.size.height as f32);
+    let min = (Vec2::new(tile.uv.x, tile.uv.y) * size).round();
+    let max = (Vec2::new(tile.uv.z, tile.uv.w) * size).round().min(size);
+    let width = texture.size.width as usize;
+    (min.y as usize..max.y as usize).all(|y| {
+        (min.x as usize..max.x as usize)
+            .all(|x| texture.data.get((y * width + x) * 4 + 3) == Some(&255))
+    })
+}
+
+pub fn build_tile_map_pipeline(
+    shaders: &mut Assets<Shader>,
+    blend_mode: BlendMode,
+) -> PipelineDescriptor {
+    tile_map_pipeline(blend_mode, tile_map_shader_stages(shaders))
+}
+
+/// The pipeline that draws the chunks of opaque layers without blending, see `OpaqueChunk`.
+pub fn build_tile_map_opaque_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    tile_map_pipeline_with_blend(BlendState::REPLACE, tile_map_shader_stages(shaders))
+}
+
+fn tile_map_shader_stages(shaders: &mut Assets<Shader>) -> ShaderStages {
+    ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(
+            ShaderStage::Vertex,
+            if cfg!(feature = "web") {
+                include_str!("tile_map_webgl2.vert")
+            } else {
+                include_str!("tile_map.vert")
+            },
+        )),
+        fragment: Some(shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            if cfg!(feature = "web") {
+                include_str!("tile_map_webgl2.frag")
+            } else {
+                include_str!("tile_map.frag")
+            },
+        ))),
+    }
+}
+
+/// The pipeline that draws chunks with their map's `TilesetArray`, see
+/// `TiledConfig::tileset_array`. Not available with the `web` feature.
+pub fn build_tile_map_array_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    tile_map_pipeline(
+        BlendMode::Alpha,
+        ShaderStages {
+            vertex: shaders.add(Shader::from_glsl(
+                ShaderStage::Vertex,
+                include_str!("tile_map_array.vert"),
+            )),
+            fragment: Some(shaders.add(Shader::from_glsl(
+                ShaderStage::Fragment,
+                include_str!("tile_map_array.frag"),
+            ))),
+        },
+    )
+}
+
+fn tile_map_pipeline(blend_mode: BlendMode, shader_stages: ShaderStages) -> PipelineDescriptor {
+    tile_map_pipeline_with_blend(blend_mode.color_blend(), shader_stages)
+}
+
+fn tile_map_pipeline_with_blend(
+    color_blend: BlendState,
+    shader_stages: ShaderStages,
+) -> PipelineDescriptor {
     PipelineDescriptor {
         depth_stencil: Some(DepthStencilState {
             format: TextureFormat::Depth32Float,
@@ -37,11 +350,7 @@ pub fn build_tile_map_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescript
         }),
         color_target_states: vec![ColorTargetState {
             format: TextureFormat::Bgra8UnormSrgb,
-            color_blend: BlendState {
-                src_factor: BlendFactor::SrcAlpha,
-                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                operation: BlendOperation::Add,
-            },
+            color_blend,
             alpha_blend: BlendState {
                 src_factor: BlendFactor::One,
                 dst_factor: BlendFactor::One,
@@ -49,38 +358,29 @@ pub fn build_tile_map_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescript
             },
             write_mask: ColorWrite::ALL,
         }],
-        ..PipelineDescriptor::new(ShaderStages {
-            vertex: shaders.add(Shader::from_glsl(
-                ShaderStage::Vertex,
-                if cfg!(feature = "web") {
-                    include_str!("tile_map_webgl2.vert")
-                } else {
-                    include_str!("tile_map.vert")
-                },
-            )),
-            fragment: Some(shaders.add(Shader::from_glsl(
-                ShaderStage::Fragment,
-                if cfg!(feature = "web") {
-                    include_str!("tile_map_webgl2.frag")
-                } else {
-                    include_str!("tile_map.frag")
-                },
-            ))),
-        })
+        ..PipelineDescriptor::new(shader_stages)
     }
 }
 
 pub mod node {
     pub const TILE_MAP_CHUNK: &'static str = "tile_map_chunk";
+    pub const TILESET_ARRAY: &str = "tileset_array";
+    pub const TILESET_MIPMAPS: &str = "tileset_mipmaps";
 }
 
 pub(crate) fn add_tile_map_graph(world: &mut World) {
     world.resource_scope(|world, mut pipelines: Mut<Assets<PipelineDescriptor>>| {
         world.resource_scope(|world, mut shaders: Mut<Assets<Shader>>| {
             let mut graph = world.get_resource_mut::<RenderGraph>().unwrap();
+            for blend_mode in [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply].iter() {
+                pipelines.set_untracked(
+                    blend_mode.pipeline_handle(),
+                    build_tile_map_pipeline(&mut shaders, *blend_mode),
+                );
+            }
             pipelines.set_untracked(
-                TILE_MAP_PIPELINE_HANDLE,
-                build_tile_map_pipeline(&mut shaders),
+                TILE_MAP_OPAQUE_PIPELINE_HANDLE,
+                build_tile_map_opaque_pipeline(&mut shaders),
             );
             graph.add_system_node(
                 node::TILE_MAP_CHUNK,
@@ -89,6 +389,23 @@ pub(crate) fn add_tile_map_graph(world: &mut World) {
             graph
                 .add_node_edge(node::TILE_MAP_CHUNK, base::node::MAIN_PASS)
                 .unwrap();
+            if !cfg!(feature = "web") {
+                pipelines.set_untracked(
+                    TILE_MAP_ARRAY_PIPELINE_HANDLE,
+                    build_tile_map_array_pipeline(&mut shaders),
+                );
+                graph.add_system_node(
+                    node::TILESET_ARRAY,
+                    AssetRenderResourcesNode::<TilesetArray>::new(true),
+                );
+                graph
+                    .add_node_edge(node::TILESET_ARRAY, base::node::MAIN_PASS)
+                    .unwrap();
+                graph.add_node(node::TILESET_MIPMAPS, TilesetMipmapNode::default());
+                graph
+                    .add_node_edge(node::TILESET_MIPMAPS, base::node::MAIN_PASS)
+                    .unwrap();
+            }
         });
     });
 }
@@ -0,0 +1,224 @@
+use crate::{
+    loader::MapLoadErrors, ChunkLocation, LayerFilter, Map, MapLoadFailedEvent, MapReadyEvent,
+    TileMapChunk, TiledMapError,
+};
+use bevy::{
+    asset::LoadState,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+/// How far along a map is in loading, for loading screens.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MapProgress {
+    /// The map asset has been parsed.
+    pub parsed: bool,
+    /// Number of chunk entities spawned, out of `chunks_total`.
+    pub chunks_spawned: usize,
+    pub chunks_total: usize,
+    /// Number of tileset textures loaded, out of `textures_total`.
+    pub textures_loaded: usize,
+    pub textures_total: usize,
+    /// All chunk and object entities of the map have been spawned.
+    pub spawned: bool,
+}
+
+impl MapProgress {
+    /// Overall progress between 0.0 and 1.0, counting every step equally.
+    pub fn fraction(&self) -> f32 {
+        let done = self.parsed as usize
+            + self.chunks_spawned
+            + self.textures_loaded
+            + self.spawned as usize;
+        let total = 2 + self.chunks_total + self.textures_total;
+        done as f32 / total as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.spawned && self.textures_loaded == self.textures_total
+    }
+}
+
+/// Loading progress of every map spawned with a `TiledMapBundle`, updated each frame.
+#[derive(Debug, Default)]
+pub struct MapLoadProgress {
+    maps: HashMap<Handle<Map>, MapProgress>,
+}
+
+impl MapLoadProgress {
+    pub fn get(&self, map_handle: &Handle<Map>) -> Option<&MapProgress> {
+        self.maps.get(map_handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Handle<Map>, &MapProgress)> {
+        self.maps.iter()
+    }
+
+    /// Whether every tracked map has finished loading.
+    pub fn is_done(&self) -> bool {
+        self.maps.values().all(|progress| progress.is_done())
+    }
+}
+
+/// Whether the maps spawned with a `TiledMapBundle` are ready, e.g. to leave a loading state
+/// once every map requested in it is fully spawned. Updated each frame after `MapLoadProgress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TiledLoadState {
+    /// No map bundle is spawned.
+    Idle,
+    Loading,
+    /// Every map is spawned and has its tileset textures loaded.
+    Ready,
+    /// A map or one of its tileset images failed to load, see `MapLoadFailedEvent`.
+    Failed,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for TiledLoadState {
+    fn default() -> Self {
+        TiledLoadState::Idle
+    }
+}
+
+impl TiledLoadState {
+    pub fn is_ready(&self) -> bool {
+        *self == TiledLoadState::Ready
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn track_map_load_progress(
+    mut progress: ResMut<MapLoadProgress>,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<Map>>,
+    mut ready_events: EventReader<MapReadyEvent>,
+    map_entities: Query<(&Handle<Map>, &LayerFilter)>,
+    chunks: Query<&Handle<Map>, With<ChunkLocation>>,
+) {
+    let mut tracked: HashMap<Handle<Map>, MapProgress> = Default::default();
+    // a map may be spawned by several bundles with different layer filters
+    for (map_handle, layer_filter) in map_entities.iter() {
+        let map_progress = tracked
+            .entry(map_handle.clone())
+            .or_insert_with(|| MapProgress {
+                chunks_total: 0,
+                chunks_spawned: 0,
+                ..progress.maps.get(map_handle).cloned().unwrap_or_default()
+            });
+        if let Some(map) = maps.get(map_handle) {
+            map_progress.parsed = true;
+            map_progress.chunks_total += map
+                .layers
+                .iter()
+                .filter(|layer| layer_filter.allows(&layer.name, &layer.properties))
+                .flat_map(|layer| layer.tileset_layers.iter())
+                .flat_map(|tileset_layer| tileset_layer.chunks.iter().flatten())
+                .filter(|chunk| !chunk.tiles.is_empty() || chunk.discarded)
+                .count();
+            map_progress.textures_total = map.tileset_textures.len();
+            map_progress.textures_loaded = map
+                .tileset_textures
+                .values()
+                .filter(|texture| asset_server.get_load_state(*texture) == LoadState::Loaded)
+                .count();
+        }
+    }
+    for map_handle in chunks.iter() {
+        if let Some(map_progress) = tracked.get_mut(map_handle) {
+            map_progress.chunks_spawned += 1;
+        }
+    }
+    for event in ready_events.iter() {
+        if let Some(map_progress) = tracked.get_mut(&event.map_handle) {
+            map_progress.spawned = true;
+        }
+    }
+    progress.maps = tracked;
+}
+
+// `Option::is_some_and` needs a newer Rust than the crate supports
+#[allow(clippy::unnecessary_map_or)]
+pub fn update_tiled_load_state(
+    progress: Res<MapLoadProgress>,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<Map>>,
+    mut load_state: ResMut<TiledLoadState>,
+) {
+    let failed = progress.iter().any(|(map_handle, _)| {
+        asset_server.get_load_state(map_handle) == LoadState::Failed
+            || maps.get(map_handle).map_or(false, |map| {
+                map.tileset_textures
+                    .values()
+                    .any(|texture| asset_server.get_load_state(texture) == LoadState::Failed)
+            })
+    });
+    let new_state = if progress.iter().next().is_none() {
+        TiledLoadState::Idle
+    } else if failed {
+        TiledLoadState::Failed
+    } else if progress.is_done() {
+        TiledLoadState::Ready
+    } else {
+        TiledLoadState::Loading
+    };
+    // only changes are written, so `Res::is_changed` tells when the state moved on
+    if *load_state != new_state {
+        *load_state = new_state;
+    }
+}
+
+/// Sends a `MapLoadFailedEvent` for maps and tileset images that failed to load.
+pub(crate) fn report_map_load_failures(
+    asset_server: Res<AssetServer>,
+    errors: Res<MapLoadErrors>,
+    maps: Res<Assets<Map>>,
+    mut reported: Local<HashSet<Handle<Map>>>,
+    mut failed_events: EventWriter<MapLoadFailedEvent>,
+    map_entities: Query<&Handle<Map>, Without<TileMapChunk>>,
+) {
+    for map_handle in map_entities.iter() {
+        if reported.contains(map_handle) {
+            continue;
+        }
+        let path = match asset_server.get_handle_path(map_handle) {
+            Some(path) => path.path().to_path_buf(),
+            None => continue,
+        };
+
+        let error = if asset_server.get_load_state(map_handle) == LoadState::Failed {
+            // errors without a parse error come from reading the file
+            errors
+                .0
+                .lock()
+                .unwrap()
+                .remove(&path)
+                .unwrap_or_else(|| TiledMapError::LoadFailed(path.clone()))
+        } else if let Some(map) = maps.get(map_handle) {
+            let failed_tileset = map
+                .tileset_textures
+                .iter()
+                .find(|(_, texture)| asset_server.get_load_state(*texture) == LoadState::Failed);
+            match failed_tileset {
+                Some((first_gid, _)) => TiledMapError::MissingImage {
+                    tileset: map
+                        .map
+                        .tilesets
+                        .iter()
+                        .find(|tileset| tileset.first_gid == *first_gid)
+                        .map(|tileset| tileset.name.clone())
+                        .unwrap_or_default(),
+                },
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+
+        reported.insert(map_handle.clone());
+        failed_events.send(MapLoadFailedEvent {
+            map_handle: map_handle.clone(),
+            path,
+            error,
+        });
+    }
+}
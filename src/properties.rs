@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use tiled::{Properties, PropertyValue};
+
+/// Typed accessors for custom properties set in Tiled.
+///
+/// Each getter returns `None` when the property is missing or has a different type.
+pub trait PropertiesExt {
+    fn get_bool(&self, name: &str) -> Option<bool>;
+    fn get_int(&self, name: &str) -> Option<i32>;
+    /// Int properties are converted to floats as well.
+    fn get_float(&self, name: &str) -> Option<f32>;
+    /// Tiled stores colors as `#AARRGGBB`.
+    fn get_color(&self, name: &str) -> Option<Color>;
+    fn get_string(&self, name: &str) -> Option<&str>;
+}
+
+impl PropertiesExt for Properties {
+    fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name) {
+            Some(PropertyValue::BoolValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn get_int(&self, name: &str) -> Option<i32> {
+        match self.get(name) {
+            Some(PropertyValue::IntValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn get_float(&self, name: &str) -> Option<f32> {
+        match self.get(name) {
+            Some(PropertyValue::FloatValue(value)) => Some(*value),
+            Some(PropertyValue::IntValue(value)) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    fn get_color(&self, name: &str) -> Option<Color> {
+        match self.get(name) {
+            Some(PropertyValue::ColorValue(argb)) => {
+                let [a, r, g, b] = argb.to_be_bytes();
+                Some(Color::rgba_u8(r, g, b, a))
+            }
+            _ => None,
+        }
+    }
+
+    fn get_string(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(PropertyValue::StringValue(value)) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+}
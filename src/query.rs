@@ -0,0 +1,89 @@
+use crate::{Map, PropertiesExt, TileHit, TiledConfig, TiledMapCenter};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// System parameter for point queries against the tiles of all spawned maps, e.g. as a
+/// simple collision check for platformers.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct MapQuery<'a> {
+    maps: Res<'a, Assets<Map>>,
+    config: Res<'a, TiledConfig>,
+    map_entities: Query<
+        'a,
+        (
+            Entity,
+            &'static Handle<Map>,
+            &'static TiledMapCenter,
+            &'static Transform,
+            &'static Option<Entity>,
+        ),
+    >,
+    parents: Query<'a, &'static GlobalTransform>,
+}
+
+impl<'a> MapQuery<'a> {
+    /// Whether any tile at a world position has the boolean property
+    /// `TiledConfig::solid_property` set to `true`.
+    pub fn is_solid(&self, world: Vec2) -> bool {
+        self.tile_has_property(world, &self.config.solid_property)
+    }
+
+    /// Whether any tile at a world position has a boolean tile property set to `true`.
+    pub fn tile_has_property(&self, world: Vec2, name: &str) -> bool {
+        self.placed_maps().any(|(_, map, tile_map_transform)| {
+            let tile = match map.world_to_tile(world, &tile_map_transform) {
+                Some(tile) => tile,
+                None => return false,
+            };
+            (0..map.map.layers.len())
+                .filter_map(|layer| map.gid_at(layer, tile))
+                .filter_map(|gid| map.tile_properties(gid))
+                .any(|properties| properties.get_bool(name) == Some(true))
+        })
+    }
+
+    /// Like `Map::raycast` between two world positions, on all spawned maps. Returns the
+    /// closest hit with the bundle entity of its map; its `position` is a world position.
+    pub fn raycast(
+        &self,
+        from_world: Vec2,
+        to_world: Vec2,
+        mut blocks: impl FnMut(u32, Option<&tiled::Properties>) -> bool,
+    ) -> Option<(Entity, TileHit)> {
+        let mut closest: Option<(Entity, TileHit)> = None;
+        for (entity, map, tile_map_transform) in self.placed_maps() {
+            let matrix = tile_map_transform.compute_matrix();
+            let inverse = matrix.inverse();
+            let from = inverse.transform_point3(from_world.extend(0.0)).truncate();
+            let to = inverse.transform_point3(to_world.extend(0.0)).truncate();
+            // fractions along the ray are the same in world and map space
+            let mut hit = match map.raycast(from, to, &mut blocks) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            if matches!(closest, Some((_, closest)) if closest.fraction <= hit.fraction) {
+                continue;
+            }
+            hit.position = matrix.transform_point3(hit.position.extend(0.0)).truncate();
+            closest = Some((entity, hit));
+        }
+        closest
+    }
+
+    // spawned maps with the transform their chunks are placed with
+    fn placed_maps(&self) -> impl Iterator<Item = (Entity, &Map, Transform)> + '_ {
+        self.map_entities.iter().filter_map(
+            move |(entity, map_handle, center, origin, optional_parent)| {
+                let map = self.maps.get(map_handle)?;
+                let mut tile_map_transform = map.tile_map_transform(origin, center);
+                // chunks are children of the parent entity, if there is one
+                if let Some(parent_transform) =
+                    optional_parent.and_then(|p| self.parents.get(p).ok())
+                {
+                    tile_map_transform = parent_transform.mul_transform(tile_map_transform).into();
+                }
+                Some((entity, map, tile_map_transform))
+            },
+        )
+    }
+}
@@ -0,0 +1,179 @@
+use crate::Map;
+use bevy::prelude::*;
+
+/// The first blocking tile found by `Map::raycast`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileHit {
+    pub tile: UVec2,
+    /// Index of the layer of the blocking tile, in `tiled::Map::layers`.
+    pub layer: usize,
+    pub gid: u32,
+    /// Where the ray enters the tile, relative to the map transform.
+    pub position: Vec2,
+    /// How far along the ray the tile is entered, from 0.0 at `from` to 1.0 at `to`.
+    pub fraction: f32,
+}
+
+impl Map {
+    /// Walks the tiles along the line from `from` to `to`, both relative to the map transform,
+    /// and returns the first tile for which `blocks` returns true. `blocks` receives each gid
+    /// and its tileset properties, starting with the topmost layer of every tile.
+    pub fn raycast(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        mut blocks: impl FnMut(u32, Option<&tiled::Properties>) -> bool,
+    ) -> Option<TileHit> {
+        let start = self.tile_coordinates(from)?;
+        let end = self.tile_coordinates(to)?;
+        let direction = end - start;
+
+        // grid traversal (Amanatides & Woo), t goes from 0.0 at start to 1.0 at end
+        let mut tile = IVec2::new(start.x.floor() as i32, start.y.floor() as i32);
+        let step = IVec2::new(direction.x.signum() as i32, direction.y.signum() as i32);
+        let t_delta = Vec2::new(1.0 / direction.x.abs(), 1.0 / direction.y.abs());
+        let boundary = |start: f32, tile: i32, direction: f32| {
+            if direction > 0.0 {
+                (tile as f32 + 1.0 - start) / direction
+            } else if direction < 0.0 {
+                (start - tile as f32) / -direction
+            } else {
+                f32::INFINITY
+            }
+        };
+        let mut t_max = Vec2::new(
+            boundary(start.x, tile.x, direction.x),
+            boundary(start.y, tile.y, direction.y),
+        );
+        let mut t = 0.0;
+
+        loop {
+            if tile.x >= 0
+                && tile.y >= 0
+                && tile.x < self.map.width as i32
+                && tile.y < self.map.height as i32
+            {
+                let tile_pos = UVec2::new(tile.x as u32, tile.y as u32);
+                for layer in (0..self.map.layers.len()).rev() {
+                    if let Some(gid) = self.gid_at(layer, tile_pos) {
+                        if blocks(gid, self.tile_properties(gid)) {
+                            return Some(TileHit {
+                                tile: tile_pos,
+                                layer,
+                                gid,
+                                position: from + (to - from) * t,
+                                fraction: t,
+                            });
+                        }
+                    }
+                }
+            }
+
+            if t_max.x.min(t_max.y) > 1.0 {
+                return None;
+            }
+            if t_max.x < t_max.y {
+                tile.x += step.x;
+                t = t_max.x;
+                t_max.x += t_delta.x;
+            } else {
+                tile.y += step.y;
+                t = t_max.y;
+                t_max.y += t_delta.y;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_maps::ascii_map, PropertiesExt};
+
+    fn solid(_: u32, properties: Option<&tiled::Properties>) -> bool {
+        properties.and_then(|p| p.get_bool("solid")) == Some(true)
+    }
+
+    // tiles are 16x16 pixels, with y pointing up from the top left corner of the map
+    fn wall_map() -> Map {
+        ascii_map(&["....", "..#.", "...."])
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-4, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn zero_length_ray_checks_its_tile() {
+        let map = wall_map();
+        let inside_wall = Vec2::new(40.0, -24.0);
+        let hit = map.raycast(inside_wall, inside_wall, solid).unwrap();
+        assert_eq!(hit.tile, UVec2::new(2, 1));
+        assert_eq!(hit.fraction, 0.0);
+        assert_near(hit.position, inside_wall);
+
+        let floor = Vec2::new(8.0, -8.0);
+        assert_eq!(map.raycast(floor, floor, solid), None);
+    }
+
+    #[test]
+    fn horizontal_ray_hits_wall_edge() {
+        let map = wall_map();
+        let hit = map
+            .raycast(Vec2::new(8.0, -24.0), Vec2::new(56.0, -24.0), solid)
+            .unwrap();
+        assert_eq!(hit.tile, UVec2::new(2, 1));
+        assert_eq!(hit.layer, 0);
+        assert_eq!(hit.gid, 2);
+        assert!((hit.fraction - 0.5).abs() < 1e-5);
+        assert_near(hit.position, Vec2::new(32.0, -24.0));
+
+        // the other way round, the wall is entered on its right edge
+        let hit = map
+            .raycast(Vec2::new(56.0, -24.0), Vec2::new(8.0, -24.0), solid)
+            .unwrap();
+        assert!((hit.fraction - 1.0 / 6.0).abs() < 1e-5);
+        assert_near(hit.position, Vec2::new(48.0, -24.0));
+    }
+
+    #[test]
+    fn vertical_ray_hits_wall_edge() {
+        let map = wall_map();
+        let hit = map
+            .raycast(Vec2::new(40.0, -8.0), Vec2::new(40.0, -40.0), solid)
+            .unwrap();
+        assert_eq!(hit.tile, UVec2::new(2, 1));
+        assert!((hit.fraction - 0.25).abs() < 1e-5);
+        assert_near(hit.position, Vec2::new(40.0, -16.0));
+    }
+
+    #[test]
+    fn ray_starting_outside_the_map() {
+        let map = wall_map();
+        let hit = map
+            .raycast(Vec2::new(-24.0, -24.0), Vec2::new(56.0, -24.0), solid)
+            .unwrap();
+        assert_eq!(hit.tile, UVec2::new(2, 1));
+        assert!((hit.fraction - 0.7).abs() < 1e-5);
+        assert_near(hit.position, Vec2::new(32.0, -24.0));
+
+        // passing by outside of the map
+        assert_eq!(
+            map.raycast(Vec2::new(-24.0, 8.0), Vec2::new(80.0, 8.0), solid),
+            None
+        );
+    }
+
+    #[test]
+    fn clear_line_has_no_hit() {
+        let map = wall_map();
+        assert_eq!(
+            map.raycast(Vec2::new(8.0, -24.0), Vec2::new(24.0, -24.0), solid),
+            None
+        );
+        assert_eq!(
+            map.raycast(Vec2::new(8.0, -8.0), Vec2::new(56.0, -40.0), |_, _| false),
+            None
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{Map, Object, PropertiesExt};
+use bevy::{ecs::component::Component, prelude::*, utils::HashMap};
+use tiled::Properties;
+
+/// Custom property holding the class of a map or layer made before Tiled 1.9, which added the
+/// `class` attribute. The attribute is used when both are set.
+pub const CLASS_PROPERTY: &str = "class";
+
+/// What a registered object spawner gets to build its prefab from.
+#[derive(Debug, Clone)]
+pub struct ObjectSpawnContext {
+    /// Already spawned with the object's `Transform`, `GlobalTransform`, map handle, `Object`
+    /// and shape component, but no sprite or debug shape.
+    pub entity: Entity,
+    pub object: Object,
+    pub map_handle: Handle<Map>,
+    /// Atlas and index of the object's tile, for tile objects outside of headless mode.
+    pub texture_atlas: Option<Handle<TextureAtlas>>,
+    pub sprite_index: Option<u32>,
+    /// Opacity of the object's group, which sprites of the default spawning are drawn with.
+    pub opacity: f32,
+}
+
+pub type ObjectSpawner = Box<dyn Fn(ObjectSpawnContext, &mut Commands) + Send + Sync>;
+
+/// Object spawners by object type, see `RegisterObjectSpawner`.
+#[derive(Default)]
+pub struct ObjectSpawners {
+    spawners: HashMap<String, ObjectSpawner>,
+}
+
+impl ObjectSpawners {
+    pub fn insert(
+        &mut self,
+        obj_type: impl Into<String>,
+        spawner: impl Fn(ObjectSpawnContext, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.spawners.insert(obj_type.into(), Box::new(spawner));
+    }
+
+    pub fn get(&self, obj_type: &str) -> Option<&ObjectSpawner> {
+        self.spawners.get(obj_type)
+    }
+}
+
+pub trait RegisterObjectSpawner {
+    /// Spawns objects of a type with user code instead of the default sprite or debug shape,
+    /// e.g. to turn every `"Chest"` object into a full prefab.
+    fn register_object_spawner(
+        &mut self,
+        obj_type: impl Into<String>,
+        spawner: impl Fn(ObjectSpawnContext, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl RegisterObjectSpawner for AppBuilder {
+    fn register_object_spawner(
+        &mut self,
+        obj_type: impl Into<String>,
+        spawner: impl Fn(ObjectSpawnContext, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.init_resource::<ObjectSpawners>();
+        self.world_mut()
+            .get_resource_mut::<ObjectSpawners>()
+            .unwrap()
+            .insert(obj_type, spawner);
+        self
+    }
+}
+
+/// The class of a map or layer, from its `class` property. See `Map::class` and `Layer::class`
+/// for the class including the `class` attribute.
+pub fn class_of(properties: &Properties) -> Option<&str> {
+    properties.get_string(CLASS_PROPERTY)
+}
+
+pub type ClassSpawner = Box<dyn Fn(Entity, &mut Commands) + Send + Sync>;
+
+/// Spawners by map or layer class, see `RegisterClassComponent`.
+#[derive(Default)]
+pub struct ClassSpawners {
+    spawners: HashMap<String, Vec<ClassSpawner>>,
+}
+
+impl ClassSpawners {
+    pub fn insert(
+        &mut self,
+        class: impl Into<String>,
+        spawner: impl Fn(Entity, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.spawners
+            .entry(class.into())
+            .or_default()
+            .push(Box::new(spawner));
+    }
+
+    /// Runs the spawners registered for `class`, if any.
+    pub fn spawn(&self, class: Option<&str>, entity: Entity, commands: &mut Commands) {
+        let spawners = class.and_then(|class| self.spawners.get(class));
+        for spawner in spawners.into_iter().flatten() {
+            spawner(entity, commands);
+        }
+    }
+}
+
+pub trait RegisterClassComponent {
+    /// Inserts a component on the map root, chunk and object layer entities of maps and layers
+    /// of a class, e.g. a `Hazard` on the chunks of every `"Hazard"` layer.
+    fn register_class_component<C: Component + Default>(
+        &mut self,
+        class: impl Into<String>,
+    ) -> &mut Self;
+
+    /// Like `register_class_component`, but with user code to build the entity.
+    fn register_class_spawner(
+        &mut self,
+        class: impl Into<String>,
+        spawner: impl Fn(Entity, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl RegisterClassComponent for AppBuilder {
+    fn register_class_component<C: Component + Default>(
+        &mut self,
+        class: impl Into<String>,
+    ) -> &mut Self {
+        self.register_class_spawner(class, |entity, commands| {
+            commands.entity(entity).insert(C::default());
+        })
+    }
+
+    fn register_class_spawner(
+        &mut self,
+        class: impl Into<String>,
+        spawner: impl Fn(Entity, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.init_resource::<ClassSpawners>();
+        self.world_mut()
+            .get_resource_mut::<ClassSpawners>()
+            .unwrap()
+            .insert(class, spawner);
+        self
+    }
+}
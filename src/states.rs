@@ -0,0 +1,58 @@
+use crate::TiledMapBundle;
+use bevy::{ecs::component::Component, prelude::*};
+use std::{fmt::Debug, hash::Hash};
+
+/// Marks the parent entity and bundle entity of a map spawned by `add_tiled_map_for_state`,
+/// with the state it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateScopedMap<T>(pub T);
+
+pub trait AddTiledMapForState {
+    /// Spawns a map when a state is entered and despawns it with all of its chunks and objects
+    /// when the state is exited, e.g. a level map for each level state. The state must already
+    /// be added to the app.
+    fn add_tiled_map_for_state<T: Component + Debug + Clone + Eq + Hash>(
+        &mut self,
+        state: T,
+        path: impl Into<String>,
+    ) -> &mut Self;
+}
+
+impl AddTiledMapForState for AppBuilder {
+    fn add_tiled_map_for_state<T: Component + Debug + Clone + Eq + Hash>(
+        &mut self,
+        state: T,
+        path: impl Into<String>,
+    ) -> &mut Self {
+        let path = path.into();
+        let scope = state.clone();
+        let spawn_map = move |mut commands: Commands, asset_server: Res<AssetServer>| {
+            // chunks and objects are children of the parent, like with world streaming
+            let parent = commands
+                .spawn_bundle((
+                    Transform::default(),
+                    GlobalTransform::default(),
+                    StateScopedMap(scope.clone()),
+                ))
+                .id();
+            commands
+                .spawn_bundle(TiledMapBundle {
+                    map_asset: asset_server.load(path.as_str()),
+                    parent_option: Some(parent),
+                    ..Default::default()
+                })
+                .insert(StateScopedMap(scope.clone()));
+        };
+        let scope = state.clone();
+        let despawn_maps =
+            move |mut commands: Commands, maps: Query<(Entity, &StateScopedMap<T>)>| {
+                for (entity, map_state) in maps.iter() {
+                    if map_state.0 == scope {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            };
+        self.add_system_set(SystemSet::on_enter(state.clone()).with_system(spawn_map.system()))
+            .add_system_set(SystemSet::on_exit(state).with_system(despawn_maps.system()))
+    }
+}
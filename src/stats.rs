@@ -0,0 +1,104 @@
+use crate::{loader::TiledMapLoader, Map};
+use bevy::utils::HashSet;
+
+// bytes per tile of a chunk mesh: four vertices with a position and texture coordinates, and
+// six u32 indices
+const BYTES_PER_TILE: usize = 4 * (3 + 2) * 4 + 6 * 4;
+
+/// Content statistics of a map, e.g. for asset budgets or CI checks on level content, see
+/// `Map::stats`.
+#[derive(Debug, Clone, Default)]
+pub struct MapStats {
+    pub layers: Vec<LayerStats>,
+    pub object_layers: Vec<ObjectLayerStats>,
+    /// Gids of all tiles placed in tile layers or as tile objects, without flip flags.
+    pub used_gids: HashSet<u32>,
+    /// Chunks with tiles, over all tile layers. Each is one mesh and one entity when spawned.
+    pub chunks: usize,
+    /// Estimated size of the vertex and index data of all chunk meshes, in bytes.
+    pub vertex_bytes: usize,
+    /// Objects over all object layers.
+    pub objects: usize,
+}
+
+/// Statistics of one tile layer, see `MapStats`.
+#[derive(Debug, Clone, Default)]
+pub struct LayerStats {
+    pub name: String,
+    /// Index of the layer in `tiled::Map::layers`.
+    pub layer_index: usize,
+    /// Non-empty tiles of the layer.
+    pub tiles: usize,
+    /// Chunks of the layer with tiles, over all of its tilesets.
+    pub chunks: usize,
+}
+
+/// Statistics of one object layer, see `MapStats`.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectLayerStats {
+    pub name: String,
+    pub objects: usize,
+    /// Objects of the layer that are tile objects.
+    pub tile_objects: usize,
+}
+
+impl Map {
+    /// Counts the tiles, chunks and objects of the map. Tiles left out by
+    /// `TiledConfig::occlude_hidden_tiles` are counted, so the vertex size is an upper bound.
+    pub fn stats(&self) -> MapStats {
+        let mut stats = MapStats::default();
+
+        for (layer_index, layer) in self.map.layers.iter().enumerate() {
+            let mut tiles = 0;
+            if let tiled::LayerData::Finite(rows) = &layer.tiles {
+                for tile in rows.iter().flatten().filter(|tile| tile.gid != 0) {
+                    tiles += 1;
+                    stats.used_gids.insert(tile.gid);
+                }
+            }
+            // chunk data may have been discarded, which keeps empty chunks empty
+            let chunks = self
+                .layers
+                .iter()
+                .find(|built| built.layer_index == layer_index)
+                .map(|built| {
+                    built
+                        .tileset_layers
+                        .iter()
+                        .flat_map(|tileset_layer| tileset_layer.chunks.iter().flatten())
+                        .filter(|chunk| chunk.discarded || !chunk.tiles.is_empty())
+                        .count()
+                })
+                .unwrap_or(0);
+            stats.chunks += chunks;
+            stats.vertex_bytes += tiles * BYTES_PER_TILE;
+            stats.layers.push(LayerStats {
+                name: layer.name.clone(),
+                layer_index,
+                tiles,
+                chunks,
+            });
+        }
+
+        for group in self.groups.iter() {
+            let tile_objects = group
+                .objects
+                .iter()
+                .filter(|object| object.gid() != 0)
+                .inspect(|object| {
+                    stats
+                        .used_gids
+                        .insert(TiledMapLoader::remove_tile_flags(object.gid()));
+                })
+                .count();
+            stats.objects += group.objects.len();
+            stats.object_layers.push(ObjectLayerStats {
+                name: group.name.clone(),
+                objects: group.objects.len(),
+                tile_objects,
+            });
+        }
+
+        stats
+    }
+}
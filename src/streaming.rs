@@ -0,0 +1,223 @@
+use crate::{
+    with_tileset_layer, BakedChunk, Chunk, Map, TiledConfig, TiledMapBundle, TilesetArrayLayer,
+};
+use bevy::{
+    prelude::*,
+    render::{camera::Camera, render_graph::base::camera::CAMERA_2D},
+    utils::HashMap,
+};
+use std::{cmp::Ordering, path::PathBuf};
+
+/// Identifies the chunk of a map that a chunk entity renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkLocation {
+    pub layer_id: usize,
+    pub tileset_guid: u32,
+    pub position: UVec2,
+}
+
+/// Marks chunk entities whose mesh was dropped to stay within `TiledConfig::max_loaded_chunks`.
+pub struct UnloadedChunk;
+
+/// Keeps at most `TiledConfig::max_loaded_chunks` chunk meshes loaded, preferring the chunks
+/// closest to the 2d camera. Dropped meshes are rebuilt from the map's tile data when needed.
+#[allow(clippy::type_complexity)]
+pub fn unload_distant_chunks(
+    mut commands: Commands,
+    config: Res<TiledConfig>,
+    maps: Res<Assets<Map>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut chunks: Query<
+        (
+            Entity,
+            &ChunkLocation,
+            &Handle<Map>,
+            &GlobalTransform,
+            &mut Handle<Mesh>,
+            Option<&UnloadedChunk>,
+            Option<&TilesetArrayLayer>,
+        ),
+        Without<BakedChunk>,
+    >,
+) {
+    let budget = match config.max_loaded_chunks {
+        Some(budget) => budget,
+        None => return,
+    };
+    let camera_position = match cameras
+        .iter()
+        .find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))
+    {
+        Some((_, transform)) => transform.translation.truncate(),
+        None => return,
+    };
+
+    let mut by_distance = chunks
+        .iter_mut()
+        .filter_map(|(entity, location, map_handle, transform, ..)| {
+            let map = maps.get(map_handle)?;
+            let chunk = map_chunk(map, location)?;
+            let center = transform.mul_vec3(chunk.center()?.extend(0.0)).truncate();
+            // a degenerate transform gives a NaN distance, such chunks go last
+            let distance = center.distance_squared(camera_position);
+            Some((
+                entity,
+                if distance.is_nan() {
+                    f32::INFINITY
+                } else {
+                    distance
+                },
+            ))
+        })
+        .collect::<Vec<_>>();
+    by_distance.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    for (index, (entity, _)) in by_distance.into_iter().enumerate() {
+        let (_, location, map_handle, _, mut mesh, unloaded, array_layer) =
+            match chunks.get_mut(entity) {
+                Ok(chunk) => chunk,
+                Err(_) => continue,
+            };
+        let keep = index < budget;
+        if keep && unloaded.is_some() {
+            let rebuilt = maps
+                .get(map_handle)
+                .and_then(|map| map_chunk(map, location))
+                .and_then(|chunk| chunk.build_mesh())
+                .map(|rebuilt| match array_layer {
+                    Some(TilesetArrayLayer(layer)) => with_tileset_layer(rebuilt, *layer),
+                    None => rebuilt,
+                });
+            if let Some(rebuilt) = rebuilt {
+                *mesh = meshes.add(rebuilt);
+                commands.entity(entity).remove::<UnloadedChunk>();
+            }
+        } else if !keep && unloaded.is_none() {
+            // the chunk entity holds the only strong handle, so this frees the mesh
+            meshes.remove(&*mesh);
+            *mesh = Handle::default();
+            commands.entity(entity).insert(UnloadedChunk);
+        }
+    }
+}
+
+pub(crate) fn map_chunk<'a>(map: &'a Map, location: &ChunkLocation) -> Option<&'a Chunk> {
+    map.layers
+        .get(location.layer_id)?
+        .tileset_layers
+        .iter()
+        .find(|tileset_layer| tileset_layer.tileset_guid == location.tileset_guid)?
+        .chunks
+        .get(location.position.x as usize)?
+        .get(location.position.y as usize)
+}
+
+/// A map placed in a world made of adjacent tmx files, like the entries of a Tiled `.world` file.
+#[derive(Debug, Clone)]
+pub struct WorldMap {
+    pub path: PathBuf,
+    /// Top left corner of the map in world pixels, with y pointing down as in Tiled.
+    pub position: Vec2,
+    /// Size of the map in pixels.
+    pub size: Vec2,
+}
+
+/// Loads the maps of a world close to the 2d camera and unloads them once it moves away.
+///
+/// Insert it as a resource; every map is spawned under its own `MapRoot` parent entity,
+/// positioned relative to `origin`.
+#[derive(Debug, Default)]
+pub struct WorldStreamer {
+    pub maps: Vec<WorldMap>,
+    /// Maps whose bounds are within this distance of the camera are loaded.
+    pub load_distance: f32,
+    pub origin: Transform,
+    // maps index into `maps` to the spawned parent and map bundle entities
+    loaded: HashMap<usize, (Entity, Entity)>,
+}
+
+impl WorldStreamer {
+    pub fn new(maps: Vec<WorldMap>, load_distance: f32) -> Self {
+        Self {
+            maps,
+            load_distance,
+            ..Default::default()
+        }
+    }
+
+    /// Parent entity of a world map, if it is currently loaded.
+    pub fn loaded_map(&self, index: usize) -> Option<Entity> {
+        self.loaded.get(&index).map(|(parent, _)| *parent)
+    }
+}
+
+pub fn stream_world_maps(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    streamer: Option<ResMut<WorldStreamer>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    let mut streamer = match streamer {
+        Some(streamer) => streamer,
+        None => return,
+    };
+    let camera_position = match cameras
+        .iter()
+        .find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))
+    {
+        Some((_, transform)) => transform.translation,
+        None => return,
+    };
+    // bring the camera into world pixels, so map bounds can be compared directly
+    let camera_position = streamer
+        .origin
+        .compute_matrix()
+        .inverse()
+        .transform_point3(camera_position)
+        .truncate();
+    let camera_position = Vec2::new(camera_position.x, -camera_position.y);
+    let load_distance = streamer.load_distance / streamer.origin.scale.truncate().max_element();
+
+    for index in 0..streamer.maps.len() {
+        let world_map = &streamer.maps[index];
+        let closest = camera_position
+            .max(world_map.position)
+            .min(world_map.position + world_map.size);
+        let in_range = closest.distance(camera_position) <= load_distance;
+
+        match (in_range, streamer.loaded.contains_key(&index)) {
+            (true, false) => {
+                let placement = Transform::from_translation(Vec3::new(
+                    world_map.position.x,
+                    -world_map.position.y,
+                    0.0,
+                ));
+                let parent = commands
+                    .spawn_bundle((
+                        Transform::from_matrix(
+                            streamer.origin.compute_matrix() * placement.compute_matrix(),
+                        ),
+                        GlobalTransform::default(),
+                    ))
+                    .id();
+                let map = commands
+                    .spawn_bundle(TiledMapBundle {
+                        map_asset: asset_server.load(world_map.path.clone()),
+                        parent_option: Some(parent),
+                        ..Default::default()
+                    })
+                    .id();
+                streamer.loaded.insert(index, (parent, map));
+            }
+            (false, _) => {
+                if let Some((parent, map)) = streamer.loaded.remove(&index) {
+                    // chunks and objects are children of the parent
+                    commands.entity(parent).despawn_recursive();
+                    commands.entity(map).despawn();
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{Map, TiledConfig};
+use std::path::Path;
+
+/// Loads a map from `tests/fixtures`.
+pub(crate) fn fixture_map(name: &str) -> Map {
+    let path = Path::new("tests/fixtures").join(name);
+    let bytes = std::fs::read(&path).unwrap();
+    Map::try_from_bytes(&path, bytes, &TiledConfig::default()).unwrap()
+}
+
+/// A single layer map with the 16x16 tiles of `tests/fixtures/tiles.tmx`, one character per
+/// tile: `.` floor, `#` a wall with the `solid` property, `~` mud with a `cost` of 5.0 and
+/// anything else an empty cell.
+pub(crate) fn ascii_map(rows: &[&str]) -> Map {
+    let gids = rows
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|tile| match tile {
+                    '.' => 1,
+                    '#' => 2,
+                    '~' => 3,
+                    _ => 0,
+                })
+                .collect()
+        })
+        .collect();
+    Map::generate(
+        &fixture_map("tiles.tmx"),
+        vec![gids],
+        &TiledConfig::default(),
+    )
+    .unwrap()
+}
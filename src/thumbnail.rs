@@ -0,0 +1,54 @@
+use crate::{bake::draw_tiles, Map};
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+
+impl Map {
+    /// Draws the visible tile layers of the map into an image with `scale` pixels per map pixel,
+    /// without spawning anything, e.g. for level select screens or save slot previews.
+    /// `None` until all tileset textures are loaded, or if chunk data was discarded.
+    pub fn thumbnail(&self, textures: &Assets<Texture>, scale: f32) -> Option<Texture> {
+        let bounds = self.bounds()?;
+        let width = (bounds.size().x * scale).ceil().max(1.0) as usize;
+        let height = (bounds.size().y * scale).ceil().max(1.0) as usize;
+        let mut data = vec![0u8; width * height * 4];
+
+        let mut layers = self
+            .layers
+            .iter()
+            .filter(|layer| layer.visible)
+            .collect::<Vec<_>>();
+        layers.sort_by_key(|layer| layer.z_index);
+        for layer in layers {
+            for tileset_layer in layer.tileset_layers.iter() {
+                let tileset_image =
+                    textures.get(self.tileset_textures.get(&tileset_layer.tileset_guid)?)?;
+                match tileset_image.format {
+                    TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => {}
+                    _ => return None,
+                }
+                let chunks = tileset_layer.chunks.iter().flatten();
+                if chunks.clone().any(|chunk| chunk.discarded) {
+                    return None;
+                }
+                draw_tiles(
+                    chunks.flat_map(|chunk| chunk.tiles.iter()),
+                    tileset_image,
+                    bounds.min,
+                    bounds.max.y,
+                    scale,
+                    (width, height),
+                    &mut data,
+                );
+            }
+        }
+
+        Some(Texture::new(
+            Extent3d::new(width as u32, height as u32, 1),
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        ))
+    }
+}
@@ -1,6 +1,7 @@
 #version 450
 
 layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_Tint;
 
 layout(location = 0) out vec4 o_Target;
 
@@ -14,7 +15,7 @@ layout(set = 1, binding = 2) uniform sampler ColorMaterial_texture_sampler;
 # endif
 
 void main() {
-    vec4 color = Color;
+    vec4 color = Color * v_Tint;
 # ifdef COLORMATERIAL_TEXTURE
     color *= texture(
         sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler),
@@ -1,12 +1,216 @@
-use bevy::core::Byteable;
-use bevy::render::renderer::{RenderResource, RenderResources};
-
-#[repr(C)]
-#[derive(Default, RenderResources, RenderResource)]
-#[render_resources(from_self)]
-pub struct TileMapChunk {
-    pub layer_id: f32,
-}
-
-// SAFE: sprite is repr(C) and only consists of byteables
-unsafe impl Byteable for TileMapChunk {}
+use crate::{ChunkLocation, Map, PropertiesExt, TilesetArray};
+use bevy::core::Byteable;
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use bevy::render::renderer::{RenderResource, RenderResources};
+use bevy::render::texture::AddressMode;
+use bevy::utils::HashMap;
+
+#[repr(C)]
+#[derive(RenderResources, RenderResource, Reflect)]
+#[reflect(Component)]
+#[render_resources(from_self)]
+pub struct TileMapChunk {
+    /// Multiplied into the color of the chunk's tiles, as linear RGBA, see `set_tint`.
+    pub tint: Vec4,
+    pub layer_id: f32,
+    // aligns `uv_offset` to 8 bytes, as in the uniform block, without SIMD too
+    #[reflect(ignore)]
+    _padding: f32,
+    /// Added to the texture coordinates of the chunk's tiles, see `UvScroll`.
+    pub uv_offset: Vec2,
+}
+
+impl TileMapChunk {
+    pub fn new(layer_id: f32) -> Self {
+        Self {
+            layer_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_tint(&mut self, color: Color) {
+        self.tint = color.as_linear_rgba_f32().into();
+    }
+}
+
+impl Default for TileMapChunk {
+    fn default() -> Self {
+        Self {
+            tint: Vec4::ONE,
+            layer_id: 0.0,
+            _padding: 0.0,
+            uv_offset: Vec2::ZERO,
+        }
+    }
+}
+
+// SAFE: sprite is repr(C) and only consists of byteables
+unsafe impl Byteable for TileMapChunk {}
+
+/// Tints the chunks of a map entity's tile layers, by layer index. Changing it sets the tint
+/// of all of the map's chunks, so it overrides tints set on single chunks.
+#[derive(Debug, Default, Clone)]
+pub struct LayerTints(pub HashMap<usize, Color>);
+
+/// Applies `LayerTints` to the chunks of their map, including chunks spawned later on.
+pub fn apply_layer_tints(
+    map_entities: Query<(&Handle<Map>, ChangeTrackers<LayerTints>, &LayerTints)>,
+    mut chunks: Query<(
+        &ChunkLocation,
+        &Handle<Map>,
+        ChangeTrackers<ChunkLocation>,
+        &mut TileMapChunk,
+    )>,
+) {
+    if map_entities.iter().next().is_none() {
+        return;
+    }
+    for (location, map_handle, chunk_tracker, mut chunk) in chunks.iter_mut() {
+        let (_, tints_tracker, tints) = match map_entities
+            .iter()
+            .find(|(handle, ..)| *handle == map_handle)
+        {
+            Some(map_entity) => map_entity,
+            None => continue,
+        };
+        if tints_tracker.is_changed() || chunk_tracker.is_added() {
+            let tint = tints.0.get(&location.layer_id).copied();
+            chunk.set_tint(tint.unwrap_or(Color::WHITE));
+        }
+    }
+}
+
+/// Scrolls the texture coordinates of a tile layer over time, e.g. for flowing water or lava
+/// painted with a seamless tileset. Set in Tiled with the float layer properties `uv_scroll_x`
+/// and `uv_scroll_y`, or at runtime with `Map::set_layer_uv_scroll`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvScroll {
+    /// Texture coordinates scrolled per second, where 1.0 is the width or height of the
+    /// tileset image.
+    pub velocity: Vec2,
+}
+
+impl UvScroll {
+    pub const PROPERTY_X: &'static str = "uv_scroll_x";
+    pub const PROPERTY_Y: &'static str = "uv_scroll_y";
+
+    pub fn from_properties(properties: &tiled::Properties) -> Option<UvScroll> {
+        let x = properties.get_float(UvScroll::PROPERTY_X);
+        let y = properties.get_float(UvScroll::PROPERTY_Y);
+        if x.is_none() && y.is_none() {
+            return None;
+        }
+        Some(UvScroll {
+            velocity: Vec2::new(x.unwrap_or(0.0), y.unwrap_or(0.0)),
+        })
+    }
+}
+
+/// Applies the `UvScroll` of layers to their chunks, and makes their tileset textures repeat
+/// so tiles wrap around instead of stretching the image edge.
+#[allow(clippy::type_complexity)]
+pub fn scroll_layer_uvs(
+    time: Res<Time>,
+    maps: Res<Assets<Map>>,
+    materials: Res<Assets<ColorMaterial>>,
+    arrays: Res<Assets<TilesetArray>>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut chunks: Query<(
+        &ChunkLocation,
+        &Handle<Map>,
+        &mut TileMapChunk,
+        Option<&Handle<ColorMaterial>>,
+        Option<&Handle<TilesetArray>>,
+    )>,
+) {
+    let elapsed = time.seconds_since_startup();
+    for (location, map_handle, mut chunk, material, array) in chunks.iter_mut() {
+        let uv_scroll = maps
+            .get(map_handle)
+            .and_then(|map| map.layers.get(location.layer_id))
+            .and_then(|layer| layer.uv_scroll);
+        // wrapped, so offsets stay precise however long the app runs
+        let uv_offset = match uv_scroll {
+            Some(UvScroll { velocity }) => Vec2::new(
+                (velocity.x as f64 * elapsed).fract() as f32,
+                (velocity.y as f64 * elapsed).fract() as f32,
+            ),
+            None => Vec2::ZERO,
+        };
+        if chunk.uv_offset != uv_offset {
+            chunk.uv_offset = uv_offset;
+        }
+        if uv_scroll.is_none() {
+            continue;
+        }
+
+        let texture = match array {
+            Some(array) => arrays.get(array).map(|array| &array.texture),
+            None => material
+                .and_then(|material| materials.get(material))
+                .and_then(|material| material.texture.as_ref()),
+        };
+        // setting the address mode modifies the texture, so only do it when it differs
+        let needs_repeat = texture
+            .and_then(|texture| textures.get(texture))
+            .map(|texture| {
+                texture.sampler.address_mode_u != AddressMode::Repeat
+                    || texture.sampler.address_mode_v != AddressMode::Repeat
+            })
+            .unwrap_or(false);
+        if needs_repeat {
+            let texture = textures.get_mut(texture.unwrap()).unwrap();
+            texture.sampler.address_mode_u = AddressMode::Repeat;
+            texture.sampler.address_mode_v = AddressMode::Repeat;
+        }
+    }
+}
+
+/// Shows or hides the chunks of a map entity's tile layers, by layer index, e.g. to toggle a
+/// roof layer. Layers without an entry keep their visibility from Tiled.
+#[derive(Debug, Default, Clone)]
+pub struct LayerVisibility(pub HashMap<usize, bool>);
+
+impl LayerVisibility {
+    pub fn set_layer_visible(&mut self, layer_id: usize, visible: bool) {
+        self.0.insert(layer_id, visible);
+    }
+}
+
+/// Applies `LayerVisibility` to the chunks of their map, including chunks spawned later on.
+pub fn apply_layer_visibility(
+    maps: Res<Assets<Map>>,
+    map_entities: Query<(
+        &Handle<Map>,
+        ChangeTrackers<LayerVisibility>,
+        &LayerVisibility,
+    )>,
+    mut chunks: Query<(
+        &ChunkLocation,
+        &Handle<Map>,
+        ChangeTrackers<ChunkLocation>,
+        &mut Visible,
+    )>,
+) {
+    if map_entities.iter().next().is_none() {
+        return;
+    }
+    for (location, map_handle, chunk_tracker, mut visible) in chunks.iter_mut() {
+        let (_, visibility_tracker, visibility) = match map_entities
+            .iter()
+            .find(|(handle, ..)| *handle == map_handle)
+        {
+            Some(map_entity) => map_entity,
+            None => continue,
+        };
+        if visibility_tracker.is_changed() || chunk_tracker.is_added() {
+            let layer_visible = visibility.0.get(&location.layer_id).copied().or_else(|| {
+                let layer = maps.get(map_handle)?.layers.get(location.layer_id)?;
+                Some(layer.visible)
+            });
+            visible.is_visible = layer_visible.unwrap_or(true);
+        }
+    }
+}
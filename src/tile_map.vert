@@ -4,6 +4,7 @@ layout(location = 0) in vec3 Vertex_Position;
 layout(location = 2) in vec2 Vertex_Uv;
 
 layout(location = 0) out vec2 v_Uv;
+layout(location = 1) out vec4 v_Tint;
 
 layout(set = 0, binding = 0) uniform CameraViewProj {
     mat4 ViewProj;
@@ -14,10 +15,13 @@ layout(set = 2, binding = 0) uniform Transform {
 };
 
 layout(set = 2, binding = 1) uniform TileMapChunk {
+    vec4 tint;
     float layer_id;
+    vec2 uv_offset;
 };
 
 void main() {
-    v_Uv = Vertex_Uv;
-    gl_Position = ViewProj * Model * vec4(Vertex_Position.xy, layer_id, 1.0);
+    v_Uv = Vertex_Uv + uv_offset;
+    v_Tint = tint;
+    gl_Position = ViewProj * Model * vec4(Vertex_Position.xy, layer_id + Vertex_Position.z, 1.0);
 }
\ No newline at end of file
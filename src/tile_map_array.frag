@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_Tint;
+layout(location = 2) in float v_TilesetLayer;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform texture2DArray TilesetArray_texture;
+layout(set = 1, binding = 1) uniform sampler TilesetArray_texture_sampler;
+
+void main() {
+    o_Target = v_Tint * texture(
+        sampler2DArray(TilesetArray_texture, TilesetArray_texture_sampler),
+        vec3(v_Uv, v_TilesetLayer));
+}
\ No newline at end of file
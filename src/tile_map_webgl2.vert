@@ -4,6 +4,7 @@ layout(location = 0) in vec3 Vertex_Position;
 layout(location = 2) in vec2 Vertex_Uv;
 
 out vec2 v_Uv;
+out vec4 v_Tint;
 
 layout(std140) uniform CameraViewProj {
     mat4 ViewProj;
@@ -13,11 +14,14 @@ layout(std140) uniform Transform {  // set = 2, binding = 0
     mat4 Model;
 };
 
-layout(std140) uniform TileMapChunk {  // set = 2, binding = 1
+layout(std140) uniform TileMapChunk {
+    vec4 tint;  // set = 2, binding = 1
     float layer_id;
+    vec2 uv_offset;
 };
 
 void main() {
-    v_Uv = Vertex_Uv;
-    gl_Position = ViewProj * Model * vec4(Vertex_Position.xy, layer_id, 1.0);
+    v_Uv = Vertex_Uv + uv_offset;
+    v_Tint = tint;
+    gl_Position = ViewProj * Model * vec4(Vertex_Position.xy, layer_id + Vertex_Position.z, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,216 @@
+use crate::{
+    BlendMode, ChunkLocation, Map, MapPipelines, MipmappedTextures, PropertiesExt, TiledConfig,
+    BAKE_PROPERTY,
+};
+use bevy::{
+    asset::HandleId,
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::VertexAttributeValues,
+        pipeline::{PipelineDescriptor, RenderPipeline},
+        renderer::RenderResources,
+        texture::{Extent3d, TextureDimension},
+    },
+    utils::HashMap,
+};
+
+pub const TILE_MAP_ARRAY_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 4129645945969645249);
+
+/// The tileset images of a map stacked into the layers of one array texture, shared by the
+/// chunks of the map when `TiledConfig::tileset_array` is set.
+#[derive(Debug, Default, RenderResources, TypeUuid)]
+#[uuid = "787c9164-b012-41ef-a287-df5e7ae24301"]
+pub struct TilesetArray {
+    pub texture: Handle<Texture>,
+}
+
+/// Layer of its map's `TilesetArray` that a chunk samples, also stored in its mesh as the
+/// `Vertex_TilesetLayer` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilesetArrayLayer(pub u32);
+
+/// The `TilesetArray` of a map and the tileset images it was stacked from, by first gid.
+#[derive(Default)]
+pub struct BuiltTilesetArray {
+    images: Vec<(u32, Handle<Texture>)>,
+    array: Option<Handle<TilesetArray>>,
+}
+
+/// Switches the chunks of maps to their map's `TilesetArray` once all of its tileset images are
+/// loaded, so that every chunk of a map is drawn with the same pipeline and bindings. Chunks are
+/// still split per layer and tileset, so this saves rebinding between draws but doesn't reduce
+/// their number.
+///
+/// Tileset images must share their size and format, otherwise the map keeps one material per
+/// tileset. Maps with a single tileset already share one material and are left as they are. Chunks of baked layers, of layers not blended with `BlendMode::Alpha` and of layers
+/// with a custom pipeline in `MapPipelines` are left as they are.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn use_tileset_arrays(
+    mut commands: Commands,
+    config: Res<TiledConfig>,
+    maps: Res<Assets<Map>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut arrays: ResMut<Assets<TilesetArray>>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut mipmapped: ResMut<MipmappedTextures>,
+    map_pipelines: Query<(&Handle<Map>, &MapPipelines)>,
+    new_chunks: Query<(Entity, &ChunkLocation, &Handle<Map>), Added<ChunkLocation>>,
+    array_chunks: Query<(Entity, &Handle<Map>), With<TilesetArrayLayer>>,
+    mut chunks: Query<(
+        &ChunkLocation,
+        &Handle<Map>,
+        &mut Handle<Mesh>,
+        &mut RenderPipelines,
+    )>,
+    // chunks waiting for the tileset images of their map
+    mut pending: Local<Vec<Entity>>,
+    mut built: Local<HashMap<HandleId, BuiltTilesetArray>>,
+) {
+    if !config.tileset_array || cfg!(feature = "web") {
+        return;
+    }
+
+    // restack the arrays of hot reloaded tileset images
+    for event in texture_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            built.retain(|map_id, built| {
+                if !built.images.iter().any(|(_, image)| image == handle) {
+                    return true;
+                }
+                pending.extend(
+                    array_chunks
+                        .iter()
+                        .filter(|(_, map_handle)| map_handle.id == *map_id)
+                        .map(|(entity, _)| entity),
+                );
+                false
+            });
+        }
+    }
+
+    for (entity, location, map_handle) in new_chunks.iter() {
+        let layer = match maps
+            .get(map_handle)
+            .and_then(|map| map.layers.get(location.layer_id))
+        {
+            Some(layer) => layer,
+            None => continue,
+        };
+        let pipeline = map_pipelines
+            .iter()
+            .find(|(handle, _)| *handle == map_handle)
+            .map(|(_, pipelines)| pipelines.pipeline(location.layer_id, layer.blend_mode))
+            .unwrap_or_else(|| layer.blend_mode.pipeline_handle());
+        let baked = layer.properties.get_bool(BAKE_PROPERTY).unwrap_or(false);
+        if pipeline == BlendMode::Alpha.pipeline_handle() && !baked {
+            pending.push(entity);
+        }
+    }
+
+    pending.retain(|entity| {
+        let (location, map_handle, mut mesh, mut render_pipelines) = match chunks.get_mut(*entity)
+        {
+            Ok(chunk) => chunk,
+            // despawned before its map's array was ready
+            Err(_) => return false,
+        };
+        let map = match maps.get(map_handle) {
+            Some(map) => map,
+            None => return false,
+        };
+
+        let mut images: Vec<(u32, Handle<Texture>)> = map
+            .tileset_textures
+            .iter()
+            .map(|(first_gid, image)| (*first_gid, image.clone()))
+            .collect();
+        images.sort_by_key(|(first_gid, _)| *first_gid);
+        // a one layer texture gets a plain 2d view, which the array pipeline can't sample
+        if images.len() < 2 {
+            return false;
+        }
+        let built = built.entry(map_handle.id).or_default();
+        if built.images != images {
+            if images.iter().any(|(_, image)| textures.get(image).is_none()) {
+                return true;
+            }
+            built.array = stack_tileset_images(&images, &textures).map(|mut array| {
+                array.sampler.mag_filter = config.texture_filter;
+                array.sampler.min_filter = config.texture_filter;
+                array.sampler.mipmap_filter = config.texture_filter;
+                array.sampler.anisotropy_clamp = config.anisotropy;
+                let size = array.size;
+                let texture = textures.add(array);
+                if config.tile_padding() > 0 {
+                    mipmapped.insert(&texture, config.mipmap_levels, size);
+                }
+                arrays.add(TilesetArray { texture })
+            });
+            if built.array.is_none() {
+                warn!("bevy_tiled: tileset images of different sizes or formats can't be stacked into a TilesetArray, keeping one material per tileset");
+            }
+            built.images = images;
+        }
+
+        let array = match &built.array {
+            Some(array) => array.clone(),
+            None => return false,
+        };
+        let layer = match built
+            .images
+            .iter()
+            .position(|(first_gid, _)| *first_gid == location.tileset_guid)
+        {
+            Some(layer) => layer as u32,
+            None => return false,
+        };
+        // meshes unloaded by `max_loaded_chunks` get the layer once they are rebuilt
+        if let Some(chunk_mesh) = meshes.get(&*mesh) {
+            let array_mesh = with_tileset_layer(chunk_mesh.clone(), layer);
+            *mesh = meshes.add(array_mesh);
+        }
+        *render_pipelines = RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+            TILE_MAP_ARRAY_PIPELINE_HANDLE.typed(),
+        )]);
+        commands
+            .entity(*entity)
+            .insert(array)
+            .insert(TilesetArrayLayer(layer));
+        false
+    });
+}
+
+/// Adds the `Vertex_TilesetLayer` attribute that the tileset array pipeline samples with.
+pub fn with_tileset_layer(mut mesh: Mesh, layer: u32) -> Mesh {
+    let count = mesh.count_vertices();
+    mesh.set_attribute(
+        "Vertex_TilesetLayer",
+        VertexAttributeValues::Float(vec![layer as f32; count]),
+    );
+    mesh
+}
+
+// stacks same sized images into the layers of one texture, in order
+fn stack_tileset_images(
+    images: &[(u32, Handle<Texture>)],
+    textures: &Assets<Texture>,
+) -> Option<Texture> {
+    let first = textures.get(&images.first()?.1)?;
+    let mut data = Vec::with_capacity(first.data.len() * images.len());
+    for (_, image) in images.iter() {
+        let image = textures.get(image)?;
+        if image.size != first.size || image.format != first.format {
+            return None;
+        }
+        data.extend_from_slice(&image.data);
+    }
+    Some(Texture::new(
+        Extent3d::new(first.size.width, first.size.height, images.len() as u32),
+        TextureDimension::D2,
+        data,
+        first.format,
+    ))
+}
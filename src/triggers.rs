@@ -0,0 +1,204 @@
+use crate::Object;
+use bevy::{prelude::*, utils::HashSet};
+
+/// Adds `TriggerEnter` and `TriggerExit` events, fired when entities with a `TriggerTracker`
+/// move into or out of a `TriggerRegion`.
+#[derive(Default)]
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<TriggerEnter>()
+            .add_event::<TriggerExit>()
+            .add_system(detect_trigger_overlaps.system());
+    }
+}
+
+/// Inserted on objects whose type is `"trigger"`, or that are in an object layer named
+/// `"triggers"`. The layer must be visible in Tiled for its objects to be spawned.
+/// No physics sensor is added, build one from `shape` to use triggers with a physics engine.
+#[derive(Debug, Clone)]
+pub struct TriggerRegion {
+    pub shape: tiled::ObjectShape,
+    pub properties: tiled::Properties,
+    // converts the entity's local space to shape coordinates, tile objects are scaled
+    shape_scale: Vec2,
+}
+
+impl TriggerRegion {
+    pub const OBJECT_TYPE: &'static str = "trigger";
+    pub const LAYER_NAME: &'static str = "triggers";
+
+    pub(crate) fn new(object: &Object, tile_scale: Option<Vec3>) -> TriggerRegion {
+        TriggerRegion {
+            shape: object.shape.clone(),
+            properties: object.props.clone(),
+            shape_scale: tile_scale.map_or(Vec2::ONE, |scale| scale.truncate()),
+        }
+    }
+
+    /// Whether a point relative to the region's transform is inside its shape.
+    /// Polylines and points have no area and never contain anything.
+    pub fn contains(&self, local: Vec2) -> bool {
+        // shapes are in tiled coordinates, where y points down
+        let point = local * self.shape_scale * Vec2::new(1.0, -1.0);
+        match &self.shape {
+            tiled::ObjectShape::Rect { width, height } => {
+                point.x.abs() <= width / 2.0 && point.y.abs() <= height / 2.0
+            }
+            tiled::ObjectShape::Ellipse { width, height } => {
+                let normalized = point / Vec2::new(width / 2.0, height / 2.0);
+                normalized.length_squared() <= 1.0
+            }
+            tiled::ObjectShape::Polygon { points } => {
+                // even-odd rule
+                let mut inside = false;
+                let mut previous = match points.last() {
+                    Some(previous) => *previous,
+                    None => return false,
+                };
+                for &(x, y) in points.iter() {
+                    let (px, py) = previous;
+                    if (y > point.y) != (py > point.y)
+                        && point.x < (px - x) * (point.y - y) / (py - y) + x
+                    {
+                        inside = !inside;
+                    }
+                    previous = (x, y);
+                }
+                inside
+            }
+            tiled::ObjectShape::Polyline { .. } | tiled::ObjectShape::Point(_, _) => false,
+        }
+    }
+}
+
+/// Marks entities whose position is checked against trigger regions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TriggerTracker;
+
+/// Fired when a tracked entity moves into a trigger region.
+pub struct TriggerEnter {
+    pub region: Entity,
+    pub entity: Entity,
+}
+
+/// Fired when a tracked entity leaves a trigger region, or either of them is despawned.
+pub struct TriggerExit {
+    pub region: Entity,
+    pub entity: Entity,
+}
+
+pub fn detect_trigger_overlaps(
+    regions: Query<(Entity, &TriggerRegion, &GlobalTransform)>,
+    trackers: Query<(Entity, &GlobalTransform), With<TriggerTracker>>,
+    mut overlapping: Local<HashSet<(Entity, Entity)>>,
+    mut enter_events: EventWriter<TriggerEnter>,
+    mut exit_events: EventWriter<TriggerExit>,
+) {
+    let mut current = HashSet::default();
+    for (region_entity, region, region_transform) in regions.iter() {
+        let inverse = region_transform.compute_matrix().inverse();
+        for (entity, transform) in trackers.iter() {
+            let local = inverse.transform_point3(transform.translation).truncate();
+            if region.contains(local) {
+                current.insert((region_entity, entity));
+            }
+        }
+    }
+
+    for &(region, entity) in current.difference(&overlapping) {
+        enter_events.send(TriggerEnter { region, entity });
+    }
+    for &(region, entity) in overlapping.difference(&current) {
+        exit_events.send(TriggerExit { region, entity });
+    }
+    *overlapping = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::{Events, ManualEventReader};
+
+    fn pairs<T>(
+        world: &World,
+        reader: &mut ManualEventReader<T>,
+        pair: fn(&T) -> (Entity, Entity),
+    ) -> Vec<(Entity, Entity)>
+    where
+        T: Send + Sync + 'static,
+    {
+        let events = world.get_resource::<Events<T>>().unwrap();
+        reader.iter(events).map(pair).collect()
+    }
+
+    #[test]
+    fn trackers_enter_and_exit_regions() {
+        let mut world = World::default();
+        world.insert_resource(Events::<TriggerEnter>::default());
+        world.insert_resource(Events::<TriggerExit>::default());
+        let mut stage = SystemStage::parallel();
+        stage.add_system(detect_trigger_overlaps.system());
+        let mut enters = ManualEventReader::<TriggerEnter>::default();
+        let mut exits = ManualEventReader::<TriggerExit>::default();
+        let enter = |event: &TriggerEnter| (event.region, event.entity);
+        let exit = |event: &TriggerExit| (event.region, event.entity);
+
+        let region = world
+            .spawn()
+            .insert_bundle((
+                TriggerRegion {
+                    shape: tiled::ObjectShape::Rect {
+                        width: 32.0,
+                        height: 16.0,
+                    },
+                    properties: Default::default(),
+                    shape_scale: Vec2::ONE,
+                },
+                GlobalTransform::from_xyz(100.0, 0.0, 0.0),
+            ))
+            .id();
+        let tracker = world
+            .spawn()
+            .insert_bundle((TriggerTracker, GlobalTransform::identity()))
+            .id();
+
+        stage.run(&mut world);
+        assert!(pairs(&world, &mut enters, enter).is_empty());
+
+        world
+            .get_mut::<GlobalTransform>(tracker)
+            .unwrap()
+            .translation = Vec3::new(90.0, 4.0, 0.0);
+        stage.run(&mut world);
+        assert_eq!(pairs(&world, &mut enters, enter), vec![(region, tracker)]);
+
+        // staying inside doesn't enter again
+        world
+            .get_mut::<GlobalTransform>(tracker)
+            .unwrap()
+            .translation = Vec3::new(110.0, -4.0, 0.0);
+        stage.run(&mut world);
+        assert!(pairs(&world, &mut enters, enter).is_empty());
+        assert!(pairs(&world, &mut exits, exit).is_empty());
+
+        world
+            .get_mut::<GlobalTransform>(tracker)
+            .unwrap()
+            .translation = Vec3::new(100.0, 10.0, 0.0);
+        stage.run(&mut world);
+        assert_eq!(pairs(&world, &mut exits, exit), vec![(region, tracker)]);
+
+        // despawning a region exits the trackers inside it
+        world
+            .get_mut::<GlobalTransform>(tracker)
+            .unwrap()
+            .translation = Vec3::new(100.0, 0.0, 0.0);
+        stage.run(&mut world);
+        assert_eq!(pairs(&world, &mut enters, enter), vec![(region, tracker)]);
+        world.despawn(region);
+        stage.run(&mut world);
+        assert_eq!(pairs(&world, &mut exits, exit), vec![(region, tracker)]);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{loader::TiledMapLoader, Map};
+use std::{fmt, path::Path};
+
+/// A problem with a map's content found by `Map::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapIssue {
+    /// A tile of a tile layer uses a gid that is in no tileset.
+    UnknownTileGid {
+        layer: String,
+        x: u32,
+        y: u32,
+        gid: u32,
+    },
+    /// A tile object uses a gid that is in no tileset.
+    UnknownObjectGid {
+        layer: String,
+        object_id: u32,
+        gid: u32,
+    },
+    /// A tileset's image file doesn't exist, see `Map::validate_image_files`.
+    MissingImageFile { tileset: String, path: String },
+    /// The map uses a feature of Tiled that is loaded but not shown the way Tiled shows it.
+    Unsupported { layer: String, feature: String },
+    /// A tile layer without tiles or an object layer without objects.
+    EmptyLayer { layer: String },
+}
+
+impl MapIssue {
+    /// Whether the issue breaks the map, rather than being a warning.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            MapIssue::UnknownTileGid { .. }
+                | MapIssue::UnknownObjectGid { .. }
+                | MapIssue::MissingImageFile { .. }
+        )
+    }
+}
+
+impl fmt::Display for MapIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapIssue::UnknownTileGid { layer, x, y, gid } => write!(
+                f,
+                "tile {} at {}, {} of layer \"{}\" is in no tileset",
+                gid, x, y, layer
+            ),
+            MapIssue::UnknownObjectGid {
+                layer,
+                object_id,
+                gid,
+            } => write!(
+                f,
+                "tile {} of object {} in layer \"{}\" is in no tileset",
+                gid, object_id, layer
+            ),
+            MapIssue::MissingImageFile { tileset, path } => {
+                write!(f, "image {} of tileset \"{}\" not found", path, tileset)
+            }
+            MapIssue::Unsupported { layer, feature } => {
+                write!(f, "layer \"{}\" uses unsupported {}", layer, feature)
+            }
+            MapIssue::EmptyLayer { layer } => write!(f, "layer \"{}\" is empty", layer),
+        }
+    }
+}
+
+impl Map {
+    /// Checks the map's content for gids in no tileset, features that aren't supported and
+    /// empty layers, e.g. to check levels in tests before shipping them. Whatever the map
+    /// failed to load on is an error of its loading instead, see `TiledMapError`.
+    pub fn validate(&self) -> Vec<MapIssue> {
+        let mut issues = Vec::new();
+
+        for layer in self.map.layers.iter() {
+            let rows = match &layer.tiles {
+                tiled::LayerData::Finite(rows) => rows,
+                tiled::LayerData::Infinite(_) => continue,
+            };
+            let mut empty = true;
+            for (y, row) in rows.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate().filter(|(_, tile)| tile.gid != 0) {
+                    empty = false;
+                    if !self.is_known_gid(tile.gid) {
+                        issues.push(MapIssue::UnknownTileGid {
+                            layer: layer.name.clone(),
+                            x: x as u32,
+                            y: y as u32,
+                            gid: tile.gid,
+                        });
+                    }
+                }
+            }
+            if empty {
+                issues.push(MapIssue::EmptyLayer {
+                    layer: layer.name.clone(),
+                });
+            }
+            if layer.offset_x != 0.0 || layer.offset_y != 0.0 {
+                issues.push(MapIssue::Unsupported {
+                    layer: layer.name.clone(),
+                    feature: "layer offset".to_string(),
+                });
+            }
+            // tile layers are tinted with `LayerTints` instead
+            if layer.opacity < 1.0 {
+                issues.push(MapIssue::Unsupported {
+                    layer: layer.name.clone(),
+                    feature: "tile layer opacity".to_string(),
+                });
+            }
+        }
+
+        for group in self.map.object_groups.iter() {
+            if group.objects.is_empty() {
+                issues.push(MapIssue::EmptyLayer {
+                    layer: group.name.clone(),
+                });
+            }
+            for object in group.objects.iter() {
+                let gid = TiledMapLoader::remove_tile_flags(object.gid);
+                if gid != 0 && !self.is_known_gid(gid) {
+                    issues.push(MapIssue::UnknownObjectGid {
+                        layer: group.name.clone(),
+                        object_id: object.id,
+                        gid,
+                    });
+                }
+                if object.rotation != 0.0 {
+                    issues.push(MapIssue::Unsupported {
+                        layer: group.name.clone(),
+                        feature: format!("rotation of object {}", object.id),
+                    });
+                }
+            }
+        }
+
+        for image_layer in self.map.image_layers.iter() {
+            issues.push(MapIssue::Unsupported {
+                layer: image_layer.name.clone(),
+                feature: "image layer".to_string(),
+            });
+        }
+
+        issues
+    }
+
+    /// Checks that the images of the map's tilesets exist, relative to the folder the asset
+    /// server reads from, e.g. `Path::new("assets")`. Maps loaded with `Map::try_from_bytes`
+    /// from a file system path have their image paths resolved already, so pass an empty path.
+    pub fn validate_image_files(&self, asset_folder: &Path) -> Vec<MapIssue> {
+        self.map
+            .tilesets
+            .iter()
+            .filter(|tileset| !tileset.images.is_empty())
+            .filter_map(|tileset| {
+                let path = self.tileset_image_path(tileset);
+                if asset_folder.join(&path).is_file() {
+                    return None;
+                }
+                Some(MapIssue::MissingImageFile {
+                    tileset: tileset.name.clone(),
+                    path: path.display().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    // a gid is known if it is one of the tiles of its tileset. `Option::is_some_and` and
+    // `is_none_or` need a newer Rust than the crate supports
+    #[allow(clippy::unnecessary_map_or)]
+    fn is_known_gid(&self, gid: u32) -> bool {
+        self.map.get_tileset_by_gid(gid).map_or(false, |tileset| {
+            tileset
+                .tilecount
+                .map_or(true, |count| gid < tileset.first_gid + count)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TiledConfig;
+
+    #[test]
+    fn reports_unknown_gids_and_empty_layers() {
+        let path = Path::new("tests/fixtures/validate.tmx");
+        let bytes = std::fs::read(path).unwrap();
+        let map = Map::try_from_bytes(path, bytes, &TiledConfig::default()).unwrap();
+
+        let issues = map.validate();
+        assert_eq!(
+            issues,
+            vec![
+                MapIssue::UnknownTileGid {
+                    layer: "Ground".to_string(),
+                    x: 2,
+                    y: 1,
+                    gid: 9,
+                },
+                MapIssue::EmptyLayer {
+                    layer: "Empty".to_string(),
+                },
+                MapIssue::UnknownObjectGid {
+                    layer: "Objects".to_string(),
+                    object_id: 2,
+                    gid: 12,
+                },
+            ]
+        );
+        assert!(issues.iter().any(MapIssue::is_error));
+        assert!(!MapIssue::EmptyLayer {
+            layer: "Empty".to_string()
+        }
+        .is_error());
+    }
+}